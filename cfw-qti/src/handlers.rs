@@ -1,3 +1,6 @@
+use qti_lib::html::HtmlRenderer;
+use qti_lib::math::MathTarget;
+use qti_lib::media::MediaMode;
 use qti_lib::{Exporter, Parser};
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
@@ -10,8 +13,31 @@ pub struct GenerateRequest {
     canvas: bool,
     #[serde(default)]
     skip_validation: bool,
+    /// Keep pasted `data:` images inline rather than packaging them as files
+    #[serde(default)]
+    inline_media: bool,
+    /// Derive identifiers from content so re-imports update existing questions
+    #[serde(default)]
+    stable_ids: bool,
+    /// `mathjax` (default), `canvas` or `mathml`
+    #[serde(default)]
+    math: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewRequest {
+    content: String,
+    /// Add an answer key page after the questions
+    #[serde(default)]
+    answer_key: bool,
 }
 
+/// Quiz text is rendered as HTML, so the preview may only run MathJax: any
+/// script in the quiz itself is blocked
+const PREVIEW_POLICY: &str = "default-src 'none'; script-src https://cdn.jsdelivr.net; \
+    style-src 'unsafe-inline'; font-src https://cdn.jsdelivr.net data:; img-src data: https:; \
+    media-src data: https:; base-uri 'none'; form-action 'none'";
+
 #[derive(Serialize)]
 pub struct ErrorResponse {
     error: String,
@@ -46,6 +72,17 @@ pub async fn generate_qti(mut req: Request, _ctx: RouteContext<()>) -> Result<Re
         .map(|r| r.with_status(400));
     }
 
+    let math = match request_data.math.as_deref().map(str::parse::<MathTarget>) {
+        None => MathTarget::default(),
+        Some(Ok(math)) => math,
+        Some(Err(e)) => {
+            return Response::from_json(&ErrorResponse {
+                error: e.to_string(),
+            })
+            .map(|r| r.with_status(400));
+        }
+    };
+
     let parser = Parser::new();
     let mut assessment = match parser.parse(&request_data.content) {
         Ok(assessment) => assessment,
@@ -66,11 +103,16 @@ pub async fn generate_qti(mut req: Request, _ctx: RouteContext<()>) -> Result<Re
     if assessment.identifier.is_empty() {
         assessment.identifier = filename.clone();
     }
+    if request_data.stable_ids {
+        qti_lib::ids::assign_stable_ids(&mut assessment);
+    }
 
     let zip_data = match generate_zip(
         &assessment,
         request_data.canvas,
         request_data.skip_validation,
+        request_data.inline_media,
+        math,
     ) {
         Ok(data) => data,
         Err(e) => {
@@ -93,10 +135,63 @@ pub async fn generate_qti(mut req: Request, _ctx: RouteContext<()>) -> Result<Re
     Ok(Response::from_bytes(zip_data)?.with_headers(headers))
 }
 
+/// Printable HTML of the quiz, for previewing and paper copies
+pub async fn preview_html(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
+    // A JSON content type keeps other sites' plain form posts out
+    let content_type = req.headers().get("Content-Type")?.unwrap_or_default();
+    if !content_type.starts_with("application/json") {
+        return Response::from_json(&ErrorResponse {
+            error: "Expected a JSON request body".to_string(),
+        })
+        .map(|r| r.with_status(415));
+    }
+
+    let request_data: PreviewRequest = match req.json().await {
+        Ok(data) => data,
+        Err(e) => {
+            return Response::from_json(&ErrorResponse {
+                error: format!("Invalid request format: {}", e),
+            })
+            .map(|r| r.with_status(400));
+        }
+    };
+
+    let assessment = match Parser::new().parse(&request_data.content) {
+        Ok(assessment) => assessment,
+        Err(e) => {
+            return Response::from_json(&ErrorResponse {
+                error: format!("Parse error: {}", e),
+            })
+            .map(|r| r.with_status(400));
+        }
+    };
+
+    let mut renderer = HtmlRenderer::new();
+    if request_data.answer_key {
+        renderer = renderer.with_answer_key();
+    }
+    let headers = Headers::new();
+    headers.set("Content-Type", "text/html; charset=utf-8")?;
+    headers.set("Content-Security-Policy", PREVIEW_POLICY)?;
+    Ok(Response::ok(with_policy_meta(&renderer.render(&assessment)))?.with_headers(headers))
+}
+
+/// Repeat the preview policy in the page itself: the browser writes the page
+/// into a new window, where response headers no longer apply
+fn with_policy_meta(html: &str) -> String {
+    let meta = format!(
+        "<head>\n<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">",
+        PREVIEW_POLICY
+    );
+    html.replacen("<head>", &meta, 1)
+}
+
 fn generate_zip(
     assessment: &qti_lib::types::Assessment,
     canvas: bool,
     skip_validation: bool,
+    inline_media: bool,
+    math: MathTarget,
 ) -> std::result::Result<Vec<u8>, String> {
     let mut buffer = Cursor::new(Vec::new());
 
@@ -110,6 +205,10 @@ fn generate_zip(
         exporter = exporter.skip_validation();
     }
 
+    exporter = exporter.with_math_target(math);
+
+    exporter = exporter.media_mode(media_mode(inline_media));
+
     exporter
         .export_to_writer(assessment, &mut buffer)
         .map_err(|e| format!("Export failed: {}", e))?;
@@ -117,6 +216,16 @@ fn generate_zip(
     Ok(buffer.into_inner())
 }
 
+/// The worker cannot read local files, so images only arrive as data URIs:
+/// they either stay inline or are extracted into packaged files
+fn media_mode(inline_media: bool) -> MediaMode {
+    if inline_media {
+        MediaMode::Inline
+    } else {
+        MediaMode::Extract
+    }
+}
+
 fn sanitize_filename(name: &str) -> String {
     let sanitized = name
         .chars()
@@ -146,4 +255,41 @@ mod tests {
         assert_eq!(sanitize_filename("Quiz #1: Test!"), "Quiz_-1-_Test");
         assert_eq!(sanitize_filename("  test  "), "test");
     }
+
+    #[test]
+    fn test_media_mode() {
+        let assessment = Parser::new()
+            .parse("1. Which shape? ![dot](data:image/gif;base64,R0lG)\n* Dot")
+            .unwrap();
+        // Zip entry names are stored uncompressed
+        let packaged = |inline_media: bool| {
+            let zip = generate_zip(
+                &assessment,
+                false,
+                false,
+                inline_media,
+                MathTarget::default(),
+            )
+            .unwrap();
+            zip.windows(15).any(|name| name == b"media/image.gif")
+        };
+        assert_eq!(media_mode(true), MediaMode::Inline);
+        assert!(!packaged(true));
+        assert!(packaged(false));
+    }
+
+    #[test]
+    fn test_preview_policy() {
+        let assessment = Parser::new()
+            .parse("1. <script>alert(1)</script> What is \\(x^2\\)?\n* x")
+            .unwrap();
+        let html = with_policy_meta(&HtmlRenderer::new().render(&assessment));
+        let meta = html.find("http-equiv=\"Content-Security-Policy\"").unwrap();
+        assert!(meta < html.find("<script").unwrap());
+        let scripts = PREVIEW_POLICY
+            .split(';')
+            .find(|directive| directive.trim().starts_with("script-src"))
+            .unwrap();
+        assert_eq!(scripts.trim(), "script-src https://cdn.jsdelivr.net");
+    }
 }
@@ -176,6 +176,18 @@ pub const INDEX_HTML: &str = r#"<!DOCTYPE html>
             transform: translateY(0);
         }
 
+        .actions {
+            display: flex;
+            gap: 15px;
+        }
+
+        button.secondary {
+            background: white;
+            color: #667eea;
+            border: 2px solid #667eea;
+            width: auto;
+        }
+
         button:disabled {
             background: #ccc;
             cursor: not-allowed;
@@ -300,9 +312,22 @@ c) 3
                         <label for="skipValidation" style="margin-bottom: 0;">Skip Validation</label>
                         <span class="tooltip" data-tooltip="Skips QTI 1.2 schema validation for faster generation">?</span>
                     </div>
+                    <div class="checkbox-group">
+                        <input type="checkbox" id="inlineMedia" name="inlineMedia">
+                        <label for="inlineMedia" style="margin-bottom: 0;">Inline Images</label>
+                        <span class="tooltip" data-tooltip="Keeps pasted data: URI images inside the question text instead of packaging them as files">?</span>
+                    </div>
+                    <div class="checkbox-group">
+                        <input type="checkbox" id="answerKey" name="answerKey">
+                        <label for="answerKey" style="margin-bottom: 0;">Answer Key</label>
+                        <span class="tooltip" data-tooltip="Adds an answer key page with solutions and feedback to the preview">?</span>
+                    </div>
                 </div>
 
-                <button id="generateBtn" onclick="generateQTI()">Generate QTI Package</button>
+                <div class="actions">
+                    <button id="generateBtn" onclick="generateQTI()">Generate QTI Package</button>
+                    <button id="previewBtn" class="secondary" onclick="previewQuiz()">Preview</button>
+                </div>
 
                 <div id="status"></div>
             </div>
@@ -318,9 +343,13 @@ c) 3
                         <li><code>[*]</code> - Correct choice (multiple answer)</li>
                         <li><code>[ ]</code> - Incorrect choice (multiple answer)</li>
                         <li><code>* answer</code> - Acceptable answer (short answer, multiple allowed)</li>
+                        <li><code>* /colou?r/</code> - Acceptable answer as a regular expression</li>
                         <li><code>= num ± margin</code> - Numerical answer with margin</li>
                         <li><code>___</code> - Essay question (3+ underscores)</li>
                         <li><code>^^^</code> - File upload (3+ carets)</li>
+                        <li><code>{*a|b}</code> - Inline dropdown in the question text</li>
+                        <li><code>formula: d / t ± tol</code> + <code>var d: 1 .. 10</code> - Formula question with random variables</li>
+                        <li><code>$x^2$</code> or <code>$$\int f$$</code> - Inline or display LaTeX math</li>
                     </ul>
                 </div>
 
@@ -329,6 +358,14 @@ c) 3
                     <ul>
                         <li><code>title: Quiz Title</code> - Assessment title (at the top)</li>
                         <li><code>feedback: text</code> - Feedback after a question</li>
+                        <li><code>passage: Title</code> - Shared passage shown before the following questions, until <code>end passage</code></li>
+                        <li><code>group: Title, pick 2, 5 points</code> - Draw 2 of the following questions at random, until <code>end group</code></li>
+                        <li><code>text: content</code> - Ungraded text-only item</li>
+                        <li><code>likert</code> - Ungraded rating scale (or <code>likert: Low | Mid | High</code>)</li>
+                        <li><code>ungraded</code> - Record a question's response without scoring it</li>
+                        <li><code>id: name</code> - Fixed question identifier, kept across rebuilds</li>
+                        <li><code>quiz_type: survey</code> - Make the whole quiz an ungraded survey</li>
+                        <li><code>attempts: 3</code>, <code>time_limit: 45</code>, <code>access_code: x</code> - Quiz settings (before question 1)</li>
                     </ul>
                 </div>
 
@@ -359,6 +396,7 @@ feedback: Paris is the capital and largest city of France.
             const canvasCheckbox = document.getElementById('canvas');
             const canvas = canvasCheckbox ? canvasCheckbox.checked : false;
             const skipValidation = document.getElementById('skipValidation').checked;
+            const inlineMedia = document.getElementById('inlineMedia').checked;
             const statusDiv = document.getElementById('status');
             const generateBtn = document.getElementById('generateBtn');
 
@@ -381,7 +419,8 @@ feedback: Paris is the capital and largest city of France.
                     },
                     body: JSON.stringify({
                         content: quizText,
-                        skip_validation: skipValidation
+                        skip_validation: skipValidation,
+                        inline_media: inlineMedia
                     })
                 });
 
@@ -422,6 +461,56 @@ feedback: Paris is the capital and largest city of France.
             }
         }
 
+        async function previewQuiz() {
+            const quizText = document.getElementById('quizText').value.trim();
+            const answerKey = document.getElementById('answerKey').checked;
+            const statusDiv = document.getElementById('status');
+
+            if (!quizText) {
+                statusDiv.className = 'error';
+                statusDiv.textContent = 'Please enter quiz text';
+                return;
+            }
+
+            // Open the window now; browsers block pop-ups opened after an await
+            const preview = window.open('', '_blank');
+            if (!preview) {
+                statusDiv.className = 'error';
+                statusDiv.textContent = '✗ Error: the preview window was blocked; allow pop-ups for this site';
+                return;
+            }
+            // The page comes from quiz text; keep it from reaching back into this one
+            preview.opener = null;
+            try {
+                const response = await fetch('/preview', {
+                    method: 'POST',
+                    headers: {
+                        'Content-Type': 'application/json',
+                    },
+                    body: JSON.stringify({
+                        content: quizText,
+                        answer_key: answerKey
+                    })
+                });
+
+                if (!response.ok) {
+                    const errorData = await response.json();
+                    throw new Error(errorData.error || 'Failed to render preview');
+                }
+
+                const html = await response.text();
+                preview.document.open();
+                preview.document.write(html);
+                preview.document.close();
+                statusDiv.className = '';
+                statusDiv.textContent = '';
+            } catch (error) {
+                preview.close();
+                statusDiv.className = 'error';
+                statusDiv.textContent = '✗ Error: ' + error.message;
+            }
+        }
+
         // Allow Ctrl/Cmd+Enter to generate
         document.getElementById('quizText').addEventListener('keydown', function(e) {
             if ((e.ctrlKey || e.metaKey) && e.key === 'Enter') {
@@ -16,6 +16,9 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
         .post_async("/generate", |req, ctx| async move {
             handlers::generate_qti(req, ctx).await
         })
+        .post_async("/preview", |req, ctx| async move {
+            handlers::preview_html(req, ctx).await
+        })
         .get_async("/ai", |req, ctx| async move {
             ai_handlers::serve_ai_html(req, ctx).await
         })
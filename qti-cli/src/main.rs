@@ -1,8 +1,17 @@
 use anyhow::Result;
 use clap::{Parser as ClapParser, Subcommand};
-use qti_lib::{Exporter, Generator, Parser};
+use qti_lib::analysis;
+use qti_lib::cartridge::{Cartridge, Module, ModuleItem, WebContent};
+use qti_lib::grading::Grader;
+use qti_lib::lint;
+use qti_lib::math::MathTarget;
+use qti_lib::media::MediaMode;
+use qti_lib::report::Severity;
+use qti_lib::types::Assessment;
+use qti_lib::validator::Validator;
+use qti_lib::{Exporter, Parser};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(ClapParser, Debug)]
 #[command(name = "qti")]
@@ -26,30 +35,160 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
 
+        #[command(flatten)]
+        options: GenerateOptions,
+    },
+
+    /// Package quizzes and web pages as an IMS Common Cartridge course
+    ///
+    /// Files from the same directory form one module, in the order given.
+    /// `.txt` files are parsed as quizzes; anything else becomes a page.
+    Cartridge {
+        /// Quiz text files and web content files
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output file path
+        #[arg(short, long, default_value = "course.imscc")]
+        output: PathBuf,
+
+        /// Course title
+        #[arg(short, long, default_value = "Course")]
+        title: String,
+
         /// Include Canvas-specific extensions
         #[arg(long)]
         canvas: bool,
 
-        /// Output XML only (no zip packaging)
-        #[arg(long)]
-        xml_only: bool,
-
         /// Skip validation
         #[arg(long)]
         skip_validation: bool,
+
+        /// How `$...$` math is written: mathjax, canvas (equation images) or mathml
+        #[arg(long, default_value = "mathjax")]
+        math: MathTarget,
+
+        /// Derive identifiers from content so rebuilds keep the same idents
+        #[arg(long)]
+        stable_ids: bool,
+
+        /// Leave out short-answer patterns QTI cannot express (they score 0) instead of failing
+        #[arg(long)]
+        allow_unsupported_patterns: bool,
     },
 
-    /// Validate an existing QTI XML file
+    /// Validate an existing QTI XML file or zip package
     Validate {
-        /// XML file to validate
+        /// XML file or zip package to validate
         #[arg(short, long)]
         file: PathBuf,
+
+        /// Validate against the QTI 1.2 DTD (the default when the file declares a DOCTYPE)
+        #[arg(long)]
+        dtd: bool,
+
+        /// Fail when the report contains warnings
+        #[arg(long)]
+        warnings_as_errors: bool,
+
+        /// Also warn about content Canvas drops or mangles on import
+        #[arg(long)]
+        canvas: bool,
+    },
+
+    /// Check a quiz text file for content problems the schema cannot catch
+    Lint {
+        /// Input text file path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Print the issues as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Grade a CSV of learner responses, one row per learner and one column per question
+    Grade {
+        /// Quiz text file the responses answer
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Responses CSV with a header row
+        #[arg(short, long)]
+        responses: PathBuf,
+
+        /// Results CSV path
+        #[arg(short, long, default_value = "results.csv")]
+        output: PathBuf,
+
+        /// Header of the column naming each learner (default: the first column)
+        #[arg(long)]
+        student_column: Option<String>,
+    },
+
+    /// Item analysis of graded responses: difficulty, discrimination, distractors and reliability
+    Stats {
+        /// Quiz text file the responses answer
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Responses CSV with a header row, as for `grade`
+        #[arg(short, long)]
+        responses: PathBuf,
+
+        /// Header of the column naming each learner (default: the first column)
+        #[arg(long)]
+        student_column: Option<String>,
+
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show example input format
     Example,
 }
 
+/// Output options for `generate`
+#[derive(clap::Args, Debug)]
+struct GenerateOptions {
+    /// Include Canvas-specific extensions
+    #[arg(long)]
+    canvas: bool,
+
+    /// Output XML only (no zip packaging)
+    #[arg(long)]
+    xml_only: bool,
+
+    /// Skip validation
+    #[arg(long)]
+    skip_validation: bool,
+
+    /// How `$...$` math is written: mathjax, canvas (equation images) or mathml
+    #[arg(long, default_value = "mathjax")]
+    math: MathTarget,
+
+    /// Derive identifiers from content so rebuilds keep the same idents
+    #[arg(long)]
+    stable_ids: bool,
+
+    /// Embed referenced media as base64 data URIs instead of packaged files
+    #[arg(long)]
+    inline_media: bool,
+
+    /// Write a QTI-Lite XML file, leaving out questions Lite cannot represent
+    #[arg(long)]
+    lite: bool,
+
+    /// Fail if any question's answer key does not score as intended (zip packages)
+    #[arg(long)]
+    check_answers: bool,
+
+    /// Leave out short-answer patterns QTI cannot express (they score 0) instead of failing
+    #[arg(long)]
+    allow_unsupported_patterns: bool,
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
@@ -57,14 +196,63 @@ fn main() -> Result<()> {
         Commands::Generate {
             input,
             output,
+            options,
+        } => {
+            generate_qti(input, output, options)?;
+        }
+        Commands::Cartridge {
+            inputs,
+            output,
+            title,
             canvas,
-            xml_only,
             skip_validation,
+            math,
+            stable_ids,
+            allow_unsupported_patterns,
+        } => {
+            let mut exporter = if canvas {
+                Exporter::new().with_canvas_extensions()
+            } else {
+                Exporter::new()
+            };
+            exporter = exporter.with_math_target(math);
+            if skip_validation {
+                exporter = exporter.skip_validation();
+            }
+            if stable_ids {
+                exporter = exporter.with_stable_ids();
+            }
+            if allow_unsupported_patterns {
+                exporter = exporter.with_unsupported_patterns();
+            }
+            generate_cartridge(inputs, output, title, exporter)?;
+        }
+        Commands::Validate {
+            file,
+            dtd,
+            warnings_as_errors,
+            canvas,
         } => {
-            generate_qti(input, output, canvas, xml_only, skip_validation)?;
+            validate_file(file, dtd, warnings_as_errors, canvas)?;
+        }
+        Commands::Lint { input, json } => {
+            lint_file(input, json)?;
         }
-        Commands::Validate { file } => {
-            validate_file(file)?;
+        Commands::Grade {
+            input,
+            responses,
+            output,
+            student_column,
+        } => {
+            grade_responses(input, responses, output, student_column)?;
+        }
+        Commands::Stats {
+            input,
+            responses,
+            student_column,
+            json,
+        } => {
+            print_stats(input, responses, student_column, json)?;
         }
         Commands::Example => {
             show_example();
@@ -74,50 +262,74 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn generate_qti(
-    input: PathBuf,
-    output: Option<PathBuf>,
-    canvas: bool,
-    xml_only: bool,
-    skip_validation: bool,
-) -> Result<()> {
-    println!("Reading input file: {}", input.display());
+fn generate_qti(input: PathBuf, output: Option<PathBuf>, options: GenerateOptions) -> Result<()> {
+    let GenerateOptions {
+        canvas,
+        xml_only,
+        skip_validation,
+        math,
+        stable_ids,
+        inline_media,
+        lite,
+        check_answers,
+        allow_unsupported_patterns,
+    } = options;
 
-    let content = fs::read_to_string(&input)?;
+    println!("Reading input file: {}", input.display());
 
     let parser = Parser::new();
-    let mut assessment = parser.parse(&content)?;
+    let mut assessment = parser.parse_file(&input)?;
 
-    let stem = input
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("assessment");
-    assessment.identifier = stem.to_string();
+    name_after_file(&mut assessment, &input);
+    if stable_ids {
+        qti_lib::ids::assign_stable_ids(&mut assessment);
+    }
 
     println!("Parsed {} questions", assessment.questions.len());
+    if !assessment.media.is_empty() {
+        let action = if inline_media { "Inlined" } else { "Packaged" };
+        println!("{} {} media files", action, assessment.media.len());
+    }
 
-    if xml_only {
-        let generator = if canvas {
-            Generator::new().with_canvas_extensions()
-        } else {
-            Generator::new()
-        };
+    let mut exporter = if canvas {
+        Exporter::new().with_canvas_extensions()
+    } else {
+        Exporter::new()
+    };
+    exporter = exporter.with_math_target(math);
+    if skip_validation {
+        exporter = exporter.skip_validation();
+    }
+    if inline_media {
+        exporter = exporter.media_mode(MediaMode::Inline);
+    }
+    if allow_unsupported_patterns {
+        exporter = exporter.with_unsupported_patterns();
+    }
 
-        let xml = generator.generate(&assessment)?;
+    if lite {
+        let export = exporter.export_lite(&assessment)?;
+        for issue in &export.issues {
+            let action = if issue.omitted { "omitted" } else { "note" };
+            println!(
+                "Question {} {}: {}",
+                issue.question_id, action, issue.reason
+            );
+        }
+
+        let output_path = output.unwrap_or_else(|| input.with_extension("xml"));
+        fs::write(&output_path, export.xml)?;
+        println!("Generated QTI-Lite XML: {}", output_path.display());
+    } else if xml_only {
+        let xml = exporter.export_to_xml(&assessment)?;
 
         let output_path = output.unwrap_or_else(|| input.with_extension("xml"));
 
         fs::write(&output_path, xml)?;
         println!("Generated QTI XML: {}", output_path.display());
     } else {
-        let mut exporter = if canvas {
-            Exporter::new().with_canvas_extensions()
-        } else {
-            Exporter::new()
-        };
-
-        if skip_validation {
-            exporter = exporter.skip_validation();
+        if check_answers {
+            exporter = exporter.with_answer_key_check();
         }
 
         let output_path = output.unwrap_or_else(|| input.with_extension("zip"));
@@ -129,21 +341,244 @@ fn generate_qti(
     Ok(())
 }
 
-fn validate_file(file: PathBuf) -> Result<()> {
+/// Use the file name as the assessment ident unless the quiz declares an `id:`
+/// or the name is not a valid ident (such as `week 1 quiz`)
+fn name_after_file(assessment: &mut Assessment, input: &Path) {
+    let stem = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    if qti_lib::ids::is_generated(&assessment.identifier, "assessment")
+        && qti_lib::ids::is_valid_ident(stem)
+    {
+        assessment.identifier = stem.to_string();
+    }
+}
+
+fn generate_cartridge(
+    inputs: Vec<PathBuf>,
+    output: PathBuf,
+    title: String,
+    exporter: Exporter,
+) -> Result<()> {
+    let parser = Parser::new();
+    let mut modules: Vec<(PathBuf, Module)> = Vec::new();
+
+    for input in inputs {
+        let directory = input.parent().map(PathBuf::from).unwrap_or_default();
+        let stem = input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("item")
+            .to_string();
+
+        let index = match modules.iter().position(|(dir, _)| *dir == directory) {
+            Some(index) => index,
+            None => {
+                let module_title = directory
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or(&title)
+                    .to_string();
+                modules.push((directory.clone(), Module::new(module_title)));
+                modules.len() - 1
+            }
+        };
+        let module = &mut modules[index].1;
+
+        if input.extension().is_some_and(|ext| ext == "txt") {
+            let mut assessment = parser.parse_file(&input)?;
+            name_after_file(&mut assessment, &input);
+            println!(
+                "Parsed {} ({} questions)",
+                input.display(),
+                assessment.questions.len()
+            );
+            module.items.push(ModuleItem::Assessment(assessment));
+        } else {
+            let filename = input
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("item")
+                .to_string();
+            let content = fs::read(&input)?;
+            module.items.push(ModuleItem::WebContent(WebContent::new(
+                stem, filename, content,
+            )));
+        }
+    }
+
+    let mut cartridge = Cartridge::new(title);
+    cartridge.modules = modules.into_iter().map(|(_, module)| module).collect();
+
+    exporter.export_cartridge_to_file(&cartridge, &output)?;
+    println!(
+        "Generated Common Cartridge with {} modules: {}",
+        cartridge.modules.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+fn validate_file(file: PathBuf, dtd: bool, warnings_as_errors: bool, canvas: bool) -> Result<()> {
     println!("Validating file: {}", file.display());
 
-    let content = fs::read_to_string(&file)?;
+    let content = fs::read(&file)?;
+    let package = content.starts_with(b"PK\x03\x04");
+
+    let dtd = dtd || (!package && String::from_utf8_lossy(&content).contains("<!DOCTYPE"));
+    let mut validator = if dtd {
+        Validator::with_dtd()
+    } else {
+        Validator::new()
+    };
+    if warnings_as_errors {
+        validator = validator.with_warnings_as_errors();
+    }
+    if canvas {
+        validator = validator.with_canvas_rules();
+    }
+    let report = if package {
+        validator.report_package(std::io::Cursor::new(content))?
+    } else {
+        validator.full_report_xml(std::str::from_utf8(&content)?)?
+    };
+    println!("{}", report);
+
+    if !report.is_valid() {
+        anyhow::bail!("{} is not valid QTI", file.display());
+    }
+    println!(
+        "✓ Valid QTI {} ({})",
+        if package { "package" } else { "XML" },
+        if dtd { "QTI 1.2 DTD" } else { "QTI 1.2 XSD" }
+    );
+
+    Ok(())
+}
 
-    let validator = qti_lib::validator::Validator::new();
-    validator.validate_xml(&content)?;
+fn lint_file(input: PathBuf, json: bool) -> Result<()> {
+    let source = fs::read_to_string(&input)?;
+    let mut assessment = Parser::new().parse_file(&input)?;
+    // Content-derived idents keep the report the same from run to run
+    name_after_file(&mut assessment, &input);
+    qti_lib::ids::assign_stable_ids(&mut assessment);
+
+    let mut issues = lint::lint_numbering(&source);
+    issues.extend(lint::lint(&assessment));
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&issues)?);
+    } else {
+        for issue in &issues {
+            println!("{}", issue);
+        }
+        println!("{} error(s), {} warning(s)", errors, issues.len() - errors);
+    }
+
+    if errors > 0 {
+        anyhow::bail!("{} has lint errors", input.display());
+    }
+    Ok(())
+}
+
+fn grade_responses(
+    input: PathBuf,
+    responses: PathBuf,
+    output: PathBuf,
+    student_column: Option<String>,
+) -> Result<()> {
+    let assessment = Parser::new().parse_file(&input)?;
+    let mut grader = Grader::new(&assessment);
+    if let Some(column) = student_column {
+        grader = grader.with_student_column(column);
+    }
+    let gradebook = grader.grade_csv(fs::File::open(&responses)?)?;
+    for label in &gradebook.missing {
+        eprintln!("Warning: no column answers {}; it scores 0", label);
+    }
+    gradebook.write_csv(fs::File::create(&output)?)?;
+
+    let manual = gradebook
+        .students
+        .iter()
+        .filter(|student| student.needs_manual_grading())
+        .count();
+    println!(
+        "Graded {} learners on {} questions",
+        gradebook.students.len(),
+        gradebook.questions.len()
+    );
+    if manual > 0 {
+        println!("{} learners have answers to grade by hand", manual);
+    }
+    println!("Wrote results: {}", output.display());
+    Ok(())
+}
 
-    println!("✓ Valid QTI XML");
+fn print_stats(
+    input: PathBuf,
+    responses: PathBuf,
+    student_column: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let assessment = Parser::new().parse_file(&input)?;
+    let mut grader = Grader::new(&assessment);
+    if let Some(column) = student_column {
+        grader = grader.with_student_column(column);
+    }
+    let gradebook = grader.grade_csv(fs::File::open(&responses)?)?;
+    for label in &gradebook.missing {
+        eprintln!("Warning: no column answers {}; it scores 0", label);
+    }
+    let stats = analysis::analyze(&assessment, &gradebook);
 
-    let element = xmltree::Element::parse(content.as_bytes())?;
-    validator.validate_completeness(&element)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
 
-    println!("✓ All required elements present");
+    let format = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.2}", v));
+    println!("{:<10} {:>6} {:>6}", "Question", "p", "r_pb");
+    for question in &stats.questions {
+        println!(
+            "{:<10} {:>6.2} {:>6}",
+            question.label,
+            question.difficulty,
+            format(question.discrimination)
+        );
+        for choice in &question.choices {
+            let marker = if choice.correct { "*" } else { " " };
+            println!(
+                "  {} {:<30} {:>4} {:>5.0}%",
+                marker,
+                choice.text,
+                choice.count,
+                choice.proportion * 100.0
+            );
+        }
+        for weak in &question.weak_distractors {
+            println!("  weak distractor: {}", weak);
+        }
+    }
 
+    let reliability = if stats.dichotomous {
+        "KR-20"
+    } else {
+        "Cronbach's alpha"
+    };
+    println!(
+        "{} learners, mean total {:.2}, {} {}",
+        stats.learners,
+        stats.mean_total,
+        reliability,
+        format(stats.reliability)
+    );
     Ok(())
 }
 
@@ -179,6 +614,23 @@ fn show_example() {
     println!("6. Upload your assignment.");
     println!("^^^");
     println!();
+    println!("7. Roses are {{*red|blue}}, violets are {{red|*blue}}.");
+    println!();
+    println!("8. A car travels [d] km in [t] h. What is its speed in km/h?");
+    println!("formula: d / t ± 0.5");
+    println!("var d: 10 .. 200");
+    println!("var t: 1.0 .. 5.0");
+    println!();
+    println!("passage: Black Tuesday");
+    println!("On October 29, 1929, stock prices collapsed.");
+    println!();
+    println!("9. In what year did the crash happen?");
+    println!("* 1929");
+    println!();
+    println!("end passage");
+    println!();
+    println!("text: Thanks for taking the quiz!");
+    println!();
     println!("Legend:");
     println!("-------");
     println!("*x) or *)     - Correct choice (multiple choice)");
@@ -186,7 +638,22 @@ fn show_example() {
     println!("[*]           - Correct choice (multiple answer)");
     println!("[ ]           - Incorrect choice (multiple answer)");
     println!("* answer      - Acceptable answer (short answer)");
+    println!("* /regex/     - Acceptable answer pattern (short answer)");
     println!("= num ± margin - Numerical answer with margin");
     println!("___           - Essay question");
     println!("^^^           - File upload");
+    println!("{{*a|b}}        - Inline dropdown (multiple dropdowns)");
+    println!("formula: expr ± tol - Formula answer, with var x: min .. max lines");
+    println!("passage: Title - Shared passage for the questions up to 'end passage'");
+    println!("group: Title, pick N, P points - Random draw from the questions up to 'end group'");
+    println!("from: bank.txt tagged t - Fill a question group from a question bank file");
+    println!("pull: N from bank.txt  - Copy N questions from a question bank file");
+    println!("tags: a, b    - Tags of a question in a bank file");
+    println!("text: content - Ungraded text-only item");
+    println!("likert        - Ungraded rating scale (or likert: Low | Mid | High)");
+    println!("ungraded      - Record the response without scoring it");
+    println!("quiz_type: survey - Canvas quiz type (header, before question 1)");
+    println!("attempts: 3, time_limit: 45, access_code: x - Quiz settings (header)");
+    println!("id: name      - Fixed question identifier (after the answers)");
+    println!("$x^2$, $$..$$ - Inline and display LaTeX math (\\$ for a dollar sign)");
 }
@@ -0,0 +1,268 @@
+use crate::builder::true_false_choices;
+use crate::grading::{pick, split, Gradebook};
+use crate::types::{Assessment, Choice, Question, QuestionType};
+use serde::Serialize;
+
+/// Distractors chosen by fewer than this share of learners are flagged as weak
+pub const WEAK_DISTRACTOR_PROPORTION: f64 = 0.05;
+
+/// How often one choice was selected
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChoiceStats {
+    pub choice_id: String,
+    pub text: String,
+    pub correct: bool,
+    pub count: usize,
+    /// Share of learners who selected it
+    pub proportion: f64,
+    /// Mean total score of the learners who selected it
+    pub mean_total: Option<f64>,
+}
+
+/// Classical item statistics for one question
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuestionStats {
+    pub question_id: String,
+    /// Column label from the gradebook, e.g. `Q3`
+    pub label: String,
+    /// Difficulty (p-value): the mean share of the points learners earned
+    pub difficulty: f64,
+    /// Point-biserial correlation of the item score with the rest of the test
+    pub discrimination: Option<f64>,
+    /// Selection frequencies, for choice questions
+    pub choices: Vec<ChoiceStats>,
+    /// Why each weak distractor was flagged
+    pub weak_distractors: Vec<String>,
+}
+
+/// Item analysis of a graded assessment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssessmentStats {
+    pub learners: usize,
+    pub mean_total: f64,
+    pub questions: Vec<QuestionStats>,
+    /// Cronbach's alpha over the automatically graded questions
+    pub reliability: Option<f64>,
+    /// Whether every item scored all or nothing, making the reliability KR-20
+    pub dichotomous: bool,
+}
+
+/// Compute difficulty, discrimination and distractor statistics per question, and
+/// the assessment's internal consistency, from graded responses. Questions left
+/// for manual grading are not analysed.
+pub fn analyze(assessment: &Assessment, gradebook: &Gradebook) -> AssessmentStats {
+    let students = &gradebook.students;
+    let totals: Vec<f64> = students.iter().map(|s| s.total).collect();
+    let mut questions = Vec::new();
+    let mut item_scores: Vec<Vec<f64>> = Vec::new();
+    let mut dichotomous = true;
+
+    for (index, question_id) in gradebook.questions.iter().enumerate() {
+        let Some(scores) = students
+            .iter()
+            .map(|s| s.scores.get(index).copied().flatten())
+            .collect::<Option<Vec<f64>>>()
+        else {
+            continue;
+        };
+        let points = gradebook.points_possible[index];
+        let question = assessment.questions.iter().find(|q| &q.id == question_id);
+
+        let difficulty = if points > 0.0 && !scores.is_empty() {
+            mean(&scores) / points
+        } else {
+            0.0
+        };
+        let rest: Vec<f64> = totals.iter().zip(&scores).map(|(t, s)| t - s).collect();
+        dichotomous &= scores.iter().all(|s| *s == 0.0 || *s == points);
+
+        let answers: Vec<&str> = students
+            .iter()
+            .map(|s| s.answers.get(index).map_or("", String::as_str))
+            .collect();
+        let choices = question
+            .map(|question| choice_stats(question, &answers, &totals))
+            .unwrap_or_default();
+        let full_marks: Vec<f64> = totals
+            .iter()
+            .zip(&scores)
+            .filter(|(_, s)| points > 0.0 && **s >= points)
+            .map(|(t, _)| *t)
+            .collect();
+        let weak_distractors = weak_distractors(&choices, &full_marks);
+
+        questions.push(QuestionStats {
+            question_id: question_id.clone(),
+            label: gradebook.labels[index].clone(),
+            difficulty,
+            discrimination: correlation(&scores, &rest),
+            choices,
+            weak_distractors,
+        });
+        item_scores.push(scores);
+    }
+
+    AssessmentStats {
+        learners: students.len(),
+        mean_total: if totals.is_empty() {
+            0.0
+        } else {
+            mean(&totals)
+        },
+        questions,
+        reliability: cronbach_alpha(&item_scores),
+        dichotomous,
+    }
+}
+
+/// Selections of each choice of a choice question; empty for other types and
+/// for questions nobody answered
+fn choice_stats(question: &Question, answers: &[&str], totals: &[f64]) -> Vec<ChoiceStats> {
+    let choices: Vec<Choice> = match &question.question_type {
+        QuestionType::MultipleChoice { choices, .. }
+        | QuestionType::MultipleAnswer { choices, .. } => choices.clone(),
+        QuestionType::TrueFalse { correct_answer } => true_false_choices(question, *correct_answer),
+        _ => return Vec::new(),
+    };
+    // A question missing from the responses says nothing about its distractors
+    if answers.iter().all(|answer| answer.is_empty()) {
+        return Vec::new();
+    }
+    // Only multiple answer cells list several choices; others may contain commas
+    let multiple = matches!(question.question_type, QuestionType::MultipleAnswer { .. });
+    let selections: Vec<Vec<String>> = answers
+        .iter()
+        .map(|answer| match *answer {
+            _ if multiple => split(answer)
+                .into_iter()
+                .map(|value| pick(&choices, value))
+                .collect(),
+            "" => Vec::new(),
+            answer => vec![pick(&choices, answer)],
+        })
+        .collect();
+
+    choices
+        .iter()
+        .map(|choice| {
+            let chosen: Vec<f64> = selections
+                .iter()
+                .zip(totals)
+                .filter(|(selected, _)| selected.contains(&choice.id))
+                .map(|(_, total)| *total)
+                .collect();
+            ChoiceStats {
+                choice_id: choice.id.clone(),
+                text: choice.text.trim().to_string(),
+                correct: choice.correct,
+                count: chosen.len(),
+                proportion: if answers.is_empty() {
+                    0.0
+                } else {
+                    chosen.len() as f64 / answers.len() as f64
+                },
+                mean_total: (!chosen.is_empty()).then(|| mean(&chosen)),
+            }
+        })
+        .collect()
+}
+
+/// Distractors almost nobody picks, or that draw learners who score better
+/// overall than those answering the question correctly
+fn weak_distractors(choices: &[ChoiceStats], full_marks: &[f64]) -> Vec<String> {
+    let correct_mean = (!full_marks.is_empty()).then(|| mean(full_marks));
+    let mut weak = Vec::new();
+    for choice in choices.iter().filter(|c| !c.correct) {
+        if choice.proportion < WEAK_DISTRACTOR_PROPORTION {
+            weak.push(format!(
+                "'{}' chosen by {:.0}% of learners",
+                choice.text,
+                choice.proportion * 100.0
+            ));
+        } else if let (Some(chosen), Some(correct)) = (choice.mean_total, correct_mean) {
+            if chosen > correct {
+                weak.push(format!(
+                    "'{}' attracts higher scorers ({:.1}) than the correct answer ({:.1})",
+                    choice.text, chosen, correct
+                ));
+            }
+        }
+    }
+    weak
+}
+
+/// Cronbach's alpha from per-item score columns; KR-20 when items are dichotomous
+fn cronbach_alpha(items: &[Vec<f64>]) -> Option<f64> {
+    let k = items.len();
+    let learners = items.first()?.len();
+    if k < 2 || learners < 2 {
+        return None;
+    }
+    let totals: Vec<f64> = (0..learners)
+        .map(|learner| items.iter().map(|item| item[learner]).sum())
+        .collect();
+    let total_variance = variance(&totals);
+    if total_variance == 0.0 {
+        return None;
+    }
+    let item_variance: f64 = items.iter().map(|item| variance(item)).sum();
+    let k = k as f64;
+    Some(k / (k - 1.0) * (1.0 - item_variance / total_variance))
+}
+
+/// Pearson correlation, `None` when either side does not vary
+fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    if xs.len() < 2 {
+        return None;
+    }
+    let (mx, my) = (mean(xs), mean(ys));
+    let covariance: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+    let spread = (variance(xs) * variance(ys)).sqrt() * xs.len() as f64;
+    (spread > 0.0).then(|| covariance / spread)
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Population variance
+fn variance(values: &[f64]) -> f64 {
+    let m = mean(values);
+    values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grading::Grader;
+
+    #[test]
+    fn test_analyze() {
+        let assessment = crate::Parser::new()
+            .parse("1. Capital?\n*a) Paris\nb) Rome, Italy\nc) Oslo\n\n2. Two plus two?\n*a) 4\nb) 5\n\n3. Pick some\n[*] x\n[ ] y\n\n4. Discuss.\n___")
+            .unwrap();
+        let responses = "name,1,2,3,4\nA,a,a,x,essay\nB,a,a,x,\nC,\"Rome, Italy\",a,y,\nD,b,b,y,\n";
+        let gradebook = Grader::new(&assessment)
+            .grade_csv(responses.as_bytes())
+            .unwrap();
+        let stats = analyze(&assessment, &gradebook);
+
+        assert_eq!(stats.learners, 4);
+        assert_eq!(stats.questions.len(), 3);
+        let capital = &stats.questions[0];
+        assert_eq!(capital.difficulty, 0.5);
+        assert!((capital.discrimination.unwrap() - 0.9045).abs() < 1e-3);
+        assert_eq!(
+            capital.choices.iter().map(|c| c.count).collect::<Vec<_>>(),
+            [2, 2, 0]
+        );
+        assert_eq!(
+            capital.weak_distractors,
+            ["'Oslo' chosen by 0% of learners"]
+        );
+        assert_eq!(stats.questions[1].difficulty, 0.75);
+
+        assert!(stats.dichotomous);
+        assert!((stats.reliability.unwrap() - 8.0 / 9.0).abs() < 1e-9);
+    }
+}
@@ -1,18 +1,31 @@
-use crate::error::Result;
-use crate::types::{AcceptableAnswer, Assessment, Choice, Question, QuestionType};
-use uuid::Uuid;
+use crate::error::{QtiError, Result};
+use crate::ids;
+use crate::math::{self, MathTarget};
+use crate::media;
+use crate::pattern::{self, PatternCondition};
+use crate::types::{
+    AcceptableAnswer, AnswerType, Assessment, Attempts, BankRef, Choice, Dropdown,
+    FormulaAnswerSet, FormulaVariable, Passage, Question, QuestionGroup, QuestionType, QuizType,
+};
+use std::collections::HashMap;
 use xmltree::{Element, XMLNode};
 
 /// Builder for QTI XML elements
 pub struct QtiBuilder {
     /// Whether to include Canvas-specific extensions
     canvas_extensions: bool,
+    /// What `$...$` math in question text is converted into
+    math_target: MathTarget,
+    /// Whether short-answer patterns QTI cannot express are left out instead of rejected
+    unsupported_patterns: bool,
 }
 
 impl QtiBuilder {
     pub fn new() -> Self {
         Self {
             canvas_extensions: false,
+            math_target: MathTarget::default(),
+            unsupported_patterns: false,
         }
     }
 
@@ -21,6 +34,26 @@ impl QtiBuilder {
         self
     }
 
+    pub fn has_canvas_extensions(&self) -> bool {
+        self.canvas_extensions
+    }
+
+    pub fn math_target(&self) -> MathTarget {
+        self.math_target
+    }
+
+    pub fn with_math_target(mut self, target: MathTarget) -> Self {
+        self.math_target = target;
+        self
+    }
+
+    /// Build short answers whose patterns have no QTI equivalent without those
+    /// answers, which then earn nothing in the LMS, instead of failing
+    pub fn with_unsupported_patterns(mut self) -> Self {
+        self.unsupported_patterns = true;
+        self
+    }
+
     /// Build the root questestinterop element
     pub fn build_questestinterop(&self, assessment: &Assessment) -> Result<Element> {
         let mut root = Element::new("questestinterop");
@@ -52,8 +85,11 @@ impl QtiBuilder {
             .insert("title".to_string(), assessment.title.clone());
 
         if self.canvas_extensions {
-            elem.children
-                .push(XMLNode::Element(self.build_qtimetadata(assessment)?));
+            let metadata = self.build_qtimetadata(assessment)?;
+            // The schema wants at least one field, and a quiz may set none
+            if !metadata.children.is_empty() {
+                elem.children.push(XMLNode::Element(metadata));
+            }
         }
 
         let section = self.build_section(assessment)?;
@@ -83,9 +119,116 @@ impl QtiBuilder {
             ));
         }
 
+        if let Some(attempts) = assessment.metadata.allowed_attempts {
+            let attempts = match attempts {
+                Attempts::Limited(attempts) => attempts.to_string(),
+                Attempts::Unlimited => "unlimited".to_string(),
+            };
+            metadata.children.push(XMLNode::Element(
+                self.build_qtimetadatafield("cc_maxattempts", &attempts),
+            ));
+        }
+
+        let quiz_type = assessment.metadata.quiz_type;
+        if quiz_type != QuizType::Assignment {
+            metadata.children.push(XMLNode::Element(
+                self.build_qtimetadatafield("quiz_type", quiz_type.canvas_name()),
+            ));
+        }
+
+        if quiz_type.is_survey() {
+            metadata.children.push(XMLNode::Element(
+                self.build_qtimetadatafield("qmd_assessmenttype", "Survey"),
+            ));
+        }
+
         Ok(metadata)
     }
 
+    /// Canvas `assessment_meta.xml`, carrying the quiz settings Canvas reads on import
+    pub fn build_assessment_meta(&self, assessment: &Assessment) -> Option<Element> {
+        if !self.canvas_extensions {
+            return None;
+        }
+        let metadata = &assessment.metadata;
+
+        let mut quiz = Element::new("quiz");
+        quiz.attributes
+            .insert("identifier".to_string(), assessment.identifier.clone());
+        quiz.attributes.insert(
+            "xmlns".to_string(),
+            "http://canvas.instructure.com/xsd/cccv1p0".to_string(),
+        );
+        quiz.attributes.insert(
+            "xmlns:xsi".to_string(),
+            "http://www.w3.org/2001/XMLSchema-instance".to_string(),
+        );
+        quiz.attributes.insert(
+            "xsi:schemaLocation".to_string(),
+            "http://canvas.instructure.com/xsd/cccv1p0 https://canvas.instructure.com/xsd/cccv1p0.xsd"
+                .to_string(),
+        );
+
+        let bool_text = |value: bool| if value { "true" } else { "false" };
+        let mut fields: Vec<(&str, String)> = vec![("title", assessment.title.clone())];
+        if let Some(description) = &assessment.description {
+            fields.push(("description", description.clone()));
+        }
+        fields.extend([
+            (
+                "shuffle_answers",
+                bool_text(metadata.shuffle_answers).to_string(),
+            ),
+            ("scoring_policy", "keep_highest".to_string()),
+            ("hide_results", String::new()),
+            ("quiz_type", metadata.quiz_type.canvas_name().to_string()),
+            ("points_possible", assessment.points_possible().to_string()),
+            (
+                "show_correct_answers",
+                bool_text(metadata.show_correct_answers).to_string(),
+            ),
+            ("anonymous_submissions", "false".to_string()),
+            ("could_be_locked", "false".to_string()),
+        ]);
+        if let Some(time_limit) = assessment.time_limit {
+            fields.push(("time_limit", time_limit.to_string()));
+        }
+        // Canvas writes unlimited attempts as -1; unset leaves its default
+        match metadata.allowed_attempts {
+            Some(Attempts::Limited(attempts)) => {
+                fields.push(("allowed_attempts", attempts.to_string()))
+            }
+            Some(Attempts::Unlimited) => fields.push(("allowed_attempts", "-1".to_string())),
+            None => {}
+        }
+        fields.extend([
+            (
+                "one_question_at_a_time",
+                bool_text(metadata.one_question_at_a_time).to_string(),
+            ),
+            (
+                "cant_go_back",
+                bool_text(metadata.one_question_at_a_time && metadata.cant_go_back).to_string(),
+            ),
+        ]);
+        if let Some(access_code) = &metadata.access_code {
+            fields.push(("access_code", access_code.clone()));
+        }
+        fields.extend([
+            ("available", "true".to_string()),
+            ("one_time_results", "false".to_string()),
+            ("show_correct_answers_last_attempt", "false".to_string()),
+            ("only_visible_to_overrides", "false".to_string()),
+            ("module_locked", "false".to_string()),
+        ]);
+
+        for (name, value) in fields {
+            quiz.children
+                .push(XMLNode::Element(text_element(name, &value)));
+        }
+        Some(quiz)
+    }
+
     fn build_qtimetadatafield(&self, label: &str, entry: &str) -> Element {
         let mut field = Element::new("qtimetadatafield");
 
@@ -102,34 +245,200 @@ impl QtiBuilder {
 
     fn build_section(&self, assessment: &Assessment) -> Result<Element> {
         let mut section = Element::new("section");
-        section
-            .attributes
-            .insert("ident".to_string(), format!("section_{}", Uuid::new_v4()));
+        section.attributes.insert(
+            "ident".to_string(),
+            ids::stable_id("section", &assessment.identifier),
+        );
         section
             .attributes
             .insert("title".to_string(), "Main Section".to_string());
 
+        let mut current_passage: Option<&str> = None;
+        // Where each group's section sits in `section.children`
+        let mut group_sections: HashMap<&str, usize> = HashMap::new();
         for question in &assessment.questions {
-            let item = self.build_item(question)?;
-            section.children.push(XMLNode::Element(item));
+            // Render a shared passage once, ahead of the run of questions using it
+            if question.passage.as_deref() != current_passage {
+                current_passage = question.passage.as_deref();
+                if let Some(passage) = current_passage.and_then(|id| assessment.passage(id)) {
+                    let item = self.build_passage_item(passage)?;
+                    section.children.push(XMLNode::Element(item));
+                }
+            }
+
+            // A group's questions share one nested section, placed where the first
+            // of them appears, so a group split by other questions keeps one ident
+            let group = question
+                .group
+                .as_deref()
+                .and_then(|id| assessment.group(id));
+            let group_index = group.map(|group| {
+                *group_sections.entry(group.id.as_str()).or_insert_with(|| {
+                    let group_section = self.build_group_section(group);
+                    section.children.push(XMLNode::Element(group_section));
+                    section.children.len() - 1
+                })
+            });
+
+            // Canvas draws bank groups from the separately packaged bank
+            if self.canvas_extensions && group.is_some_and(|g| g.bank.is_some()) {
+                continue;
+            }
+
+            let item = XMLNode::Element(self.build_item(question)?);
+            match group_index.map(|index| &mut section.children[index]) {
+                Some(XMLNode::Element(group_section)) => group_section.children.push(item),
+                _ => section.children.push(item),
+            }
         }
 
         Ok(section)
     }
 
+    /// Canvas `objectbank` documents for the banks the assessment's groups draw from
+    pub fn build_objectbanks(&self, assessment: &Assessment) -> Result<Vec<(BankRef, Element)>> {
+        let mut banks: Vec<(BankRef, Element)> = Vec::new();
+        if !self.canvas_extensions {
+            return Ok(banks);
+        }
+
+        // One objectbank per source bank, holding every question the quiz's groups
+        // draw from it under the bank's own idents
+        for group in &assessment.groups {
+            let Some(bank) = &group.bank else {
+                continue;
+            };
+            let index = match banks
+                .iter()
+                .position(|(existing, _)| existing.id == bank.id)
+            {
+                Some(index) => index,
+                None => {
+                    let mut objectbank = Element::new("objectbank");
+                    objectbank
+                        .attributes
+                        .insert("ident".to_string(), bank.id.clone());
+                    let mut metadata = Element::new("qtimetadata");
+                    metadata.children.push(XMLNode::Element(
+                        self.build_qtimetadatafield("bank_title", &bank.title),
+                    ));
+                    objectbank.children.push(XMLNode::Element(metadata));
+
+                    let mut root = Element::new("questestinterop");
+                    root.attributes.insert(
+                        "xmlns".to_string(),
+                        "http://www.imsglobal.org/xsd/ims_qtiasiv1p2".to_string(),
+                    );
+                    root.attributes.insert(
+                        "xmlns:xsi".to_string(),
+                        "http://www.w3.org/2001/XMLSchema-instance".to_string(),
+                    );
+                    root.children.push(XMLNode::Element(objectbank));
+                    let bank = BankRef {
+                        tags: Vec::new(),
+                        ..bank.clone()
+                    };
+                    banks.push((bank, root));
+                    banks.len() - 1
+                }
+            };
+            let objectbank = banks[index].1.get_mut_child("objectbank").unwrap();
+
+            for question in assessment
+                .questions
+                .iter()
+                .filter(|q| q.group.as_ref() == Some(&group.id))
+            {
+                let mut item = question.clone();
+                if let Some(id) = &question.bank_item {
+                    item.id = id.clone();
+                }
+                if objectbank.children.iter().any(|child| {
+                    child
+                        .as_element()
+                        .and_then(|e| e.attributes.get("ident"))
+                        .is_some_and(|ident| *ident == item.id)
+                }) {
+                    continue;
+                }
+                objectbank
+                    .children
+                    .push(XMLNode::Element(self.build_bank_item(&item)?));
+            }
+        }
+
+        Ok(banks)
+    }
+
+    /// An objectbank item, carrying the question's tags for groups that filter by them
+    fn build_bank_item(&self, question: &Question) -> Result<Element> {
+        let mut item = self.build_item(question)?;
+        if !question.tags.is_empty() {
+            let field = self.build_qtimetadatafield("tags", &bank_tags(&question.tags));
+            if let Some(metadata) = item
+                .get_mut_child("itemmetadata")
+                .and_then(|m| m.get_mut_child("qtimetadata"))
+            {
+                metadata.children.push(XMLNode::Element(field));
+            }
+        }
+        Ok(item)
+    }
+
+    /// Section for a question group: draw `pick` of its items per attempt
+    fn build_group_section(&self, group: &QuestionGroup) -> Element {
+        let mut section = Element::new("section");
+        section
+            .attributes
+            .insert("ident".to_string(), group.id.clone());
+        section
+            .attributes
+            .insert("title".to_string(), group.title.clone());
+
+        let mut selection = Element::new("selection");
+        let bank = group.bank.as_ref().filter(|_| self.canvas_extensions);
+        if let Some(bank) = bank {
+            selection
+                .children
+                .push(XMLNode::Element(text_element("sourcebank_ref", &bank.id)));
+        }
+        selection.children.push(XMLNode::Element(text_element(
+            "selection_number",
+            &group.pick.to_string(),
+        )));
+        // A tag filter draws only the bank items whose `tags` field names one of them
+        if let Some(bank) = bank.filter(|bank| !bank.tags.is_empty()) {
+            let mut filter = text_element("selection_metadata", &bank_tags(&bank.tags));
+            filter
+                .attributes
+                .insert("mdname".to_string(), "tags".to_string());
+            filter
+                .attributes
+                .insert("mdoperator".to_string(), "EQ".to_string());
+            selection.children.push(XMLNode::Element(filter));
+        }
+        if self.canvas_extensions {
+            let mut extension = Element::new("selection_extension");
+            extension.children.push(XMLNode::Element(text_element(
+                "points_per_item",
+                &group.points_per_question.to_string(),
+            )));
+            selection.children.push(XMLNode::Element(extension));
+        }
+
+        let mut ordering = Element::new("selection_ordering");
+        ordering.children.push(XMLNode::Element(selection));
+        section.children.push(XMLNode::Element(ordering));
+        section
+    }
+
     /// Build item element for a question
     fn build_item(&self, question: &Question) -> Result<Element> {
         let mut item = Element::new("item");
         item.attributes
             .insert("ident".to_string(), question.id.clone());
-        item.attributes.insert(
-            "title".to_string(),
-            if question.title.is_empty() {
-                format!("Question {}", &question.id[9..15])
-            } else {
-                question.title.clone()
-            },
-        );
+        item.attributes
+            .insert("title".to_string(), item_title(question));
 
         if self.canvas_extensions {
             item.children
@@ -139,8 +448,30 @@ impl QtiBuilder {
         item.children
             .push(XMLNode::Element(self.build_presentation(question)?));
 
-        item.children
-            .push(XMLNode::Element(self.build_resprocessing(question)?));
+        if !matches!(question.question_type, QuestionType::TextOnly) {
+            item.children
+                .push(XMLNode::Element(self.build_resprocessing(question)?));
+        }
+
+        if self.canvas_extensions {
+            if let QuestionType::Formula {
+                formula,
+                variables,
+                answer_sets,
+                tolerance,
+                decimals,
+            } = &question.question_type
+            {
+                item.children
+                    .push(XMLNode::Element(self.build_itemproc_calculated(
+                        formula,
+                        variables,
+                        answer_sets,
+                        *tolerance,
+                        *decimals,
+                    )));
+            }
+        }
 
         if let Some(ref feedback) = question.feedback {
             if let Some(ref correct_feedback) = feedback.correct {
@@ -158,8 +489,21 @@ impl QtiBuilder {
         Ok(item)
     }
 
+    /// A passage is emitted as an ungraded text-only item
+    fn build_passage_item(&self, passage: &Passage) -> Result<Element> {
+        let mut question = Question::new(passage.text.clone(), QuestionType::TextOnly);
+        question.id = passage.id.clone();
+        question.title = if passage.title.is_empty() {
+            "Passage".to_string()
+        } else {
+            passage.title.clone()
+        };
+        question.points = 0.0;
+        self.build_item(&question)
+    }
+
     fn build_itemmetadata(&self, question: &Question) -> Result<Element> {
-        let mut metadata = Element::new("itemmetadata");
+        let mut metadata = Element::new("qtimetadata");
 
         let mut field = Element::new("qtimetadatafield");
 
@@ -178,6 +522,10 @@ impl QtiBuilder {
             QuestionType::Numerical { .. } => "numerical_question",
             QuestionType::Essay { .. } => "essay_question",
             QuestionType::FileUpload { .. } => "file_upload_question",
+            QuestionType::MultipleDropdowns { .. } => "multiple_dropdowns_question",
+            QuestionType::Formula { .. } => "calculated_question",
+            QuestionType::TextOnly => "text_only_question",
+            QuestionType::Likert { .. } => "multiple_choice_question",
         };
         entry
             .children
@@ -195,25 +543,40 @@ impl QtiBuilder {
         let mut points_entry = Element::new("fieldentry");
         points_entry
             .children
-            .push(XMLNode::Text(question.points.to_string()));
+            .push(XMLNode::Text(points_possible(question).to_string()));
         points_field.children.push(XMLNode::Element(points_entry));
         metadata.children.push(XMLNode::Element(points_field));
 
-        Ok(metadata)
+        let mut itemmetadata = Element::new("itemmetadata");
+        itemmetadata.children.push(XMLNode::Element(metadata));
+        Ok(itemmetadata)
     }
 
     fn build_presentation(&self, question: &Question) -> Result<Element> {
         let mut presentation = Element::new("presentation");
 
         // Add question text as material
-        let mut material = Element::new("material");
-        let mut mattext = Element::new("mattext");
-        mattext
-            .attributes
-            .insert("texttype".to_string(), "text/html".to_string());
-        mattext.children.push(XMLNode::Text(question.text.clone()));
-        material.children.push(XMLNode::Element(mattext));
-        presentation.children.push(XMLNode::Element(material));
+        let text = match &question.question_type {
+            // Without Canvas's calculated extension, show the first answer set's values
+            QuestionType::Formula { answer_sets, .. } if !self.canvas_extensions => answer_sets
+                .first()
+                .map(|set| substitute_variables(&question.text, set))
+                .unwrap_or_else(|| question.text.clone()),
+            _ => question.text.clone(),
+        };
+        // Canvas fills `[x]` placeholders into the HTML, which it cannot do
+        // inside an equation image URL
+        let math_target = match &question.question_type {
+            QuestionType::Formula { .. }
+                if self.canvas_extensions && self.math_target == MathTarget::CanvasEquation =>
+            {
+                MathTarget::MathJax
+            }
+            _ => self.math_target,
+        };
+        presentation
+            .children
+            .push(XMLNode::Element(self.build_material_as(&text, math_target)));
 
         match &question.question_type {
             QuestionType::MultipleChoice { choices, shuffle } => {
@@ -224,8 +587,8 @@ impl QtiBuilder {
                 let response = self.build_response_lid(question, choices, false)?;
                 presentation.children.push(XMLNode::Element(response));
             }
-            QuestionType::TrueFalse { .. } => {
-                let choices = vec![Choice::new("True", false), Choice::new("False", false)];
+            QuestionType::TrueFalse { correct_answer } => {
+                let choices = true_false_choices(question, *correct_answer);
                 let response = self.build_response_lid(question, &choices, false)?;
                 presentation.children.push(XMLNode::Element(response));
             }
@@ -233,7 +596,7 @@ impl QtiBuilder {
                 let response = self.build_response_str(question)?;
                 presentation.children.push(XMLNode::Element(response));
             }
-            QuestionType::Numerical { .. } => {
+            QuestionType::Numerical { .. } | QuestionType::Formula { .. } => {
                 let response = self.build_response_num(question)?;
                 presentation.children.push(XMLNode::Element(response));
             }
@@ -245,6 +608,17 @@ impl QtiBuilder {
                 let response = self.build_response_str(question)?;
                 presentation.children.push(XMLNode::Element(response));
             }
+            QuestionType::MultipleDropdowns { dropdowns } => {
+                for dropdown in dropdowns {
+                    let response = self.build_response_lid_dropdown(dropdown)?;
+                    presentation.children.push(XMLNode::Element(response));
+                }
+            }
+            QuestionType::Likert { scale } => {
+                let response = self.build_response_lid(question, scale, false)?;
+                presentation.children.push(XMLNode::Element(response));
+            }
+            QuestionType::TextOnly => {}
         }
 
         Ok(presentation)
@@ -271,6 +645,35 @@ impl QtiBuilder {
             .attributes
             .insert("rcardinality".to_string(), rcardinality.to_string());
 
+        response
+            .children
+            .push(XMLNode::Element(self.build_render_choice(choices, shuffle)));
+        Ok(response)
+    }
+
+    /// Build one response_lid per dropdown, labelled with the placeholder name
+    fn build_response_lid_dropdown(&self, dropdown: &Dropdown) -> Result<Element> {
+        let mut response = Element::new("response_lid");
+        response
+            .attributes
+            .insert("ident".to_string(), format!("response_{}", dropdown.name));
+        response
+            .attributes
+            .insert("rcardinality".to_string(), "Single".to_string());
+
+        let mut material = Element::new("material");
+        let mut mattext = Element::new("mattext");
+        mattext.children.push(XMLNode::Text(dropdown.name.clone()));
+        material.children.push(XMLNode::Element(mattext));
+        response.children.push(XMLNode::Element(material));
+
+        response.children.push(XMLNode::Element(
+            self.build_render_choice(&dropdown.choices, false),
+        ));
+        Ok(response)
+    }
+
+    fn build_render_choice(&self, choices: &[Choice], shuffle: bool) -> Element {
         let mut render = Element::new("render_choice");
         if shuffle {
             render
@@ -284,20 +687,14 @@ impl QtiBuilder {
                 .attributes
                 .insert("ident".to_string(), choice.id.clone());
 
-            let mut material = Element::new("material");
-            let mut mattext = Element::new("mattext");
-            mattext
-                .attributes
-                .insert("texttype".to_string(), "text/html".to_string());
-            mattext.children.push(XMLNode::Text(choice.text.clone()));
-            material.children.push(XMLNode::Element(mattext));
-            label.children.push(XMLNode::Element(material));
+            label
+                .children
+                .push(XMLNode::Element(self.build_material(&choice.text)));
 
             render.children.push(XMLNode::Element(label));
         }
 
-        response.children.push(XMLNode::Element(render));
-        Ok(response)
+        render
     }
 
     fn build_response_str(&self, question: &Question) -> Result<Element> {
@@ -358,32 +755,125 @@ impl QtiBuilder {
             .push(XMLNode::Element(self.build_outcomes(question)?));
 
         match &question.question_type {
+            // Graded-survey questions earn their points for any answer
+            _ if question.participation => {
+                let condition = self.build_respcondition_participation(question)?;
+                resprocessing.children.push(XMLNode::Element(condition));
+            }
+            // Survey questions record the response without scoring it
+            _ if !question.graded => {}
             QuestionType::MultipleChoice { choices, .. } => {
                 for choice in choices {
                     let condition = self.build_respcondition_mc(question, choice)?;
                     resprocessing.children.push(XMLNode::Element(condition));
                 }
             }
+            QuestionType::TrueFalse { correct_answer } => {
+                for choice in true_false_choices(question, *correct_answer) {
+                    let condition = self.build_respcondition_mc(question, &choice)?;
+                    resprocessing.children.push(XMLNode::Element(condition));
+                }
+            }
             QuestionType::MultipleAnswer {
                 choices,
                 partial_credit,
             } => {
-                for choice in choices {
-                    let condition =
-                        self.build_respcondition_ma(question, choice, *partial_credit)?;
+                if *partial_credit {
+                    let correct_count = choices.iter().filter(|c| c.correct).count();
+                    for choice in choices {
+                        let condition =
+                            self.build_respcondition_ma(question, choice, correct_count)?;
+                        resprocessing.children.push(XMLNode::Element(condition));
+                    }
+                } else {
+                    let condition = self.build_respcondition_ma_all(question, choices)?;
                     resprocessing.children.push(XMLNode::Element(condition));
                 }
             }
-            QuestionType::ShortAnswer { answers, .. } => {
+            QuestionType::Numerical {
+                answer,
+                margin,
+                min,
+                max,
+            } => {
+                let condition = match (min, max) {
+                    (Some(min), Some(max)) => {
+                        self.build_respcondition_range(question, *min, *max)?
+                    }
+                    _ => {
+                        self.build_respcondition_numeric(question, *answer, margin.unwrap_or(0.0))?
+                    }
+                };
+                resprocessing.children.push(XMLNode::Element(condition));
+            }
+            QuestionType::ShortAnswer {
+                answers,
+                case_sensitive,
+            } => {
                 for answer in answers {
-                    let condition = self.build_respcondition_sa(question, answer)?;
+                    // Patterns become one condition per equivalent literal answer;
+                    // patterns QTI cannot express are skipped only when allowed
+                    let (operator, texts) = match &answer.answer_type {
+                        AnswerType::Pattern(regex) => {
+                            match pattern::to_condition(regex, *case_sensitive) {
+                                PatternCondition::Exact(texts) => ("varequal", texts),
+                                PatternCondition::Substring(texts) => ("varsubstring", texts),
+                                PatternCondition::Unsupported if self.unsupported_patterns => {
+                                    continue
+                                }
+                                PatternCondition::Unsupported => {
+                                    return Err(QtiError::ValidationError(format!(
+                                        "Question {}: answer pattern /{}/ cannot be written as QTI conditions, so it would score 0 points",
+                                        question.id, regex
+                                    )))
+                                }
+                            }
+                        }
+                        _ => ("varequal", vec![answer.text.clone()]),
+                    };
+                    for text in &texts {
+                        let condition = self.build_respcondition_sa(
+                            question,
+                            answer,
+                            operator,
+                            text,
+                            *case_sensitive,
+                        )?;
+                        resprocessing.children.push(XMLNode::Element(condition));
+                    }
+                }
+            }
+            QuestionType::Formula {
+                answer_sets,
+                tolerance,
+                ..
+            } => {
+                if let Some(set) = answer_sets.first() {
+                    let condition = self.build_respcondition_range(
+                        question,
+                        set.answer - tolerance,
+                        set.answer + tolerance,
+                    )?;
                     resprocessing.children.push(XMLNode::Element(condition));
                 }
             }
-            _ => {
-                let condition = self.build_respcondition_default(question)?;
-                resprocessing.children.push(XMLNode::Element(condition));
+            QuestionType::MultipleDropdowns { dropdowns } => {
+                let points_each = question.points / dropdowns.len().max(1) as f32;
+                for dropdown in dropdowns {
+                    if let Some(correct) = dropdown.choices.iter().find(|c| c.correct) {
+                        let condition =
+                            self.build_respcondition_dropdown(dropdown, correct, points_each)?;
+                        resprocessing.children.push(XMLNode::Element(condition));
+                    }
+                }
             }
+            _ => {}
+        }
+
+        // QTI requires at least one condition after the outcomes
+        if resprocessing.children.len() == 1 {
+            let condition = self.build_respcondition_default(question)?;
+            resprocessing.children.push(XMLNode::Element(condition));
         }
 
         Ok(resprocessing)
@@ -393,9 +883,10 @@ impl QtiBuilder {
         let mut outcomes = Element::new("outcomes");
 
         let mut decvar = Element::new("decvar");
-        decvar
-            .attributes
-            .insert("maxvalue".to_string(), question.points.to_string());
+        decvar.attributes.insert(
+            "maxvalue".to_string(),
+            points_possible(question).to_string(),
+        );
         decvar
             .attributes
             .insert("minvalue".to_string(), "0".to_string());
@@ -447,11 +938,13 @@ impl QtiBuilder {
         Ok(condition)
     }
 
+    /// Partial credit: each correct choice adds its share of the points and
+    /// each wrong choice takes the same share away
     fn build_respcondition_ma(
         &self,
         question: &Question,
         choice: &Choice,
-        partial_credit: bool,
+        correct_count: usize,
     ) -> Result<Element> {
         let mut condition = Element::new("respcondition");
         condition
@@ -475,36 +968,79 @@ impl QtiBuilder {
             .attributes
             .insert("varname".to_string(), "SCORE".to_string());
 
-        let score = if partial_credit && choice.correct {
-            // TODO: should count actual number of correct answers for accurate partial credit
-            question.points / 2.0
-        } else if choice.correct {
-            question.points
-        } else {
-            0.0
-        };
+        let share = question.points / correct_count.max(1) as f32;
+        let score = if choice.correct { share } else { -share };
         setvar.children.push(XMLNode::Text(score.to_string()));
         condition.children.push(XMLNode::Element(setvar));
 
         Ok(condition)
     }
 
+    /// All or nothing: every correct choice and no wrong one
+    fn build_respcondition_ma_all(
+        &self,
+        question: &Question,
+        choices: &[Choice],
+    ) -> Result<Element> {
+        let mut condition = Element::new("respcondition");
+        condition
+            .attributes
+            .insert("continue".to_string(), "No".to_string());
+
+        let respident = format!("response_{}", question.id);
+        let mut all = Element::new("and");
+        for choice in choices {
+            let mut varequal = text_element("varequal", &choice.id);
+            varequal
+                .attributes
+                .insert("respident".to_string(), respident.clone());
+            if choice.correct {
+                all.children.push(XMLNode::Element(varequal));
+            } else {
+                let mut not = Element::new("not");
+                not.children.push(XMLNode::Element(varequal));
+                all.children.push(XMLNode::Element(not));
+            }
+        }
+        let mut condvar = Element::new("conditionvar");
+        condvar.children.push(XMLNode::Element(all));
+        condition.children.push(XMLNode::Element(condvar));
+
+        let mut setvar = Element::new("setvar");
+        setvar
+            .attributes
+            .insert("action".to_string(), "Set".to_string());
+        setvar
+            .attributes
+            .insert("varname".to_string(), "SCORE".to_string());
+        setvar
+            .children
+            .push(XMLNode::Text(question.points.to_string()));
+        condition.children.push(XMLNode::Element(setvar));
+
+        Ok(condition)
+    }
+
     fn build_respcondition_sa(
         &self,
         question: &Question,
         answer: &AcceptableAnswer,
+        operator: &str,
+        text: &str,
+        case_sensitive: bool,
     ) -> Result<Element> {
         let mut condition = Element::new("respcondition");
 
         let mut condvar = Element::new("conditionvar");
-        let mut varequal = Element::new("varequal");
+        let mut varequal = Element::new(operator);
         varequal
             .attributes
             .insert("respident".to_string(), format!("response_{}", question.id));
-        varequal
-            .attributes
-            .insert("case".to_string(), "No".to_string());
-        varequal.children.push(XMLNode::Text(answer.text.clone()));
+        varequal.attributes.insert(
+            "case".to_string(),
+            if case_sensitive { "Yes" } else { "No" }.to_string(),
+        );
+        varequal.children.push(XMLNode::Text(text.to_string()));
         condvar.children.push(XMLNode::Element(varequal));
         condition.children.push(XMLNode::Element(condvar));
 
@@ -522,10 +1058,241 @@ impl QtiBuilder {
         Ok(condition)
     }
 
+    fn build_respcondition_dropdown(
+        &self,
+        dropdown: &Dropdown,
+        correct: &Choice,
+        points: f32,
+    ) -> Result<Element> {
+        let mut condition = Element::new("respcondition");
+        condition
+            .attributes
+            .insert("continue".to_string(), "Yes".to_string());
+
+        let mut condvar = Element::new("conditionvar");
+        let mut varequal = Element::new("varequal");
+        varequal.attributes.insert(
+            "respident".to_string(),
+            format!("response_{}", dropdown.name),
+        );
+        varequal.children.push(XMLNode::Text(correct.id.clone()));
+        condvar.children.push(XMLNode::Element(varequal));
+        condition.children.push(XMLNode::Element(condvar));
+
+        let mut setvar = Element::new("setvar");
+        setvar
+            .attributes
+            .insert("action".to_string(), "Add".to_string());
+        setvar
+            .attributes
+            .insert("varname".to_string(), "SCORE".to_string());
+        setvar.children.push(XMLNode::Text(points.to_string()));
+        condition.children.push(XMLNode::Element(setvar));
+
+        Ok(condition)
+    }
+
+    fn build_respcondition_range(
+        &self,
+        question: &Question,
+        min: f64,
+        max: f64,
+    ) -> Result<Element> {
+        let mut condition = Element::new("respcondition");
+        condition
+            .attributes
+            .insert("continue".to_string(), "No".to_string());
+
+        let mut condvar = Element::new("conditionvar");
+        let mut vargte = Element::new("vargte");
+        vargte
+            .attributes
+            .insert("respident".to_string(), format!("response_{}", question.id));
+        vargte.children.push(XMLNode::Text(min.to_string()));
+        condvar.children.push(XMLNode::Element(vargte));
+        let mut varlte = Element::new("varlte");
+        varlte
+            .attributes
+            .insert("respident".to_string(), format!("response_{}", question.id));
+        varlte.children.push(XMLNode::Text(max.to_string()));
+        condvar.children.push(XMLNode::Element(varlte));
+        condition.children.push(XMLNode::Element(condvar));
+
+        let mut setvar = Element::new("setvar");
+        setvar
+            .attributes
+            .insert("action".to_string(), "Set".to_string());
+        setvar
+            .attributes
+            .insert("varname".to_string(), "SCORE".to_string());
+        setvar
+            .children
+            .push(XMLNode::Text(question.points.to_string()));
+        condition.children.push(XMLNode::Element(setvar));
+
+        Ok(condition)
+    }
+
+    /// Full points for answering at all; a dropdown question for answering any blank
+    fn build_respcondition_participation(&self, question: &Question) -> Result<Element> {
+        let mut condition = Element::new("respcondition");
+        condition
+            .attributes
+            .insert("continue".to_string(), "No".to_string());
+
+        let respidents: Vec<String> = match &question.question_type {
+            QuestionType::MultipleDropdowns { dropdowns } => dropdowns
+                .iter()
+                .map(|dropdown| format!("response_{}", dropdown.name))
+                .collect(),
+            _ => vec![format!("response_{}", question.id)],
+        };
+        let mut answered = Element::new("or");
+        for respident in respidents {
+            let mut unanswered = Element::new("unanswered");
+            unanswered
+                .attributes
+                .insert("respident".to_string(), respident);
+            let mut not = Element::new("not");
+            not.children.push(XMLNode::Element(unanswered));
+            answered.children.push(XMLNode::Element(not));
+        }
+        let mut condvar = Element::new("conditionvar");
+        condvar.children.push(XMLNode::Element(answered));
+        condition.children.push(XMLNode::Element(condvar));
+
+        let mut setvar = Element::new("setvar");
+        setvar
+            .attributes
+            .insert("action".to_string(), "Set".to_string());
+        setvar
+            .attributes
+            .insert("varname".to_string(), "SCORE".to_string());
+        setvar
+            .children
+            .push(XMLNode::Text(question.points.to_string()));
+        condition.children.push(XMLNode::Element(setvar));
+
+        Ok(condition)
+    }
+
+    /// An exact numeric answer, or anything within `margin` of it
+    fn build_respcondition_numeric(
+        &self,
+        question: &Question,
+        answer: f64,
+        margin: f64,
+    ) -> Result<Element> {
+        let respident = format!("response_{}", question.id);
+        let compare = |name: &str, value: f64| {
+            let mut element = text_element(name, &value.to_string());
+            element
+                .attributes
+                .insert("respident".to_string(), respident.clone());
+            XMLNode::Element(element)
+        };
+
+        let mut range = Element::new("and");
+        range.children.push(compare("vargte", answer - margin));
+        range.children.push(compare("varlte", answer + margin));
+        let mut either = Element::new("or");
+        either.children.push(compare("varequal", answer));
+        either.children.push(XMLNode::Element(range));
+
+        let mut condition = Element::new("respcondition");
+        condition
+            .attributes
+            .insert("continue".to_string(), "No".to_string());
+        let mut condvar = Element::new("conditionvar");
+        condvar.children.push(XMLNode::Element(either));
+        condition.children.push(XMLNode::Element(condvar));
+
+        let mut setvar = text_element("setvar", &question.points.to_string());
+        setvar
+            .attributes
+            .insert("action".to_string(), "Set".to_string());
+        setvar
+            .attributes
+            .insert("varname".to_string(), "SCORE".to_string());
+        condition.children.push(XMLNode::Element(setvar));
+
+        Ok(condition)
+    }
+
+    /// Canvas `calculated_question` data: formula, variable ranges and answer sets
+    fn build_itemproc_calculated(
+        &self,
+        formula: &str,
+        variables: &[FormulaVariable],
+        answer_sets: &[FormulaAnswerSet],
+        tolerance: f64,
+        decimals: u32,
+    ) -> Element {
+        let mut calculated = Element::new("calculated");
+        calculated.children.push(XMLNode::Element(text_element(
+            "answer_tolerance",
+            &tolerance.to_string(),
+        )));
+
+        let mut formulas = Element::new("formulas");
+        formulas
+            .attributes
+            .insert("decimal_places".to_string(), decimals.to_string());
+        formulas
+            .children
+            .push(XMLNode::Element(text_element("formula", formula)));
+        calculated.children.push(XMLNode::Element(formulas));
+
+        let mut vars = Element::new("vars");
+        for variable in variables {
+            let mut var = Element::new("var");
+            var.attributes
+                .insert("name".to_string(), variable.name.clone());
+            var.attributes
+                .insert("scale".to_string(), variable.decimals.to_string());
+            var.children.push(XMLNode::Element(text_element(
+                "min",
+                &variable.min.to_string(),
+            )));
+            var.children.push(XMLNode::Element(text_element(
+                "max",
+                &variable.max.to_string(),
+            )));
+            vars.children.push(XMLNode::Element(var));
+        }
+        calculated.children.push(XMLNode::Element(vars));
+
+        let mut var_sets = Element::new("var_sets");
+        for (index, set) in answer_sets.iter().enumerate() {
+            let mut var_set = Element::new("var_set");
+            var_set
+                .attributes
+                .insert("ident".to_string(), (index + 1).to_string());
+            for (name, value) in &set.values {
+                let mut var = text_element("var", &value.to_string());
+                var.attributes.insert("name".to_string(), name.clone());
+                var_set.children.push(XMLNode::Element(var));
+            }
+            var_set.children.push(XMLNode::Element(text_element(
+                "answer",
+                &set.answer.to_string(),
+            )));
+            var_sets.children.push(XMLNode::Element(var_set));
+        }
+        calculated.children.push(XMLNode::Element(var_sets));
+
+        let mut extension = Element::new("itemproc_extension");
+        extension.children.push(XMLNode::Element(calculated));
+        extension
+    }
+
     fn build_respcondition_default(&self, _question: &Question) -> Result<Element> {
         let mut condition = Element::new("respcondition");
 
-        let condvar = Element::new("conditionvar");
+        let mut condvar = Element::new("conditionvar");
+        condvar
+            .children
+            .push(XMLNode::Element(Element::new("other")));
         condition.children.push(XMLNode::Element(condvar));
 
         let mut setvar = Element::new("setvar");
@@ -547,19 +1314,129 @@ impl QtiBuilder {
             .attributes
             .insert("ident".to_string(), ident.to_string());
 
+        feedback
+            .children
+            .push(XMLNode::Element(self.build_material(text)));
+
+        feedback
+    }
+
+    /// Build an HTML material block. Canvas gets `<img>` tags pointing into
+    /// its file base; other targets get `matimage`/`mataudio`/`matvideo`.
+    fn build_material(&self, text: &str) -> Element {
+        self.build_material_as(text, self.math_target)
+    }
+
+    fn build_material_as(&self, text: &str, math_target: MathTarget) -> Element {
+        let text = &math::render(text, math_target);
         let mut material = Element::new("material");
         let mut mattext = Element::new("mattext");
         mattext
             .attributes
             .insert("texttype".to_string(), "text/html".to_string());
-        mattext.children.push(XMLNode::Text(text.to_string()));
+
+        if self.canvas_extensions {
+            mattext
+                .children
+                .push(XMLNode::Text(media::render_html(text, true)));
+            material.children.push(XMLNode::Element(mattext));
+            return material;
+        }
+
+        let (remaining, refs) = media::extract_media(text);
+        let remaining = if remaining.is_empty() {
+            refs.iter()
+                .map(|r| r.alt.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        } else {
+            remaining
+        };
+        mattext
+            .children
+            .push(XMLNode::Text(media::render_html(&remaining, false)));
         material.children.push(XMLNode::Element(mattext));
-        feedback.children.push(XMLNode::Element(material));
 
-        feedback
+        for media_ref in refs {
+            let mime = media::mime_type(&media_ref.src);
+            let (name, type_attribute) = match mime.split('/').next() {
+                Some("audio") => ("mataudio", "audiotype"),
+                Some("video") => ("matvideo", "videotype"),
+                _ => ("matimage", "imagtype"),
+            };
+            let mut elem = Element::new(name);
+            elem.attributes
+                .insert(type_attribute.to_string(), mime.to_string());
+            elem.attributes
+                .insert("uri".to_string(), media::resolve_src(&media_ref.src, false));
+            if !media_ref.alt.is_empty() {
+                elem.attributes
+                    .insert("label".to_string(), media_ref.alt.clone());
+            }
+            material.children.push(XMLNode::Element(elem));
+        }
+
+        material
+    }
+}
+
+/// Item title: the question's own, else a short form of its id
+pub(crate) fn item_title(question: &Question) -> String {
+    if question.title.is_empty() {
+        match question.id.get(9..15) {
+            Some(short) if ids::is_generated(&question.id, "question") => {
+                format!("Question {}", short)
+            }
+            _ => question.id.clone(),
+        }
+    } else {
+        question.title.clone()
     }
 }
 
+/// The True and False choices of a true/false question, with Canvas-style idents
+pub(crate) fn true_false_choices(question: &Question, correct_answer: bool) -> Vec<Choice> {
+    [("True", true), ("False", false)]
+        .into_iter()
+        .map(|(text, value)| {
+            let mut choice = Choice::new(text, value == correct_answer);
+            choice.id = format!("{}_{}", question.id, text.to_lowercase());
+            choice
+        })
+        .collect()
+}
+
+pub(crate) fn points_possible(question: &Question) -> f32 {
+    if question.earns_points() {
+        question.points
+    } else {
+        0.0
+    }
+}
+
+/// Tags as written into bank metadata: lowercase, comma-separated
+fn bank_tags(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| tag.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn text_element(name: &str, text: &str) -> Element {
+    let mut elem = Element::new(name);
+    elem.children.push(XMLNode::Text(text.to_string()));
+    elem
+}
+
+/// Replace `[name]` placeholders with the values from an answer set
+pub(crate) fn substitute_variables(text: &str, set: &FormulaAnswerSet) -> String {
+    set.values
+        .iter()
+        .fold(text.to_string(), |text, (name, value)| {
+            text.replace(&format!("[{}]", name), &value.to_string())
+        })
+}
+
 impl Default for QtiBuilder {
     fn default() -> Self {
         Self::new()
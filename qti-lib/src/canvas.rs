@@ -0,0 +1,241 @@
+use crate::report::{visit, Severity, SourceMap, ValidationReport};
+use xmltree::{Element, XMLNode};
+
+/// Question types Canvas's QTI importer understands
+const QUESTION_TYPES: [&str; 12] = [
+    "multiple_choice_question",
+    "true_false_question",
+    "short_answer_question",
+    "fill_in_multiple_blanks_question",
+    "multiple_answers_question",
+    "multiple_dropdowns_question",
+    "matching_question",
+    "numerical_question",
+    "calculated_question",
+    "essay_question",
+    "file_upload_question",
+    "text_only_question",
+];
+
+/// Question types whose items legitimately carry several responses
+const MULTI_RESPONSE_TYPES: [&str; 3] = [
+    "fill_in_multiple_blanks_question",
+    "multiple_dropdowns_question",
+    "matching_question",
+];
+
+/// Item metadata Canvas reads, plus the bank item `tags` that group
+/// `selection_metadata` filters refer to; other fields are discarded on import
+const ITEM_FIELD_LABELS: [&str; 5] = [
+    "question_type",
+    "points_possible",
+    "original_answer_ids",
+    "assessment_question_identifierref",
+    "tags",
+];
+
+/// Material Canvas does not import
+const UNSUPPORTED_MATERIAL: [&str; 4] = ["mataudio", "matvideo", "matapplet", "matapplication"];
+
+/// Responses and renderings Canvas has no question type for
+const UNSUPPORTED_RESPONSES: [&str; 5] = [
+    "response_xy",
+    "response_grp",
+    "render_hotspot",
+    "render_slider",
+    "render_extension",
+];
+
+/// Canvas stores idents as migration ids, which are cut off past this length
+pub const MAX_IDENT_LENGTH: usize = 255;
+
+const RESPONSES: [&str; 5] = [
+    "response_lid",
+    "response_str",
+    "response_num",
+    "response_xy",
+    "response_grp",
+];
+
+/// Warn about constructs Canvas drops or mangles when importing `document`
+pub(crate) fn check(document: &Element, source: &SourceMap, report: &mut ValidationReport) {
+    visit(document, &document.name, &mut |element, path| {
+        let mut warn = |code, message: String| {
+            report.push(source, element, path, Severity::Warning, code, message)
+        };
+
+        if let Some(ident) = element.attributes.get("ident") {
+            if ident.chars().count() > MAX_IDENT_LENGTH {
+                warn(
+                    "canvas-ident-length",
+                    format!(
+                        "Ident on '{}' is longer than Canvas's {} characters",
+                        element.name, MAX_IDENT_LENGTH
+                    ),
+                );
+            }
+        }
+
+        let name = element.name.as_str();
+        if UNSUPPORTED_MATERIAL.contains(&name) {
+            warn(
+                "canvas-material",
+                format!("Canvas drops '{}' material on import", name),
+            );
+        }
+        if UNSUPPORTED_RESPONSES.contains(&name) {
+            warn(
+                "canvas-response",
+                format!("Canvas has no question type for '{}'", name),
+            );
+        }
+        if name == "item" {
+            check_item(element, &mut warn);
+        }
+    });
+}
+
+fn check_item(item: &Element, warn: &mut impl FnMut(&'static str, String)) {
+    let fields: Vec<(String, String)> = item
+        .get_child("itemmetadata")
+        .and_then(|metadata| metadata.get_child("qtimetadata"))
+        .map(|metadata| {
+            child_elements(metadata, "qtimetadatafield")
+                .map(|field| {
+                    (
+                        child_text(field, "fieldlabel"),
+                        child_text(field, "fieldentry"),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for (label, _) in &fields {
+        if !ITEM_FIELD_LABELS.contains(&label.as_str()) {
+            warn(
+                "canvas-fieldlabel",
+                format!("Canvas ignores item metadata field '{}'", label),
+            );
+        }
+    }
+
+    let question_type = fields
+        .iter()
+        .find(|(label, _)| label == "question_type")
+        .map(|(_, entry)| entry.as_str());
+    match question_type {
+        None => warn(
+            "canvas-question-type",
+            "Item has no 'question_type' metadata; Canvas guesses the type from its response"
+                .to_string(),
+        ),
+        Some(question_type) if !QUESTION_TYPES.contains(&question_type) => warn(
+            "canvas-question-type",
+            format!("Canvas does not know question type '{}'", question_type),
+        ),
+        Some(_) => {}
+    }
+
+    // Name and ident of every response the learner answers
+    let mut responses: Vec<(String, String)> = Vec::new();
+    if let Some(presentation) = item.get_child("presentation") {
+        visit(presentation, "", &mut |element, _| {
+            if RESPONSES.contains(&element.name.as_str()) {
+                let ident = element.attributes.get("ident").cloned().unwrap_or_default();
+                responses.push((element.name.clone(), ident));
+            }
+        });
+    }
+
+    if responses.len() > 1 && !question_type.is_some_and(|t| MULTI_RESPONSE_TYPES.contains(&t)) {
+        warn(
+            "canvas-multiple-responses",
+            format!(
+                "Canvas keeps only the first of {} responses for this question type",
+                responses.len()
+            ),
+        );
+    }
+
+    let numeric = responses.iter().any(|(name, _)| name == "response_num")
+        || question_type == Some("numerical_question");
+    if numeric {
+        let idents: Vec<&str> = responses.iter().map(|(_, ident)| ident.as_str()).collect();
+        if !has_numeric_answer(item, &idents) {
+            warn(
+                "canvas-numeric-answer",
+                "Numeric response has no varequal/vargte/varlte answer condition; Canvas imports it with no answers"
+                    .to_string(),
+            );
+        }
+    }
+}
+
+/// Whether any response condition compares one of `idents` to a number
+fn has_numeric_answer(item: &Element, idents: &[&str]) -> bool {
+    let Some(resprocessing) = item.get_child("resprocessing") else {
+        return false;
+    };
+    let mut found = false;
+    visit(resprocessing, "", &mut |element, _| {
+        let comparison = matches!(element.name.as_str(), "varequal" | "vargte" | "varlte");
+        let respident = element.attributes.get("respident").map(String::as_str);
+        let number = element
+            .get_text()
+            .is_some_and(|text| text.trim().parse::<f64>().is_ok());
+        if comparison && number && respident.is_some_and(|r| idents.contains(&r)) {
+            found = true;
+        }
+    });
+    found
+}
+
+fn child_elements<'a>(element: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    element.children.iter().filter_map(move |node| match node {
+        XMLNode::Element(child) if child.name == name => Some(child),
+        _ => None,
+    })
+}
+
+fn child_text(element: &Element, name: &str) -> String {
+    element
+        .get_child(name)
+        .and_then(|child| child.get_text())
+        .map(|text| text.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::validator::Validator;
+    use crate::Generator;
+
+    #[test]
+    fn test_canvas_import_rules() {
+        let assessment = crate::Parser::new()
+            .parse("1. Pick one\n*a) x\nb) y\n\n2. What is pi?\n= 3.14 ± 0.01\nungraded")
+            .unwrap();
+        let xml = Generator::new()
+            .with_canvas_extensions()
+            .generate(&assessment)
+            .unwrap();
+        let xml = xml.replacen(
+            "<fieldlabel>points_possible</fieldlabel>",
+            "<fieldlabel>difficulty</fieldlabel>",
+            1,
+        );
+
+        let report = Validator::canvas().report_xml(&xml).unwrap();
+        let codes: Vec<_> = report.violations.iter().map(|v| v.code).collect();
+        assert!(codes.contains(&"canvas-fieldlabel"));
+        assert!(codes.contains(&"canvas-numeric-answer"));
+        assert!(report.is_valid());
+
+        assert!(Validator::new()
+            .report_xml(&xml)
+            .unwrap()
+            .violations
+            .is_empty());
+    }
+}
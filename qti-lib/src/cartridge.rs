@@ -0,0 +1,199 @@
+use crate::error::{QtiError, Result};
+use crate::types::Assessment;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Folder of a Common Cartridge that LMSs import as course files
+pub const WEB_RESOURCES_FOLDER: &str = "web_resources";
+
+/// A course shell: assessments and web pages arranged in modules, exported
+/// as an IMS Common Cartridge (`.imscc`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cartridge {
+    pub title: String,
+    pub identifier: String,
+    pub modules: Vec<Module>,
+}
+
+impl Cartridge {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            identifier: format!("cartridge_{}", Uuid::new_v4()),
+            modules: Vec::new(),
+        }
+    }
+
+    pub fn with_module(mut self, module: Module) -> Self {
+        self.modules.push(module);
+        self
+    }
+
+    /// Every assessment in the course, in module order
+    pub fn assessments(&self) -> Vec<&Assessment> {
+        let mut assessments = Vec::new();
+        for module in &self.modules {
+            module.collect_assessments(&mut assessments);
+        }
+        assessments
+    }
+
+    pub fn assessments_mut(&mut self) -> Vec<&mut Assessment> {
+        let mut assessments = Vec::new();
+        for module in &mut self.modules {
+            module.collect_assessments_mut(&mut assessments);
+        }
+        assessments
+    }
+
+    /// Every web page or file in the course, in module order
+    pub fn web_content(&self) -> Vec<&WebContent> {
+        let mut content = Vec::new();
+        for module in &self.modules {
+            module.collect_web_content(&mut content);
+        }
+        content
+    }
+}
+
+/// A titled module of the course outline; modules may nest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Module {
+    pub id: String,
+    pub title: String,
+    pub items: Vec<ModuleItem>,
+}
+
+impl Module {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            id: format!("module_{}", Uuid::new_v4()),
+            title: title.into(),
+            items: Vec::new(),
+        }
+    }
+
+    pub fn with_assessment(mut self, assessment: Assessment) -> Self {
+        self.items.push(ModuleItem::Assessment(assessment));
+        self
+    }
+
+    pub fn with_web_content(mut self, content: WebContent) -> Self {
+        self.items.push(ModuleItem::WebContent(content));
+        self
+    }
+
+    pub fn with_module(mut self, module: Module) -> Self {
+        self.items.push(ModuleItem::Module(module));
+        self
+    }
+
+    fn collect_assessments<'a>(&'a self, assessments: &mut Vec<&'a Assessment>) {
+        for item in &self.items {
+            match item {
+                ModuleItem::Assessment(assessment) => assessments.push(assessment),
+                ModuleItem::Module(module) => module.collect_assessments(assessments),
+                ModuleItem::WebContent(_) => {}
+            }
+        }
+    }
+
+    fn collect_assessments_mut<'a>(&'a mut self, assessments: &mut Vec<&'a mut Assessment>) {
+        for item in &mut self.items {
+            match item {
+                ModuleItem::Assessment(assessment) => assessments.push(assessment),
+                ModuleItem::Module(module) => module.collect_assessments_mut(assessments),
+                ModuleItem::WebContent(_) => {}
+            }
+        }
+    }
+
+    fn collect_web_content<'a>(&'a self, content: &mut Vec<&'a WebContent>) {
+        for item in &self.items {
+            match item {
+                ModuleItem::WebContent(page) => content.push(page),
+                ModuleItem::Module(module) => module.collect_web_content(content),
+                ModuleItem::Assessment(_) => {}
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ModuleItem {
+    Assessment(Assessment),
+    WebContent(WebContent),
+    Module(Module),
+}
+
+/// A page or file shipped under `web_resources/`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebContent {
+    pub id: String,
+    pub title: String,
+    /// Path relative to `web_resources/`, e.g. `week1/syllabus.html`
+    pub filename: String,
+    pub content: Vec<u8>,
+}
+
+impl WebContent {
+    pub fn new(
+        title: impl Into<String>,
+        filename: impl Into<String>,
+        content: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            id: format!("webcontent_{}", Uuid::new_v4()),
+            title: title.into(),
+            filename: filename.into(),
+            content: content.into(),
+        }
+    }
+
+    /// Path of the file inside the cartridge
+    pub fn path(&self) -> Result<String> {
+        let relative = self.filename.trim_start_matches("./");
+        if relative.is_empty()
+            || relative.starts_with('/')
+            || relative.contains('\\')
+            || relative
+                .split('/')
+                .any(|part| part.is_empty() || part == "..")
+        {
+            return Err(QtiError::ValidationError(format!(
+                "Web content path must be relative to {}/: {}",
+                WEB_RESOURCES_FOLDER, self.filename
+            )));
+        }
+        Ok(format!("{}/{}", WEB_RESOURCES_FOLDER, relative))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cartridge_outline() {
+        let cartridge = Cartridge::new("Biology")
+            .with_module(
+                Module::new("Week 1")
+                    .with_web_content(WebContent::new("Syllabus", "syllabus.html", "<p>Hi</p>"))
+                    .with_module(Module::new("Labs").with_assessment(Assessment::new("Lab Quiz"))),
+            )
+            .with_module(Module::new("Week 2").with_assessment(Assessment::new("Quiz 2")));
+
+        let titles: Vec<_> = cartridge
+            .assessments()
+            .iter()
+            .map(|a| a.title.as_str())
+            .collect();
+        assert_eq!(titles, ["Lab Quiz", "Quiz 2"]);
+        assert_eq!(
+            cartridge.web_content()[0].path().unwrap(),
+            "web_resources/syllabus.html"
+        );
+        assert!(WebContent::new("Bad", "../secret", "").path().is_err());
+        assert!(WebContent::new("Bad", "/etc/passwd", "").path().is_err());
+    }
+}
@@ -0,0 +1,449 @@
+use crate::error::{QtiError, Result};
+use crate::schema::{AttributeDef, ElementDef, Particle, QtiSchema, Term};
+use std::collections::HashMap;
+
+/// Build a schema from a DTD: `<!ELEMENT>` content models (sequences,
+/// choices, `?`/`*`/`+`, mixed content, `EMPTY` and `ANY`) and `<!ATTLIST>`
+/// declarations, with parameter entities expanded. The root is the first
+/// declared element. Undeclared attributes are rejected, as DTD validators do.
+pub fn parse(dtd: &str) -> Result<QtiSchema> {
+    let text = expand_entities(&strip_comments(dtd))?;
+
+    let mut elements: Vec<ElementDef> = Vec::new();
+    let mut attributes: HashMap<String, Vec<AttributeDef>> = HashMap::new();
+    for declaration in declarations(&text)? {
+        let tokens = tokenize(declaration)?;
+        match tokens.first().map(String::as_str) {
+            Some("ELEMENT") => elements.push(element_def(&tokens[1..])?),
+            Some("ATTLIST") => {
+                let (name, defs) = attribute_list(&tokens[1..])?;
+                attributes.entry(name).or_default().extend(defs);
+            }
+            _ => {}
+        }
+    }
+
+    for element in &mut elements {
+        element.attributes = attributes.remove(&element.name).unwrap_or_default();
+    }
+    if let Some(name) = attributes.keys().next() {
+        return Err(dtd_error(format!(
+            "ATTLIST for undeclared element '{}'",
+            name
+        )));
+    }
+
+    let root = elements
+        .first_mut()
+        .ok_or_else(|| dtd_error("DTD declares no elements".to_string()))?;
+    root.required = true;
+
+    Ok(QtiSchema {
+        root: root.name.clone(),
+        elements,
+        strict_attributes: true,
+    })
+}
+
+fn dtd_error(message: String) -> QtiError {
+    QtiError::ValidationError(format!("Invalid DTD: {}", message))
+}
+
+fn strip_comments(dtd: &str) -> String {
+    let mut text = String::new();
+    let mut rest = dtd;
+    while let Some(start) = rest.find("<!--") {
+        text.push_str(&rest[..start]);
+        rest = match rest[start..].find("-->") {
+            Some(end) => &rest[start + end + 3..],
+            None => "",
+        };
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Replace `%name;` references with the values of `<!ENTITY % name "...">`
+fn expand_entities(text: &str) -> Result<String> {
+    let mut entities = HashMap::new();
+    for declaration in declarations(text)? {
+        let tokens = tokenize(declaration)?;
+        if let [keyword, percent, name, value] = tokens.as_slice() {
+            if keyword == "ENTITY" && percent == "%" {
+                entities.insert(name.clone(), unquote(value).to_string());
+            }
+        }
+    }
+
+    let mut text = text.to_string();
+    // Entity values may themselves refer to entities
+    for _ in 0..8 {
+        let mut changed = false;
+        for (name, value) in &entities {
+            let reference = format!("%{};", name);
+            if text.contains(&reference) {
+                text = text.replace(&reference, value);
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(text);
+        }
+    }
+    Err(dtd_error("parameter entities nest too deeply".to_string()))
+}
+
+/// Bodies of the `<!...>` markup declarations, without the brackets
+fn declarations(text: &str) -> Result<Vec<&str>> {
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<!") {
+        let body = &rest[start + 2..];
+        let mut quote = None;
+        let end = body
+            .char_indices()
+            .find(|&(_, c)| match quote {
+                Some(q) => {
+                    if c == q {
+                        quote = None;
+                    }
+                    false
+                }
+                None if c == '"' || c == '\'' => {
+                    quote = Some(c);
+                    false
+                }
+                None => c == '>',
+            })
+            .map(|(index, _)| index)
+            .ok_or_else(|| dtd_error("unterminated declaration".to_string()))?;
+        found.push(&body[..end]);
+        rest = &body[end + 1..];
+    }
+    Ok(found)
+}
+
+/// Names, quoted literals (kept with their quotes) and punctuation
+fn tokenize(declaration: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut chars = declaration.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut literal = String::from(c);
+            loop {
+                match chars.next() {
+                    Some(next) if next == c => break,
+                    Some(next) => literal.push(next),
+                    None => return Err(dtd_error("unterminated literal".to_string())),
+                }
+            }
+            literal.push(c);
+            tokens.push(literal);
+        } else if "()|,?*+%".contains(c) {
+            chars.next();
+            tokens.push(c.to_string());
+        } else {
+            let mut name = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_whitespace() || "()|,?*+\"'".contains(next) {
+                    break;
+                }
+                name.push(next);
+                chars.next();
+            }
+            tokens.push(name);
+        }
+    }
+    Ok(tokens)
+}
+
+fn unquote(literal: &str) -> &str {
+    literal
+        .strip_prefix(['"', '\''])
+        .and_then(|l| l.strip_suffix(['"', '\'']))
+        .unwrap_or(literal)
+}
+
+fn element_def(tokens: &[String]) -> Result<ElementDef> {
+    let name = tokens
+        .first()
+        .ok_or_else(|| dtd_error("ELEMENT without a name".to_string()))?
+        .clone();
+    let model = &tokens[1..];
+
+    let content = match model.first().map(String::as_str) {
+        Some("EMPTY") => Particle::empty(),
+        Some("ANY") => Particle {
+            term: Term::Any,
+            min_occurs: 0,
+            max_occurs: None,
+        },
+        Some("(") if model.get(1).is_some_and(|t| t == "#PCDATA") => mixed_content(&name, model)?,
+        Some("(") => {
+            let mut position = 0;
+            let particle = content_particle(model, &mut position)?;
+            if position != model.len() {
+                return Err(dtd_error(format!(
+                    "unexpected '{}' in content model of '{}'",
+                    model[position], name
+                )));
+            }
+            particle
+        }
+        _ => return Err(dtd_error(format!("missing content model for '{}'", name))),
+    };
+
+    let mut children = Vec::new();
+    collect_names(&content, &mut children);
+    Ok(ElementDef {
+        name,
+        attributes: Vec::new(),
+        required: false,
+        children,
+        content: Some(content),
+    })
+}
+
+/// `(#PCDATA)` or `(#PCDATA | a | b)*`: text mixed with the named elements in any order
+fn mixed_content(name: &str, model: &[String]) -> Result<Particle> {
+    let close = model
+        .iter()
+        .position(|t| t == ")")
+        .ok_or_else(|| dtd_error(format!("unclosed content model of '{}'", name)))?;
+    let items: Vec<Particle> = model[2..close]
+        .iter()
+        .filter(|t| *t != "|")
+        .map(|t| occurs(Term::Element(t.clone()), None))
+        .collect();
+    if items.is_empty() {
+        return Ok(Particle::empty());
+    }
+    Ok(Particle {
+        term: Term::Choice(items),
+        min_occurs: 0,
+        max_occurs: None,
+    })
+}
+
+/// A name or parenthesized group, followed by an optional `?`, `*` or `+`
+fn content_particle(tokens: &[String], position: &mut usize) -> Result<Particle> {
+    let token = tokens
+        .get(*position)
+        .ok_or_else(|| dtd_error("content model ends early".to_string()))?;
+    *position += 1;
+
+    let term = if token == "(" {
+        let mut items = vec![content_particle(tokens, position)?];
+        let mut separator = None;
+        loop {
+            match tokens.get(*position).map(String::as_str) {
+                Some(")") => {
+                    *position += 1;
+                    break;
+                }
+                Some(next @ ("," | "|")) if separator.is_none_or(|s| s == next) => {
+                    separator = Some(next);
+                    *position += 1;
+                    items.push(content_particle(tokens, position)?);
+                }
+                other => {
+                    return Err(dtd_error(format!(
+                        "unexpected '{}' in content model",
+                        other.unwrap_or("end")
+                    )))
+                }
+            }
+        }
+        if separator == Some("|") {
+            Term::Choice(items)
+        } else {
+            Term::Sequence(items)
+        }
+    } else {
+        Term::Element(token.clone())
+    };
+
+    let suffix = tokens
+        .get(*position)
+        .filter(|t| matches!(t.as_str(), "?" | "*" | "+"))
+        .map(String::as_str);
+    if suffix.is_some() {
+        *position += 1;
+    }
+    Ok(occurs(term, suffix))
+}
+
+fn occurs(term: Term, suffix: Option<&str>) -> Particle {
+    let (min_occurs, max_occurs) = match suffix {
+        Some("?") => (0, Some(1)),
+        Some("*") => (0, None),
+        Some("+") => (1, None),
+        _ => (1, Some(1)),
+    };
+    Particle {
+        term,
+        min_occurs,
+        max_occurs,
+    }
+}
+
+fn collect_names(particle: &Particle, names: &mut Vec<String>) {
+    match &particle.term {
+        Term::Element(name) => {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        Term::Sequence(items) | Term::Choice(items) => {
+            for item in items {
+                collect_names(item, names);
+            }
+        }
+        Term::Any => {}
+    }
+}
+
+/// `name (type default)*` of an `<!ATTLIST>`
+fn attribute_list(tokens: &[String]) -> Result<(String, Vec<AttributeDef>)> {
+    let element = tokens
+        .first()
+        .ok_or_else(|| dtd_error("ATTLIST without an element name".to_string()))?
+        .clone();
+
+    let mut defs = Vec::new();
+    let mut position = 1;
+    while let Some(name) = tokens.get(position) {
+        position += 1;
+
+        let mut values = None;
+        match tokens.get(position).map(String::as_str) {
+            Some("(") => {
+                let close = tokens[position..]
+                    .iter()
+                    .position(|t| t == ")")
+                    .map(|offset| position + offset)
+                    .ok_or_else(|| dtd_error(format!("unclosed values of '{}'", name)))?;
+                values = Some(
+                    tokens[position + 1..close]
+                        .iter()
+                        .filter(|t| *t != "|")
+                        .cloned()
+                        .collect(),
+                );
+                position = close + 1;
+            }
+            Some("NOTATION") => {
+                // NOTATION (a | b): the values are notation names
+                position += 1;
+                let close = tokens[position..]
+                    .iter()
+                    .position(|t| t == ")")
+                    .map(|offset| position + offset)
+                    .ok_or_else(|| dtd_error(format!("unclosed values of '{}'", name)))?;
+                position = close + 1;
+            }
+            Some(_) => position += 1,
+            None => return Err(dtd_error(format!("attribute '{}' has no type", name))),
+        }
+
+        let required = match tokens.get(position).map(String::as_str) {
+            Some("#REQUIRED") => {
+                position += 1;
+                true
+            }
+            Some("#IMPLIED") => {
+                position += 1;
+                false
+            }
+            Some("#FIXED") => {
+                let fixed = tokens.get(position + 1).map(|v| unquote(v).to_string());
+                values = fixed.map(|v| vec![v]);
+                position += 2;
+                false
+            }
+            Some(literal) if literal.starts_with(['"', '\'']) => {
+                position += 1;
+                false
+            }
+            other => {
+                return Err(dtd_error(format!(
+                    "unexpected '{}' for attribute '{}' of '{}'",
+                    other.unwrap_or("end"),
+                    name,
+                    element
+                )))
+            }
+        };
+
+        defs.push(AttributeDef {
+            name: name.clone(),
+            required,
+            values,
+        });
+    }
+    Ok((element, defs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::Element;
+
+    #[test]
+    fn test_parse_qti_dtd() {
+        let schema = QtiSchema::qti_1_2_dtd();
+        assert_eq!(schema.root, "questestinterop");
+        assert!(schema.elements.len() > 100);
+
+        let item = schema.element("item").unwrap();
+        let ident = item.attributes.iter().find(|a| a.name == "ident").unwrap();
+        assert!(ident.required);
+        let setvar = schema.element("setvar").unwrap();
+        let action = setvar
+            .attributes
+            .iter()
+            .find(|a| a.name == "action")
+            .unwrap();
+        assert_eq!(action.values.as_ref().unwrap().len(), 5);
+
+        let parse = |xml: &str| Element::parse(xml.as_bytes()).unwrap();
+        let legacy = r#"<?xml version="1.0"?>
+<!DOCTYPE questestinterop SYSTEM "ims_qtiasiv1p2p1.dtd">
+<questestinterop>
+  <item ident="q1" title="Legacy">
+    <presentation>
+      <material><mattext>2 + 2?</mattext></material>
+      <response_lid ident="r1" rcardinality="Single">
+        <render_choice shuffle="No">
+          <response_label ident="a"><material><mattext>4</mattext></material></response_label>
+        </render_choice>
+      </response_lid>
+    </presentation>
+    <resprocessing>
+      <outcomes><decvar/></outcomes>
+      <respcondition>
+        <conditionvar><varequal respident="r1">a</varequal></conditionvar>
+        <setvar action="Set">1</setvar>
+      </respcondition>
+    </resprocessing>
+  </item>
+</questestinterop>"#;
+        schema
+            .validate(&parse(legacy))
+            .expect("Legacy item should be valid");
+
+        let bad_value = legacy.replace("action=\"Set\"", "action=\"Replace\"");
+        assert!(schema.validate(&parse(&bad_value)).is_err());
+        let undeclared = legacy.replace("title=\"Legacy\"", "colour=\"red\"");
+        let error = schema.validate(&parse(&undeclared)).unwrap_err();
+        assert!(error.to_string().contains("Undeclared attribute 'colour'"));
+        let misordered = legacy.replace(
+            "<outcomes><decvar/></outcomes>\n      <respcondition>",
+            "<respcondition>",
+        );
+        assert!(schema.validate(&parse(&misordered)).is_err());
+    }
+}
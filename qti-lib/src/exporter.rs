@@ -1,7 +1,13 @@
+use crate::cartridge::Cartridge;
 use crate::error::{QtiError, Result};
 use crate::generator::{Generator, QtiPackage};
+use crate::ids;
+use crate::lite::LiteExport;
+use crate::math::MathTarget;
+use crate::media::{self, MediaMode};
 use crate::types::Assessment;
 use crate::validator::Validator;
+use std::borrow::Cow;
 use std::io::{Seek, Write};
 use std::path::Path;
 use zip::write::FileOptions;
@@ -12,6 +18,9 @@ pub struct Exporter {
     generator: Generator,
     validator: Validator,
     validate_before_export: bool,
+    media_mode: MediaMode,
+    stable_ids: bool,
+    answer_key_check: bool,
 }
 
 impl Exporter {
@@ -20,6 +29,9 @@ impl Exporter {
             generator: Generator::new(),
             validator: Validator::new(),
             validate_before_export: true,
+            media_mode: MediaMode::default(),
+            stable_ids: false,
+            answer_key_check: false,
         }
     }
 
@@ -28,11 +40,95 @@ impl Exporter {
         self
     }
 
+    pub fn with_math_target(mut self, target: MathTarget) -> Self {
+        self.generator = self.generator.with_math_target(target);
+        self
+    }
+
+    /// Leave out short-answer patterns QTI cannot express instead of failing
+    pub fn with_unsupported_patterns(mut self) -> Self {
+        self.generator = self.generator.with_unsupported_patterns();
+        self
+    }
+
     pub fn skip_validation(mut self) -> Self {
         self.validate_before_export = false;
         self
     }
 
+    /// Choose whether media travel as packaged files or inline `data:` URIs
+    pub fn media_mode(mut self, mode: MediaMode) -> Self {
+        self.media_mode = mode;
+        self
+    }
+
+    /// Derive identifiers from content so rebuilding an unchanged quiz gives the same idents
+    pub fn with_stable_ids(mut self) -> Self {
+        self.stable_ids = true;
+        self
+    }
+
+    /// Fail the export when a correct answer would not earn full points under the
+    /// generated response processing, or a distractor would earn any
+    pub fn with_answer_key_check(mut self) -> Self {
+        self.answer_key_check = true;
+        self
+    }
+
+    fn check_answer_key(&self, assessment: &Assessment) -> Result<()> {
+        if !self.answer_key_check {
+            return Ok(());
+        }
+        let problems = self.generator.check_answer_key(assessment)?;
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(QtiError::ValidationError(format!(
+            "Answer key check failed: {}",
+            problems
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; ")
+        )))
+    }
+
+    /// Apply the media mode and id mode to a copy of the assessment when they change anything
+    fn prepare<'a>(&self, assessment: &'a Assessment) -> Result<Cow<'a, Assessment>> {
+        if self.media_mode == MediaMode::Files && !self.stable_ids {
+            return Ok(Cow::Borrowed(assessment));
+        }
+
+        let mut assessment = assessment.clone();
+        match self.media_mode {
+            MediaMode::Files => {}
+            MediaMode::Inline => media::inline_media(&mut assessment),
+            MediaMode::Extract => media::extract_data_uris(&mut assessment)?,
+        }
+        if self.stable_ids {
+            ids::assign_stable_ids(&mut assessment);
+        }
+        Ok(Cow::Owned(assessment))
+    }
+
+    /// Apply the media mode and id mode to every assessment of a cartridge
+    fn prepare_cartridge<'a>(&self, cartridge: &'a Cartridge) -> Result<Cow<'a, Cartridge>> {
+        if self.media_mode == MediaMode::Files && !self.stable_ids {
+            return Ok(Cow::Borrowed(cartridge));
+        }
+
+        let mut cartridge = cartridge.clone();
+        for assessment in cartridge.assessments_mut() {
+            if let Cow::Owned(prepared) = self.prepare(assessment)? {
+                *assessment = prepared;
+            }
+        }
+        if self.stable_ids {
+            ids::assign_cartridge_ids(&mut cartridge);
+        }
+        Ok(Cow::Owned(cartridge))
+    }
+
     /// Export assessment to a QTI zip file
     pub fn export_to_file(&self, assessment: &Assessment, path: impl AsRef<Path>) -> Result<()> {
         let file = std::fs::File::create(path)?;
@@ -45,7 +141,9 @@ impl Exporter {
         assessment: &Assessment,
         writer: W,
     ) -> Result<()> {
-        let package = self.generator.generate_package(assessment)?;
+        let assessment = self.prepare(assessment)?;
+        self.check_answer_key(&assessment)?;
+        let package = self.generator.generate_package(&assessment)?;
 
         if self.validate_before_export {
             self.validator.validate_xml(&package.assessment_xml)?;
@@ -79,6 +177,57 @@ impl Exporter {
         Ok(())
     }
 
+    /// Export a course to an IMS Common Cartridge (`.imscc`) file
+    pub fn export_cartridge_to_file(
+        &self,
+        cartridge: &Cartridge,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        self.export_cartridge_to_writer(cartridge, file)
+    }
+
+    /// Export a course as a Common Cartridge to a writer
+    pub fn export_cartridge_to_writer<W: Write + Seek>(
+        &self,
+        cartridge: &Cartridge,
+        writer: W,
+    ) -> Result<()> {
+        let cartridge = self.prepare_cartridge(cartridge)?;
+        for assessment in cartridge.assessments() {
+            self.check_answer_key(assessment)?;
+        }
+        let package = self.generator.generate_cartridge(&cartridge)?;
+
+        if self.validate_before_export {
+            for assessment in &package.assessments {
+                let xml = std::str::from_utf8(&assessment.content)?;
+                self.validator.validate_xml(xml)?;
+            }
+        }
+
+        let mut zip = ZipWriter::new(writer);
+        let options = FileOptions::<()>::default()
+            .compression_method(CompressionMethod::Deflated)
+            .unix_permissions(0o644);
+
+        zip.start_file("imsmanifest.xml", options)?;
+        zip.write_all(package.manifest_xml.as_bytes())?;
+        for resource in package.assessments.iter().chain(&package.resources) {
+            zip.start_file(&resource.filename, options)?;
+            zip.write_all(&resource.content)?;
+        }
+
+        zip.finish()?;
+        Ok(())
+    }
+
+    pub fn export_cartridge_to_memory(&self, cartridge: &Cartridge) -> Result<Vec<u8>> {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        self.export_cartridge_to_writer(cartridge, &mut buffer)?;
+        Ok(buffer.into_inner())
+    }
+
     fn extract_assessment_ident(&self, xml: &str) -> Result<String> {
         let element = xmltree::Element::parse(xml.as_bytes())
             .map_err(|e| QtiError::XmlError(format!("Failed to parse XML: {}", e)))?;
@@ -95,7 +244,8 @@ impl Exporter {
     }
 
     pub fn export_to_xml(&self, assessment: &Assessment) -> Result<String> {
-        let xml = self.generator.generate(assessment)?;
+        let assessment = self.prepare(assessment)?;
+        let xml = self.generator.generate(&assessment)?;
 
         if self.validate_before_export {
             self.validator.validate_xml(&xml)?;
@@ -104,6 +254,18 @@ impl Exporter {
         Ok(xml)
     }
 
+    /// Export to QTI-Lite XML, listing the questions Lite cannot represent
+    pub fn export_lite(&self, assessment: &Assessment) -> Result<LiteExport> {
+        let assessment = self.prepare(assessment)?;
+        let export = self.generator.generate_lite(&assessment)?;
+
+        if self.validate_before_export {
+            Validator::qti_lite().validate_xml(&export.xml)?;
+        }
+
+        Ok(export)
+    }
+
     pub fn export_to_memory(&self, assessment: &Assessment) -> Result<Vec<u8>> {
         let mut buffer = std::io::Cursor::new(Vec::new());
         self.export_to_writer(assessment, &mut buffer)?;
@@ -122,6 +284,7 @@ mod tests {
     use super::*;
     use crate::types::{Choice, Question, QuestionType};
     use tempfile::NamedTempFile;
+    use xmltree::Element;
 
     #[test]
     fn test_export_to_file() {
@@ -159,6 +322,283 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_export_packages_media() {
+        let mut assessment = Assessment::new("Media Quiz");
+        assessment.identifier = "media_quiz".to_string();
+        assessment.questions.push(Question::new(
+            "Describe ![the diagram](media/diagram.png)",
+            QuestionType::Essay {
+                expected_length: None,
+                rich_text: true,
+            },
+        ));
+        assessment.media.push(crate::types::MediaFile {
+            filename: "media/diagram.png".to_string(),
+            content: b"png".to_vec(),
+        });
+
+        let exporter = Exporter::new().with_canvas_extensions();
+        let data = exporter
+            .export_to_memory(&assessment)
+            .expect("Should export to memory");
+
+        let mut archive =
+            zip::ZipArchive::new(std::io::Cursor::new(data)).expect("Should be valid zip file");
+        assert!(archive.by_name("media/diagram.png").is_ok());
+
+        let mut manifest = String::new();
+        std::io::Read::read_to_string(
+            &mut archive.by_name("imsmanifest.xml").unwrap(),
+            &mut manifest,
+        )
+        .unwrap();
+        assert!(manifest.contains("<file href=\"media/diagram.png\""));
+        assert!(manifest.contains("<dependency identifierref=\"media_quiz_media\""));
+        assert!(
+            manifest.contains("<file href=\"non_cc_assessments/media_quiz/assessment_meta.xml\"")
+        );
+
+        let mut meta = String::new();
+        std::io::Read::read_to_string(
+            &mut archive
+                .by_name("non_cc_assessments/media_quiz/assessment_meta.xml")
+                .unwrap(),
+            &mut meta,
+        )
+        .unwrap();
+        assert!(meta.contains("<quiz identifier=\"media_quiz\""));
+        assert!(meta.contains("<points_possible>1</points_possible>"));
+        assert!(!meta.contains("<allowed_attempts>"));
+
+        let mut xml = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("media_quiz.xml").unwrap(), &mut xml)
+            .unwrap();
+        assert!(xml.contains("$IMS-CC-FILEBASE$/media/diagram.png"));
+        assert!(!xml.contains("cc_maxattempts"));
+
+        assessment.metadata.allowed_attempts = Some(crate::types::Attempts::Limited(2));
+        let xml = exporter.export_to_xml(&assessment).unwrap();
+        assert!(xml.contains("<fieldlabel>cc_maxattempts</fieldlabel>"));
+        assert!(xml.contains("<fieldentry>2</fieldentry>"));
+        let xml = Exporter::new().export_to_xml(&assessment).unwrap();
+        assert!(!xml.contains("cc_maxattempts"));
+
+        let inline = Exporter::new()
+            .media_mode(MediaMode::Inline)
+            .export_to_xml(&assessment)
+            .expect("Should export to XML");
+        assert!(inline.contains("data:image/png;base64,cG5n"));
+        assert!(!inline.contains("media/diagram.png"));
+    }
+
+    #[test]
+    fn test_answer_key_check() {
+        let mut assessment = crate::Parser::new()
+            .parse("1. Pick one\n*a) x\nb) y\n\n2. Pick some\n[*] a\n[ ] b\n[*] c\n[*] d\n\n3. What is pi?\n= 3.14 ± 0.01\n\n4. Roses are {*red|blue}, violets are {red|*blue}.")
+            .unwrap();
+        assessment.questions.push(Question::new(
+            "The sky is blue",
+            QuestionType::TrueFalse {
+                correct_answer: false,
+            },
+        ));
+        let mut all_or_nothing = assessment.questions[1].clone();
+        all_or_nothing.id = "all_or_nothing".to_string();
+        if let QuestionType::MultipleAnswer { partial_credit, .. } =
+            &mut all_or_nothing.question_type
+        {
+            *partial_credit = false;
+        }
+        assessment.questions.push(all_or_nothing);
+        let exporter = Exporter::new().with_answer_key_check();
+        assert!(exporter.export_to_memory(&assessment).is_ok());
+        assert!(exporter
+            .with_canvas_extensions()
+            .export_to_memory(&assessment)
+            .is_ok());
+
+        // The first answer matches "Red" too, so the partial-credit one can never score
+        let mut partial = crate::types::AcceptableAnswer::new("Red");
+        partial.weight = 0.5;
+        assessment.questions.push(Question::new(
+            "Colour?",
+            QuestionType::ShortAnswer {
+                answers: vec![crate::types::AcceptableAnswer::new("red"), partial],
+                case_sensitive: false,
+            },
+        ));
+        let error = Exporter::new()
+            .with_answer_key_check()
+            .export_to_memory(&assessment)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("answer 'Red' scores 1 but should score 0.5"));
+        assert!(Exporter::new().export_to_memory(&assessment).is_ok());
+    }
+
+    #[test]
+    fn test_export_question_bank() {
+        let mut bank = crate::types::QuestionBank::new("Capitals");
+        for (city, tag) in [("Paris", "europe"), ("Rome", "europe"), ("Lima", "america")] {
+            let mut question = Question::new(
+                format!("Which city is {}?", city),
+                QuestionType::ShortAnswer {
+                    answers: vec![crate::types::AcceptableAnswer::new(city)],
+                    case_sensitive: false,
+                },
+            );
+            question.tags = vec![tag.to_string()];
+            bank.questions.push(question);
+        }
+
+        let mut assessment = Assessment::new("Bank Quiz");
+        assessment.identifier = "bank_quiz".to_string();
+        assessment.add_group_from_bank(&bank, &["europe".to_string()], Some(1), 2.0);
+        assessment.add_group_from_bank(&bank, &[], Some(1), 1.0);
+        assessment.pull_from_bank(&bank, &[], Some(1));
+        let bank_id = assessment.groups[0].bank.clone().unwrap().id;
+        assert_eq!(assessment.groups[1].bank.clone().unwrap().id, bank_id);
+
+        let data = Exporter::new()
+            .with_canvas_extensions()
+            .export_to_memory(&assessment)
+            .expect("Should export to memory");
+        let mut archive =
+            zip::ZipArchive::new(std::io::Cursor::new(&data)).expect("Should be valid zip file");
+
+        let read = |archive: &mut zip::ZipArchive<_>, name: &str| {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut archive.by_name(name).unwrap(), &mut text).unwrap();
+            text
+        };
+        let xml = read(&mut archive, "bank_quiz.xml");
+        assert_eq!(
+            xml.matches(&format!("<sourcebank_ref>{}</sourcebank_ref>", bank_id))
+                .count(),
+            2
+        );
+        assert!(xml.contains(
+            "<selection_metadata mdname=\"tags\" mdoperator=\"EQ\">europe</selection_metadata>"
+        ));
+        // Only the pulled copy is written into the quiz, under an ident of its own
+        assert_eq!(xml.matches("<item ").count(), 1);
+        assert!(!xml.contains(&format!("ident=\"{}\"", bank.questions[0].id)));
+
+        let bank_xml = read(&mut archive, &format!("banks/{}.xml", bank_id));
+        assert!(bank_xml.contains("<objectbank"));
+        assert_eq!(bank_xml.matches("<item ").count(), 3);
+        assert!(bank_xml.contains(&format!("ident=\"{}\"", bank.questions[0].id)));
+        assert!(bank_xml.contains("<fieldentry>europe</fieldentry>"));
+        crate::validator::Validator::new()
+            .validate_xml(&bank_xml)
+            .expect("Bank should be valid");
+
+        let manifest = read(&mut archive, "imsmanifest.xml");
+        assert!(manifest.contains(&format!("<dependency identifierref=\"{}\"", bank_id)));
+
+        let report = crate::validator::Validator::canvas()
+            .report_package(std::io::Cursor::new(data))
+            .unwrap();
+        let issues: Vec<_> = report.errors().chain(report.warnings()).collect();
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn test_export_cartridge() {
+        use crate::cartridge::{Module, WebContent};
+
+        let quiz = |title: &str, identifier: &str| {
+            let mut assessment = Assessment::new(title);
+            assessment.identifier = identifier.to_string();
+            assessment.questions.push(Question::new(
+                "See ![the map](media/map.png)",
+                QuestionType::Essay {
+                    expected_length: None,
+                    rich_text: true,
+                },
+            ));
+            assessment.media.push(crate::types::MediaFile {
+                filename: "media/map.png".to_string(),
+                content: b"png".to_vec(),
+            });
+            assessment
+        };
+        let syllabus = WebContent::new("Syllabus", "syllabus.html", "<h1>Syllabus</h1>");
+        let syllabus_id = syllabus.id.clone();
+        let cartridge = Cartridge::new("Geography 101")
+            .with_module(
+                Module::new("Week 1")
+                    .with_web_content(syllabus)
+                    .with_assessment(quiz("Quiz 1", "quiz_1")),
+            )
+            .with_module(
+                Module::new("Week 2")
+                    .with_module(Module::new("Review").with_assessment(quiz("Quiz 2", "quiz_2"))),
+            );
+
+        let data = Exporter::new()
+            .with_canvas_extensions()
+            .export_cartridge_to_memory(&cartridge)
+            .expect("Should export cartridge");
+        let mut archive =
+            zip::ZipArchive::new(std::io::Cursor::new(data)).expect("Should be valid zip file");
+        for name in [
+            "quiz_1/assessment_qti.xml",
+            "quiz_1/assessment_meta.xml",
+            "quiz_2/assessment_qti.xml",
+            "web_resources/media/map.png",
+            "web_resources/syllabus.html",
+        ] {
+            assert!(archive.by_name(name).is_ok(), "missing {}", name);
+        }
+        assert_eq!(archive.len(), 7);
+
+        let mut manifest = String::new();
+        std::io::Read::read_to_string(
+            &mut archive.by_name("imsmanifest.xml").unwrap(),
+            &mut manifest,
+        )
+        .unwrap();
+        assert!(manifest.contains("<lomimscc:string>Geography 101</lomimscc:string>"));
+        assert!(manifest.contains("structure=\"rooted-hierarchy\""));
+        assert!(manifest.contains("<title>Review</title>"));
+        assert!(manifest
+            .contains("<item identifier=\"quiz_2_item\" identifierref=\"quiz_2_resource\">"));
+        assert!(manifest.contains(&format!("identifierref=\"{}\"", syllabus_id)));
+        assert!(manifest.contains("type=\"imsqti_xmlv1p2/imscc_xmlv1p1/assessment\""));
+
+        let duplicate = Cartridge::new("Twice").with_module(
+            Module::new("Week 1")
+                .with_assessment(quiz("Quiz 1", "quiz_1"))
+                .with_assessment(quiz("Again", "quiz_1")),
+        );
+        assert!(Exporter::new()
+            .export_cartridge_to_memory(&duplicate)
+            .is_err());
+
+        let stable_manifest = || {
+            let course = Cartridge::new("Geography 101").with_module(
+                Module::new("Week 1")
+                    .with_web_content(WebContent::new("Syllabus", "syllabus.html", "<p>Hi</p>"))
+                    .with_module(Module::new("Review").with_assessment(Assessment::new("Quiz"))),
+            );
+            let data = Exporter::new()
+                .with_stable_ids()
+                .export_cartridge_to_memory(&course)
+                .expect("Should export cartridge");
+            let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+            let mut manifest = String::new();
+            std::io::Read::read_to_string(
+                &mut archive.by_name("imsmanifest.xml").unwrap(),
+                &mut manifest,
+            )
+            .unwrap();
+            manifest
+        };
+        assert_eq!(stable_manifest(), stable_manifest());
+    }
+
     #[test]
     fn test_export_to_xml() {
         let mut assessment = Assessment::new("XML Test");
@@ -179,5 +619,59 @@ mod tests {
         assert!(xml.contains("questestinterop"));
         assert!(xml.contains("assessment"));
         assert!(xml.contains("Test question"));
+
+        let mut rebuilt = assessment.clone();
+        rebuilt.identifier = Assessment::new("XML Test").identifier;
+        rebuilt.questions[0].id = Question::new("", QuestionType::TextOnly).id;
+        let stable = Exporter::new().with_stable_ids();
+        assert_eq!(
+            stable.export_to_xml(&assessment).unwrap(),
+            stable.export_to_xml(&rebuilt).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_split_group_exports_one_section() {
+        let mut assessment = crate::Parser::new()
+            .parse("group: G, pick 1\n1. A?\n* a\n\n2. B?\n* b\nend group\n\n3. C?\n* c")
+            .unwrap();
+        assessment.questions.swap(1, 2);
+        let group_id = assessment.groups[0].id.clone();
+
+        let xml = Exporter::new().export_to_xml(&assessment).unwrap();
+        assert_eq!(xml.matches(&format!("ident=\"{}\"", group_id)).count(), 1);
+        let root = Element::parse(xml.as_bytes()).unwrap();
+        let group_section = root
+            .get_child("assessment")
+            .and_then(|a| a.get_child("section"))
+            .and_then(|s| s.get_child("section"))
+            .unwrap();
+        assert_eq!(
+            group_section
+                .children
+                .iter()
+                .filter(|c| c.as_element().is_some_and(|e| e.name == "item"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_unsupported_pattern() {
+        let assessment = crate::Parser::new()
+            .parse("1. Any word?\n* /[a-z]+/\n* word")
+            .unwrap();
+        let error = Exporter::new()
+            .export_to_xml(&assessment)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(&assessment.questions[0].id));
+        assert!(error.contains("/[a-z]+/"));
+
+        let xml = Exporter::new()
+            .with_unsupported_patterns()
+            .export_to_xml(&assessment)
+            .unwrap();
+        assert!(xml.contains(">word</varequal>"));
     }
 }
@@ -0,0 +1,446 @@
+use crate::error::{QtiError, Result};
+use crate::types::{FormulaAnswerSet, FormulaVariable};
+use std::collections::HashMap;
+
+/// Parsed arithmetic expression used by formula questions
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Variable(String),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+    Function(String, Box<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+const FUNCTIONS: &[&str] = &[
+    "sqrt", "abs", "sin", "cos", "tan", "asin", "acos", "atan", "ln", "log", "exp", "round",
+    "floor", "ceil",
+];
+
+impl Expression {
+    /// Parse an expression such as `d / t` or `sqrt(a^2 + b^2)`
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = ExpressionParser { tokens, pos: 0 };
+        let expr = parser.parse_sum()?;
+
+        if parser.pos != parser.tokens.len() {
+            return Err(QtiError::ParseError(format!(
+                "Unexpected '{}' in formula '{}'",
+                parser.tokens[parser.pos], input
+            )));
+        }
+
+        Ok(expr)
+    }
+
+    /// Names of all variables referenced by the expression
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables(&self, names: &mut Vec<String>) {
+        match self {
+            Expression::Number(_) => {}
+            Expression::Variable(name) => {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            Expression::Negate(inner) | Expression::Function(_, inner) => {
+                inner.collect_variables(names)
+            }
+            Expression::Binary(left, _, right) => {
+                left.collect_variables(names);
+                right.collect_variables(names);
+            }
+        }
+    }
+
+    pub fn evaluate(&self, values: &HashMap<String, f64>) -> Result<f64> {
+        match self {
+            Expression::Number(n) => Ok(*n),
+            Expression::Variable(name) => match name.as_str() {
+                "pi" => Ok(std::f64::consts::PI),
+                "e" => Ok(std::f64::consts::E),
+                _ => values.get(name).copied().ok_or_else(|| {
+                    QtiError::ParseError(format!("Undefined formula variable '{}'", name))
+                }),
+            },
+            Expression::Negate(inner) => Ok(-inner.evaluate(values)?),
+            Expression::Binary(left, op, right) => {
+                let l = left.evaluate(values)?;
+                let r = right.evaluate(values)?;
+                Ok(match op {
+                    Operator::Add => l + r,
+                    Operator::Subtract => l - r,
+                    Operator::Multiply => l * r,
+                    Operator::Divide => l / r,
+                    Operator::Power => l.powf(r),
+                })
+            }
+            Expression::Function(name, arg) => {
+                let x = arg.evaluate(values)?;
+                Ok(match name.as_str() {
+                    "sqrt" => x.sqrt(),
+                    "abs" => x.abs(),
+                    "sin" => x.sin(),
+                    "cos" => x.cos(),
+                    "tan" => x.tan(),
+                    "asin" => x.asin(),
+                    "acos" => x.acos(),
+                    "atan" => x.atan(),
+                    "ln" => x.ln(),
+                    "log" => x.log10(),
+                    "exp" => x.exp(),
+                    "round" => x.round(),
+                    "floor" => x.floor(),
+                    "ceil" => x.ceil(),
+                    _ => {
+                        return Err(QtiError::ParseError(format!(
+                            "Unknown formula function '{}'",
+                            name
+                        )))
+                    }
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Op(c) => write!(f, "{}", c),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let value = literal.parse::<f64>().map_err(|_| {
+                QtiError::ParseError(format!("Invalid number '{}' in formula", literal))
+            })?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/^".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else {
+            return Err(QtiError::ParseError(format!(
+                "Unexpected character '{}' in formula",
+                c
+            )));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct ExpressionParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExpressionParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_sum(&mut self) -> Result<Expression> {
+        let mut expr = self.parse_product()?;
+        while let Some(Token::Op(c @ ('+' | '-'))) = self.peek() {
+            let op = if *c == '+' {
+                Operator::Add
+            } else {
+                Operator::Subtract
+            };
+            self.pos += 1;
+            let right = self.parse_product()?;
+            expr = Expression::Binary(Box::new(expr), op, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_product(&mut self) -> Result<Expression> {
+        let mut expr = self.parse_unary()?;
+        while let Some(Token::Op(c @ ('*' | '/'))) = self.peek() {
+            let op = if *c == '*' {
+                Operator::Multiply
+            } else {
+                Operator::Divide
+            };
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            expr = Expression::Binary(Box::new(expr), op, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(Expression::Negate(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.parse_unary()
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    /// Exponentiation is right-associative and binds tighter than unary minus
+    fn parse_power(&mut self) -> Result<Expression> {
+        let base = self.parse_atom()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            let exponent = self.parse_unary()?;
+            return Ok(Expression::Binary(
+                Box::new(base),
+                Operator::Power,
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn parse_atom(&mut self) -> Result<Expression> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expression::Number(n)),
+            Some(Token::Ident(name)) => {
+                if let Some(Token::LParen) = self.peek() {
+                    if !FUNCTIONS.contains(&name.as_str()) {
+                        return Err(QtiError::ParseError(format!(
+                            "Unknown formula function '{}'",
+                            name
+                        )));
+                    }
+                    self.pos += 1;
+                    let arg = self.parse_sum()?;
+                    self.expect_rparen()?;
+                    Ok(Expression::Function(name, Box::new(arg)))
+                } else {
+                    Ok(Expression::Variable(name))
+                }
+            }
+            Some(Token::LParen) => {
+                let expr = self.parse_sum()?;
+                self.expect_rparen()?;
+                Ok(expr)
+            }
+            Some(token) => Err(QtiError::ParseError(format!(
+                "Unexpected '{}' in formula",
+                token
+            ))),
+            None => Err(QtiError::ParseError(
+                "Unexpected end of formula".to_string(),
+            )),
+        }
+    }
+
+    fn expect_rparen(&mut self) -> Result<()> {
+        match self.next() {
+            Some(Token::RParen) => Ok(()),
+            _ => Err(QtiError::ParseError(
+                "Missing closing parenthesis in formula".to_string(),
+            )),
+        }
+    }
+}
+
+/// Round `value` to `decimals` places
+pub fn round_to(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+/// 64-bit FNV-1a over `parts`, with a separator so `("ab", "c")` and `("a", "bc")`
+/// differ. Written out because std's hashers may change between Rust releases,
+/// and the seeded answer sets must not.
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.iter().chain(&[0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Pre-compute `count` answer sets by drawing each variable from its range.
+///
+/// The draws are seeded from `seed`, so the same question always produces the
+/// same answer sets. Draws that make the formula undefined (e.g. division by
+/// zero) are retried.
+pub fn generate_answer_sets(
+    formula: &str,
+    variables: &[FormulaVariable],
+    decimals: u32,
+    count: usize,
+    seed: &str,
+) -> Result<Vec<FormulaAnswerSet>> {
+    let expr = Expression::parse(formula)?;
+
+    for name in expr.variables() {
+        if name != "pi" && name != "e" && !variables.iter().any(|v| v.name == name) {
+            return Err(QtiError::ParseError(format!(
+                "Formula '{}' uses undefined variable '{}'",
+                formula, name
+            )));
+        }
+    }
+
+    let mut rng = SplitMix64(fnv1a(&[seed.as_bytes(), formula.as_bytes()]));
+
+    let mut sets = Vec::with_capacity(count);
+    let mut attempts = 0;
+    while sets.len() < count {
+        attempts += 1;
+        if attempts > count * 100 {
+            return Err(QtiError::ParseError(format!(
+                "Formula '{}' has no defined value over the variable ranges",
+                formula
+            )));
+        }
+
+        let values: Vec<(String, f64)> = variables
+            .iter()
+            .map(|v| {
+                let raw = v.min + (v.max - v.min) * rng.next_f64();
+                (v.name.clone(), round_to(raw, v.decimals))
+            })
+            .collect();
+
+        let lookup: HashMap<String, f64> = values.iter().cloned().collect();
+        let answer = expr.evaluate(&lookup)?;
+        if answer.is_finite() {
+            sets.push(FormulaAnswerSet {
+                values,
+                answer: round_to(answer, decimals),
+            });
+        }
+    }
+
+    Ok(sets)
+}
+
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[0, 1]`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / ((1u64 << 53) - 1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_expression() {
+        let expr = Expression::parse("sqrt(a^2 + b^2) - -1 * 2 / 4").unwrap();
+        let values = HashMap::from([("a".to_string(), 3.0), ("b".to_string(), 4.0)]);
+        assert_eq!(expr.evaluate(&values).unwrap(), 5.5);
+        assert_eq!(expr.variables(), vec!["a".to_string(), "b".to_string()]);
+
+        let expr = Expression::parse("-2^2").unwrap();
+        assert_eq!(expr.evaluate(&HashMap::new()).unwrap(), -4.0);
+
+        assert!(Expression::parse("(1 + 2").is_err());
+        assert!(Expression::parse("foo(1)").is_err());
+    }
+
+    #[test]
+    fn test_generate_answer_sets() {
+        let variables = vec![
+            FormulaVariable::new("d", 10.0, 200.0, 0),
+            FormulaVariable::new("t", 1.0, 5.0, 1),
+        ];
+        let sets = generate_answer_sets("d / t", &variables, 2, 5, "seed").unwrap();
+        assert_eq!(sets.len(), 5);
+
+        for set in &sets {
+            let d = set.values[0].1;
+            let t = set.values[1].1;
+            assert!((10.0..=200.0).contains(&d));
+            assert_eq!(d, d.round());
+            assert_eq!(set.answer, round_to(d / t, 2));
+        }
+
+        let again = generate_answer_sets("d / t", &variables, 2, 5, "seed").unwrap();
+        assert_eq!(sets[0].values, again[0].values);
+        // Pinned so a change to the seeding shows up as a failing test
+        assert_eq!(
+            sets[0].values,
+            [("d".to_string(), 155.0), ("t".to_string(), 3.7)]
+        );
+
+        assert!(generate_answer_sets("d / x", &variables, 2, 5, "seed").is_err());
+    }
+}
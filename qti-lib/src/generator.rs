@@ -1,6 +1,11 @@
 use crate::builder::QtiBuilder;
+use crate::cartridge::{Cartridge, Module, ModuleItem, WEB_RESOURCES_FOLDER};
 use crate::error::{QtiError, Result};
-use crate::types::Assessment;
+use crate::lite::{self, LiteExport};
+use crate::math::MathTarget;
+use crate::scoring::{self, AnswerKeyProblem};
+use crate::types::{Assessment, BankRef};
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use xmltree::Element;
 
@@ -23,6 +28,17 @@ impl Generator {
         self
     }
 
+    pub fn with_math_target(mut self, target: MathTarget) -> Self {
+        self.builder = self.builder.with_math_target(target);
+        self
+    }
+
+    /// Leave out short-answer patterns QTI cannot express instead of failing
+    pub fn with_unsupported_patterns(mut self) -> Self {
+        self.builder = self.builder.with_unsupported_patterns();
+        self
+    }
+
     pub fn pretty_print(mut self, enabled: bool) -> Self {
         self.pretty_print = enabled;
         self
@@ -41,6 +57,38 @@ impl Generator {
         Ok(())
     }
 
+    /// Generate a QTI-Lite document, leaving out and reporting questions Lite cannot hold
+    pub fn generate_lite(&self, assessment: &Assessment) -> Result<LiteExport> {
+        let (root, issues) = lite::build_questestinterop(assessment, self.builder.math_target())?;
+        Ok(LiteExport {
+            xml: self.element_to_xml_string(&root)?,
+            issues,
+        })
+    }
+
+    /// Score every question's answer key against the response processing it is
+    /// generated with, in the assessment or in the bank it is drawn from
+    pub fn check_answer_key(&self, assessment: &Assessment) -> Result<Vec<AnswerKeyProblem>> {
+        let mut documents = vec![self.builder.build_questestinterop(assessment)?];
+        documents.extend(
+            self.builder
+                .build_objectbanks(assessment)?
+                .into_iter()
+                .map(|(_, element)| element),
+        );
+
+        let mut problems = Vec::new();
+        for question in &assessment.questions {
+            if let Some(item) = documents
+                .iter()
+                .find_map(|document| scoring::find_item(document, &question.id))
+            {
+                problems.extend(scoring::check_answer_key(question, item)?);
+            }
+        }
+        Ok(problems)
+    }
+
     fn element_to_xml_string(&self, element: &Element) -> Result<String> {
         let mut buf = Vec::new();
         self.write_element(&mut buf, element)?;
@@ -59,55 +107,53 @@ impl Generator {
 
     pub fn generate_package(&self, assessment: &Assessment) -> Result<QtiPackage> {
         let xml = self.generate(assessment)?;
-        let manifest = self.generate_manifest(assessment)?;
+        let banks = self.builder.build_objectbanks(assessment)?;
+        let bank_refs: Vec<BankRef> = banks.iter().map(|(bank, _)| bank.clone()).collect();
+        let meta = self.builder.build_assessment_meta(assessment);
+        let meta_filename = meta.as_ref().map(|_| assessment_meta_filename(assessment));
+        let manifest = self.generate_manifest(assessment, &bank_refs, meta_filename.as_deref())?;
+
+        let mut resources: Vec<QtiResource> = assessment
+            .media
+            .iter()
+            .map(|media| QtiResource {
+                filename: media.filename.clone(),
+                content: media.content.clone(),
+            })
+            .collect();
+
+        if let (Some(meta), Some(filename)) = (&meta, meta_filename) {
+            resources.push(QtiResource {
+                filename,
+                content: self.element_to_xml_string(meta)?.into_bytes(),
+            });
+        }
+
+        for (bank, element) in &banks {
+            resources.push(QtiResource {
+                filename: bank_filename(bank),
+                content: self.element_to_xml_string(element)?.into_bytes(),
+            });
+        }
 
         Ok(QtiPackage {
             assessment_xml: xml,
             manifest_xml: manifest,
-            resources: Vec::new(),
+            resources,
         })
     }
 
-    fn generate_manifest(&self, assessment: &Assessment) -> Result<String> {
-        let mut manifest = Element::new("manifest");
-
-        manifest.attributes.insert(
-            "identifier".to_string(),
-            format!("{}_manifest", assessment.identifier),
-        );
-        manifest.attributes.insert(
-            "xmlns".to_string(),
-            "http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1".to_string(),
-        );
-        manifest.attributes.insert(
-            "xmlns:lom".to_string(),
-            "http://ltsc.ieee.org/xsd/imsccv1p1/LOM/resource".to_string(),
+    fn generate_manifest(
+        &self,
+        assessment: &Assessment,
+        banks: &[BankRef],
+        meta_filename: Option<&str>,
+    ) -> Result<String> {
+        let mut manifest = manifest_element(
+            &format!("{}_manifest", assessment.identifier),
+            "IMS Content",
+            "1.1.3",
         );
-        manifest.attributes.insert(
-            "xmlns:lomimscc".to_string(),
-            "http://ltsc.ieee.org/xsd/imsccv1p1/LOM/manifest".to_string(),
-        );
-        manifest.attributes.insert(
-            "xmlns:xsi".to_string(),
-            "http://www.w3.org/2001/XMLSchema-instance".to_string(),
-        );
-        manifest.attributes.insert("xsi:schemaLocation".to_string(),
-            "http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1 http://www.imsglobal.org/profile/cc/ccv1p1/ccv1p1_imscp_v1p2_v1p0.xsd".to_string());
-
-        let mut metadata = Element::new("metadata");
-        let mut schema = Element::new("schema");
-        schema
-            .children
-            .push(xmltree::XMLNode::Text("IMS Content".to_string()));
-        metadata.children.push(xmltree::XMLNode::Element(schema));
-        let mut schemaversion = Element::new("schemaversion");
-        schemaversion
-            .children
-            .push(xmltree::XMLNode::Text("1.1.3".to_string()));
-        metadata
-            .children
-            .push(xmltree::XMLNode::Element(schemaversion));
-        manifest.children.push(xmltree::XMLNode::Element(metadata));
 
         let organizations = Element::new("organizations");
         manifest
@@ -133,11 +179,432 @@ impl Generator {
             .insert("href".to_string(), format!("{}.xml", assessment.identifier));
         resource.children.push(xmltree::XMLNode::Element(file));
 
+        if let Some(meta_filename) = meta_filename {
+            let mut file = Element::new("file");
+            file.attributes
+                .insert("href".to_string(), meta_filename.to_string());
+            resource.children.push(xmltree::XMLNode::Element(file));
+        }
+
+        let media_identifier = format!("{}_media", assessment.identifier);
+        if !assessment.media.is_empty() {
+            let mut dependency = Element::new("dependency");
+            dependency
+                .attributes
+                .insert("identifierref".to_string(), media_identifier.clone());
+            resource
+                .children
+                .push(xmltree::XMLNode::Element(dependency));
+        }
+
+        for bank in banks {
+            let mut dependency = Element::new("dependency");
+            dependency
+                .attributes
+                .insert("identifierref".to_string(), bank.id.clone());
+            resource
+                .children
+                .push(xmltree::XMLNode::Element(dependency));
+        }
+
         resources.children.push(xmltree::XMLNode::Element(resource));
+
+        // Question banks that groups draw from, each its own QTI resource
+        for bank in banks {
+            let mut bank_resource = Element::new("resource");
+            bank_resource
+                .attributes
+                .insert("identifier".to_string(), bank.id.clone());
+            bank_resource
+                .attributes
+                .insert("type".to_string(), "imsqti_xmlv1p2".to_string());
+            bank_resource
+                .attributes
+                .insert("href".to_string(), bank_filename(bank));
+            let mut file = Element::new("file");
+            file.attributes
+                .insert("href".to_string(), bank_filename(bank));
+            bank_resource.children.push(xmltree::XMLNode::Element(file));
+            resources
+                .children
+                .push(xmltree::XMLNode::Element(bank_resource));
+        }
+
+        // Media files referenced from the assessment, as one web content resource
+        if !assessment.media.is_empty() {
+            let mut media_resource = Element::new("resource");
+            media_resource
+                .attributes
+                .insert("identifier".to_string(), media_identifier);
+            media_resource
+                .attributes
+                .insert("type".to_string(), "webcontent".to_string());
+            media_resource
+                .attributes
+                .insert("href".to_string(), assessment.media[0].filename.clone());
+            for media in &assessment.media {
+                let mut file = Element::new("file");
+                file.attributes
+                    .insert("href".to_string(), media.filename.clone());
+                media_resource
+                    .children
+                    .push(xmltree::XMLNode::Element(file));
+            }
+            resources
+                .children
+                .push(xmltree::XMLNode::Element(media_resource));
+        }
         manifest.children.push(xmltree::XMLNode::Element(resources));
 
         self.element_to_xml_string(&manifest)
     }
+
+    /// Generate an IMS Common Cartridge with every assessment and page of a
+    /// course, laid out in the cartridge's modules
+    pub fn generate_cartridge(&self, cartridge: &Cartridge) -> Result<CartridgePackage> {
+        let mut files: Vec<QtiResource> = Vec::new();
+        let mut assessments = Vec::new();
+        let mut resources = Element::new("resources");
+        let mut identifiers = HashSet::new();
+        let mut banks: Vec<(BankRef, Element)> = Vec::new();
+        let mut shared_media: HashMap<String, &[u8]> = HashMap::new();
+
+        for assessment in cartridge.assessments() {
+            let resource_id = format!("{}_resource", assessment.identifier);
+            if !identifiers.insert(resource_id.clone()) {
+                return Err(QtiError::ValidationError(format!(
+                    "Duplicate assessment identifier in cartridge: {}",
+                    assessment.identifier
+                )));
+            }
+
+            let qti_filename = format!("{}/assessment_qti.xml", assessment.identifier);
+            assessments.push(QtiResource {
+                filename: qti_filename.clone(),
+                content: self.generate(assessment)?.into_bytes(),
+            });
+            let mut resource =
+                resource_element(&resource_id, CC_ASSESSMENT_TYPE, Some(&qti_filename));
+
+            if let Some(meta) = self.builder.build_assessment_meta(assessment) {
+                let filename = format!("{}/assessment_meta.xml", assessment.identifier);
+                resource
+                    .children
+                    .push(xmltree::XMLNode::Element(file_element(&filename)));
+                files.push(QtiResource {
+                    filename,
+                    content: self.element_to_xml_string(&meta)?.into_bytes(),
+                });
+            }
+
+            if !assessment.media.is_empty() {
+                let media_id = format!("{}_media", assessment.identifier);
+                let mut media_resource = resource_element(&media_id, "webcontent", None);
+                for media in &assessment.media {
+                    // Canvas resolves $IMS-CC-FILEBASE$ to web_resources/, so
+                    // its assessments share that folder; plain QTI links are
+                    // relative to the assessment file
+                    let path = if self.builder.has_canvas_extensions() {
+                        format!("{}/{}", WEB_RESOURCES_FOLDER, media.filename)
+                    } else {
+                        format!("{}/{}", assessment.identifier, media.filename)
+                    };
+                    media_resource
+                        .children
+                        .push(xmltree::XMLNode::Element(file_element(&path)));
+                    match shared_media.get(&path) {
+                        Some(content) if *content != media.content.as_slice() => {
+                            return Err(QtiError::ValidationError(format!(
+                                "Media file {} differs between assessments",
+                                media.filename
+                            )));
+                        }
+                        Some(_) => {}
+                        None => {
+                            shared_media.insert(path.clone(), &media.content);
+                            files.push(QtiResource {
+                                filename: path,
+                                content: media.content.clone(),
+                            });
+                        }
+                    }
+                }
+                resource
+                    .children
+                    .push(xmltree::XMLNode::Element(dependency_element(&media_id)));
+                resources
+                    .children
+                    .push(xmltree::XMLNode::Element(media_resource));
+            }
+
+            for (bank, element) in self.builder.build_objectbanks(assessment)? {
+                resource
+                    .children
+                    .push(xmltree::XMLNode::Element(dependency_element(&bank.id)));
+                match banks
+                    .iter_mut()
+                    .find(|(existing, _)| existing.id == bank.id)
+                {
+                    // Quizzes drawing different questions from one bank share its file
+                    Some((_, existing)) => merge_objectbank(existing, element),
+                    None => {
+                        resources
+                            .children
+                            .push(xmltree::XMLNode::Element(resource_element(
+                                &bank.id,
+                                CC_QUESTION_BANK_TYPE,
+                                Some(&bank_filename(&bank)),
+                            )));
+                        banks.push((bank, element));
+                    }
+                }
+            }
+
+            resources.children.push(xmltree::XMLNode::Element(resource));
+        }
+
+        for (bank, element) in &banks {
+            files.push(QtiResource {
+                filename: bank_filename(bank),
+                content: self.element_to_xml_string(element)?.into_bytes(),
+            });
+        }
+
+        for content in cartridge.web_content() {
+            if !identifiers.insert(content.id.clone()) {
+                return Err(QtiError::ValidationError(format!(
+                    "Duplicate web content identifier in cartridge: {}",
+                    content.id
+                )));
+            }
+            let path = content.path()?;
+            resources
+                .children
+                .push(xmltree::XMLNode::Element(resource_element(
+                    &content.id,
+                    "webcontent",
+                    Some(&path),
+                )));
+            files.push(QtiResource {
+                filename: path,
+                content: content.content.clone(),
+            });
+        }
+
+        let mut manifest = manifest_element(
+            &format!("{}_manifest", cartridge.identifier),
+            "IMS Common Cartridge",
+            "1.1.0",
+        );
+        if let Some(xmltree::XMLNode::Element(metadata)) = manifest.children.last_mut() {
+            let mut string = Element::new("lomimscc:string");
+            string
+                .children
+                .push(xmltree::XMLNode::Text(cartridge.title.clone()));
+            let mut title = Element::new("lomimscc:title");
+            title.children.push(xmltree::XMLNode::Element(string));
+            let mut general = Element::new("lomimscc:general");
+            general.children.push(xmltree::XMLNode::Element(title));
+            let mut lom = Element::new("lomimscc:lom");
+            lom.children.push(xmltree::XMLNode::Element(general));
+            metadata.children.push(xmltree::XMLNode::Element(lom));
+        }
+
+        // Canvas, Moodle and Brightspace read the modules from a single
+        // rooted hierarchy under one "LearningModules" item
+        let mut root = Element::new("item");
+        root.attributes
+            .insert("identifier".to_string(), "LearningModules".to_string());
+        for module in &cartridge.modules {
+            root.children
+                .push(xmltree::XMLNode::Element(module_element(module)));
+        }
+        let mut organization = Element::new("organization");
+        organization.attributes.insert(
+            "identifier".to_string(),
+            format!("{}_organization", cartridge.identifier),
+        );
+        organization
+            .attributes
+            .insert("structure".to_string(), "rooted-hierarchy".to_string());
+        organization.children.push(xmltree::XMLNode::Element(root));
+        let mut organizations = Element::new("organizations");
+        organizations
+            .children
+            .push(xmltree::XMLNode::Element(organization));
+        manifest
+            .children
+            .push(xmltree::XMLNode::Element(organizations));
+        manifest.children.push(xmltree::XMLNode::Element(resources));
+
+        Ok(CartridgePackage {
+            manifest_xml: self.element_to_xml_string(&manifest)?,
+            assessments,
+            resources: files,
+        })
+    }
+}
+
+/// Common Cartridge resource type of an assessment
+const CC_ASSESSMENT_TYPE: &str = "imsqti_xmlv1p2/imscc_xmlv1p1/assessment";
+
+/// Common Cartridge resource type of a question bank
+const CC_QUESTION_BANK_TYPE: &str = "imsqti_xmlv1p2/imscc_xmlv1p1/question-bank";
+
+/// Organization `item` for a module, with an item per assessment, page and submodule
+fn module_element(module: &Module) -> Element {
+    let mut element = titled_item(&module.id, None, &module.title);
+    for item in &module.items {
+        let child = match item {
+            ModuleItem::Assessment(assessment) => titled_item(
+                &format!("{}_item", assessment.identifier),
+                Some(&format!("{}_resource", assessment.identifier)),
+                &assessment.title,
+            ),
+            ModuleItem::WebContent(content) => titled_item(
+                &format!("{}_item", content.id),
+                Some(&content.id),
+                &content.title,
+            ),
+            ModuleItem::Module(module) => module_element(module),
+        };
+        element.children.push(xmltree::XMLNode::Element(child));
+    }
+    element
+}
+
+fn titled_item(identifier: &str, identifierref: Option<&str>, title: &str) -> Element {
+    let mut item = Element::new("item");
+    item.attributes
+        .insert("identifier".to_string(), identifier.to_string());
+    if let Some(identifierref) = identifierref {
+        item.attributes
+            .insert("identifierref".to_string(), identifierref.to_string());
+    }
+    let mut title_element = Element::new("title");
+    title_element
+        .children
+        .push(xmltree::XMLNode::Text(title.to_string()));
+    item.children.push(xmltree::XMLNode::Element(title_element));
+    item
+}
+
+/// `resource` element, listing `href` as its first file when given
+fn resource_element(identifier: &str, kind: &str, href: Option<&str>) -> Element {
+    let mut resource = Element::new("resource");
+    resource
+        .attributes
+        .insert("identifier".to_string(), identifier.to_string());
+    resource
+        .attributes
+        .insert("type".to_string(), kind.to_string());
+    if let Some(href) = href {
+        resource
+            .attributes
+            .insert("href".to_string(), href.to_string());
+        resource
+            .children
+            .push(xmltree::XMLNode::Element(file_element(href)));
+    }
+    resource
+}
+
+fn file_element(href: &str) -> Element {
+    let mut file = Element::new("file");
+    file.attributes.insert("href".to_string(), href.to_string());
+    file
+}
+
+fn dependency_element(identifierref: &str) -> Element {
+    let mut dependency = Element::new("dependency");
+    dependency
+        .attributes
+        .insert("identifierref".to_string(), identifierref.to_string());
+    dependency
+}
+
+/// Root `manifest` element with the Common Cartridge namespaces and schema metadata
+fn manifest_element(identifier: &str, schema: &str, schemaversion: &str) -> Element {
+    let mut manifest = Element::new("manifest");
+
+    manifest
+        .attributes
+        .insert("identifier".to_string(), identifier.to_string());
+    manifest.attributes.insert(
+        "xmlns".to_string(),
+        "http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1".to_string(),
+    );
+    manifest.attributes.insert(
+        "xmlns:lom".to_string(),
+        "http://ltsc.ieee.org/xsd/imsccv1p1/LOM/resource".to_string(),
+    );
+    manifest.attributes.insert(
+        "xmlns:lomimscc".to_string(),
+        "http://ltsc.ieee.org/xsd/imsccv1p1/LOM/manifest".to_string(),
+    );
+    manifest.attributes.insert(
+        "xmlns:xsi".to_string(),
+        "http://www.w3.org/2001/XMLSchema-instance".to_string(),
+    );
+    manifest.attributes.insert("xsi:schemaLocation".to_string(),
+        "http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1 http://www.imsglobal.org/profile/cc/ccv1p1/ccv1p1_imscp_v1p2_v1p0.xsd".to_string());
+
+    let mut metadata = Element::new("metadata");
+    let mut schema_element = Element::new("schema");
+    schema_element
+        .children
+        .push(xmltree::XMLNode::Text(schema.to_string()));
+    metadata
+        .children
+        .push(xmltree::XMLNode::Element(schema_element));
+    let mut version = Element::new("schemaversion");
+    version
+        .children
+        .push(xmltree::XMLNode::Text(schemaversion.to_string()));
+    metadata.children.push(xmltree::XMLNode::Element(version));
+    manifest.children.push(xmltree::XMLNode::Element(metadata));
+    manifest
+}
+
+/// Path of the Canvas quiz settings file inside the package
+fn assessment_meta_filename(assessment: &Assessment) -> String {
+    format!(
+        "non_cc_assessments/{}/assessment_meta.xml",
+        assessment.identifier
+    )
+}
+
+/// Add the items of `other`'s objectbank that `bank`'s does not hold yet
+fn merge_objectbank(bank: &mut Element, other: Element) {
+    let (Some(objectbank), Some(other)) = (
+        bank.get_mut_child("objectbank"),
+        other.children.into_iter().find_map(|child| match child {
+            xmltree::XMLNode::Element(e) if e.name == "objectbank" => Some(e),
+            _ => None,
+        }),
+    ) else {
+        return;
+    };
+    for child in other.children {
+        let xmltree::XMLNode::Element(item) = child else {
+            continue;
+        };
+        let ident = item.attributes.get("ident");
+        let present = ident.is_none()
+            || objectbank.children.iter().any(|existing| {
+                existing
+                    .as_element()
+                    .is_some_and(|e| e.attributes.get("ident") == ident)
+            });
+        if item.name == "item" && !present {
+            objectbank.children.push(xmltree::XMLNode::Element(item));
+        }
+    }
+}
+
+/// Path of a question bank inside the package
+fn bank_filename(bank: &BankRef) -> String {
+    format!("banks/{}.xml", bank.id)
 }
 
 impl Default for Generator {
@@ -153,6 +620,14 @@ pub struct QtiPackage {
     pub resources: Vec<QtiResource>,
 }
 
+/// Represents a complete Common Cartridge: the manifest, one QTI file per
+/// assessment, and every other file the manifest lists
+pub struct CartridgePackage {
+    pub manifest_xml: String,
+    pub assessments: Vec<QtiResource>,
+    pub resources: Vec<QtiResource>,
+}
+
 pub struct QtiResource {
     pub filename: String,
     pub content: Vec<u8>,
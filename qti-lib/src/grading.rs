@@ -0,0 +1,378 @@
+use crate::builder::{points_possible, true_false_choices, QtiBuilder};
+use crate::error::{QtiError, Result};
+use crate::pattern::{self, PatternCondition};
+use crate::scoring::{self, Response};
+use crate::types::{AnswerType, Assessment, Choice, Question, QuestionType};
+use std::io::{Read, Write};
+use xmltree::Element;
+
+/// Cell written to the results CSV for answers a person has to grade
+pub const MANUAL: &str = "manual";
+
+/// Grades a table of learner responses with the rules the generated QTI scores by
+pub struct Grader<'a> {
+    assessment: &'a Assessment,
+    student_column: Option<String>,
+}
+
+/// The score of each scored question for one learner
+#[derive(Debug, Clone, PartialEq)]
+pub struct StudentGrades {
+    pub student: String,
+    /// One per `Gradebook::questions`; `None` when the answer needs manual grading
+    pub scores: Vec<Option<f64>>,
+    /// Sum of the automatically graded scores
+    pub total: f64,
+    /// The learner's answer to each question, as written in the responses
+    pub answers: Vec<String>,
+}
+
+impl StudentGrades {
+    pub fn needs_manual_grading(&self) -> bool {
+        self.scores.iter().any(Option::is_none)
+    }
+}
+
+/// Grades for every learner in a responses table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradebook {
+    /// Ids of the scored questions, in assessment order
+    pub questions: Vec<String>,
+    /// Column labels for the questions: the 1-based question number as `Q1`, `Q2`, ...
+    pub labels: Vec<String>,
+    pub points_possible: Vec<f64>,
+    pub students: Vec<StudentGrades>,
+    /// Scored questions no column answers; they score 0 for everyone
+    pub missing: Vec<String>,
+}
+
+impl Gradebook {
+    /// Write one row per learner: the student, each question's score and the total.
+    /// Answers awaiting manual grading are written as `manual`.
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        let mut header = vec!["student".to_string()];
+        header.extend(self.labels.iter().cloned());
+        header.push("total".to_string());
+        csv.write_record(&header).map_err(csv_error)?;
+
+        let mut possible = vec!["points possible".to_string()];
+        possible.extend(self.points_possible.iter().map(f64::to_string));
+        possible.push(self.points_possible.iter().sum::<f64>().to_string());
+        csv.write_record(&possible).map_err(csv_error)?;
+
+        for student in &self.students {
+            let mut row = vec![student.student.clone()];
+            row.extend(student.scores.iter().map(|score| match score {
+                Some(score) => score.to_string(),
+                None => MANUAL.to_string(),
+            }));
+            row.push(student.total.to_string());
+            csv.write_record(&row).map_err(csv_error)?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+impl<'a> Grader<'a> {
+    pub fn new(assessment: &'a Assessment) -> Self {
+        Self {
+            assessment,
+            student_column: None,
+        }
+    }
+
+    /// Identify learners by the column with this header instead of the first column
+    pub fn with_student_column(mut self, header: impl Into<String>) -> Self {
+        self.student_column = Some(header.into());
+        self
+    }
+
+    /// Grade a CSV with a header row and one row per learner.
+    ///
+    /// A column answers a question when its header is the question's id, its
+    /// number (`3`, `Q3`) or its text; other columns are ignored. Choices are
+    /// given by letter or text, several separated by `;` (or `,`); dropdown
+    /// blanks are answered in order, separated the same way.
+    pub fn grade_csv<R: Read>(&self, reader: R) -> Result<Gradebook> {
+        let mut csv = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers: Vec<String> = csv
+            .headers()
+            .map_err(csv_error)?
+            .iter()
+            .map(|h| h.trim().to_string())
+            .collect();
+
+        let student_index = match &self.student_column {
+            Some(name) => headers
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(name))
+                .ok_or_else(|| {
+                    QtiError::ConfigError(format!("No '{}' column in the responses", name))
+                })?,
+            None => 0,
+        };
+
+        let document = QtiBuilder::new()
+            .with_unsupported_patterns()
+            .build_questestinterop(self.assessment)?;
+        let mut gradebook = Gradebook {
+            questions: Vec::new(),
+            labels: Vec::new(),
+            points_possible: Vec::new(),
+            students: Vec::new(),
+            missing: Vec::new(),
+        };
+        // Each scored question with its built item and the column answering it
+        let mut columns: Vec<(&Question, Option<&Element>, Option<usize>)> = Vec::new();
+        // Numbered as printed: text blocks take no number
+        let numbered = self
+            .assessment
+            .questions
+            .iter()
+            .filter(|question| !matches!(question.question_type, QuestionType::TextOnly))
+            .enumerate();
+        for (index, question) in numbered {
+            if !is_scored(question) {
+                continue;
+            }
+            let column = headers.iter().enumerate().position(|(i, header)| {
+                i != student_index && answers_question(header, index + 1, question)
+            });
+            if column.is_none() {
+                gradebook.missing.push(format!("Q{}", index + 1));
+            }
+            columns.push((
+                question,
+                scoring::find_item(&document, &question.id),
+                column,
+            ));
+            gradebook.questions.push(question.id.clone());
+            gradebook.labels.push(format!("Q{}", index + 1));
+            gradebook
+                .points_possible
+                .push(points_possible(question) as f64);
+        }
+
+        for record in csv.records() {
+            let record = record.map_err(csv_error)?;
+            if record.iter().all(|cell| cell.trim().is_empty()) {
+                continue;
+            }
+            let mut grades = StudentGrades {
+                student: record
+                    .get(student_index)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                scores: Vec::new(),
+                total: 0.0,
+                answers: Vec::new(),
+            };
+            for (question, item, column) in &columns {
+                let cell = column.and_then(|c| record.get(c)).unwrap_or_default();
+                let score = grade_answer(question, *item, cell)?;
+                grades.total += score.unwrap_or(0.0);
+                grades.scores.push(score);
+                grades.answers.push(cell.trim().to_string());
+            }
+            gradebook.students.push(grades);
+        }
+        Ok(gradebook)
+    }
+}
+
+/// Questions that earn points; text blocks, Likert items and surveys do not
+fn is_scored(question: &Question) -> bool {
+    question.earns_points()
+        && !matches!(
+            question.question_type,
+            QuestionType::TextOnly | QuestionType::Likert { .. }
+        )
+}
+
+fn answers_question(header: &str, number: usize, question: &Question) -> bool {
+    let numbered = header
+        .strip_prefix(['Q', 'q'])
+        .unwrap_or(header)
+        .trim_end_matches('.')
+        .parse::<usize>()
+        == Ok(number);
+    numbered || header == question.id || header.eq_ignore_ascii_case(question.text.trim())
+}
+
+/// Score one answer, or `None` when a person has to grade it
+fn grade_answer(question: &Question, item: Option<&Element>, cell: &str) -> Result<Option<f64>> {
+    let cell = cell.trim();
+    let respident = format!("response_{}", question.id);
+    let response = match &question.question_type {
+        _ if question.participation => {
+            let points = points_possible(question) as f64;
+            return Ok(Some(if cell.is_empty() { 0.0 } else { points }));
+        }
+        QuestionType::Essay { .. } | QuestionType::FileUpload { .. } => return Ok(None),
+        // Graded here rather than through the item so patterns QTI cannot express still count
+        QuestionType::ShortAnswer {
+            answers,
+            case_sensitive,
+        } => {
+            let points = points_possible(question) as f64;
+            let matched = answers
+                .iter()
+                .find(|answer| answer.matches(cell, *case_sensitive));
+            // The item scores these 0, so leave the answer to a person
+            if let Some(AnswerType::Pattern(regex)) = matched.map(|answer| &answer.answer_type) {
+                if pattern::to_condition(regex, *case_sensitive) == PatternCondition::Unsupported {
+                    return Ok(None);
+                }
+            }
+            let score = matched.map_or(0.0, |answer| points * answer.weight as f64);
+            return Ok(Some(score.clamp(0.0, points)));
+        }
+        QuestionType::MultipleChoice { choices, .. } => {
+            Response::new().with(&respident, pick(choices, cell))
+        }
+        QuestionType::TrueFalse { correct_answer } => {
+            let choices = true_false_choices(question, *correct_answer);
+            let cell = match cell.to_lowercase().as_str() {
+                "t" => "true",
+                "f" => "false",
+                _ => cell,
+            };
+            Response::new().with(&respident, pick(&choices, cell))
+        }
+        QuestionType::MultipleAnswer { choices, .. } => split(cell)
+            .into_iter()
+            .fold(Response::new(), |response, value| {
+                response.with(&respident, pick(choices, value))
+            }),
+        QuestionType::MultipleDropdowns { dropdowns } => dropdowns.iter().zip(split(cell)).fold(
+            Response::new(),
+            |response, (dropdown, value)| {
+                response.with(
+                    format!("response_{}", dropdown.name),
+                    pick(&dropdown.choices, value),
+                )
+            },
+        ),
+        _ if cell.is_empty() => Response::new(),
+        _ => Response::new().with(&respident, cell),
+    };
+
+    match item {
+        Some(item) => Ok(Some(scoring::score_item(item, &response)?.score)),
+        None => Ok(Some(0.0)),
+    }
+}
+
+/// The ident of the choice `value` names by letter, text or ident; unknown
+/// values are passed through and score nothing
+pub(crate) fn pick(choices: &[Choice], value: &str) -> String {
+    let value = value.trim();
+    let letter = value.trim_end_matches(')');
+    let by_letter = (letter.chars().count() == 1)
+        .then(|| letter.to_ascii_lowercase().chars().next())
+        .flatten()
+        .filter(char::is_ascii_lowercase)
+        .and_then(|c| choices.get((c as u8 - b'a') as usize));
+    by_letter
+        .or_else(|| {
+            choices
+                .iter()
+                .find(|c| c.text.trim().eq_ignore_ascii_case(value) || c.id == value)
+        })
+        .map_or_else(|| value.to_string(), |choice| choice.id.clone())
+}
+
+/// Several values in one cell: `;`-separated, or `,`-separated as form exports write them
+pub(crate) fn split(cell: &str) -> Vec<&str> {
+    let separator = if cell.contains(';') { ';' } else { ',' };
+    cell.split(separator)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+fn csv_error(error: csv::Error) -> QtiError {
+    QtiError::ParseError(format!("Invalid responses CSV: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AcceptableAnswer;
+
+    #[test]
+    fn test_grade_csv() {
+        let mut assessment = crate::Parser::new()
+            .parse("1. Pick one\n*a) Paris\nb) Rome\n\n2. Pick some\n[*] x\n[ ] y\n[*] z\n\n3. What is pi?\n= 3.14 ± 0.01\n\n4. Discuss.\n___")
+            .unwrap();
+        let mut partial = AcceptableAnswer::new("colour");
+        partial.weight = 0.5;
+        let mut colour = Question::new(
+            "Spell it",
+            QuestionType::ShortAnswer {
+                answers: vec![AcceptableAnswer::new("Color"), partial],
+                case_sensitive: true,
+            },
+        );
+        colour.points = 2.0;
+        assessment.questions.push(colour);
+        assessment.questions.push(Question::new(
+            "Any word?",
+            QuestionType::ShortAnswer {
+                answers: vec![AcceptableAnswer::pattern("[a-z]+")],
+                case_sensitive: false,
+            },
+        ));
+
+        let responses = "Timestamp,Name,Q1,Pick some,3,Discuss.,5,6\n\
+            t,Ada,b,\"x, z\",3.141,Long essay,Color,\n\
+            t,Bob,Rome,x,2,,colour,word\n\
+            t,Cy,a,x;y;z,,,color,42\n";
+        let gradebook = Grader::new(&assessment)
+            .with_student_column("name")
+            .grade_csv(responses.as_bytes())
+            .unwrap();
+
+        assert_eq!(gradebook.labels, ["Q1", "Q2", "Q3", "Q4", "Q5", "Q6"]);
+        let ada = &gradebook.students[0];
+        assert_eq!(ada.student, "Ada");
+        assert_eq!(
+            ada.scores,
+            [Some(0.0), Some(1.0), Some(1.0), None, Some(2.0), Some(0.0)]
+        );
+        assert_eq!(ada.total, 4.0);
+        assert!(ada.needs_manual_grading());
+        assert_eq!(gradebook.students[1].scores[4..], [Some(1.0), None]);
+        assert_eq!(
+            gradebook.students[2].scores[..3],
+            [Some(1.0), Some(0.5), Some(0.0)]
+        );
+        assert_eq!(gradebook.students[2].scores[4..], [Some(0.0), Some(0.0)]);
+
+        assert!(gradebook.missing.is_empty());
+
+        let mut out = Vec::new();
+        gradebook.write_csv(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("student,Q1,Q2,Q3,Q4,Q5,Q6,total\npoints possible,1,1,1,1,2,1,7\n"));
+        assert!(out.contains("Ada,0,1,1,manual,2,0,4\n"));
+
+        // Text blocks take no number, as on the printed quiz
+        let assessment = crate::Parser::new()
+            .parse("1. Pick one\n*a) Paris\nb) Rome\n\ntext: Part two\n\n2. What is pi?\n= 3.14\n\n3. Pick again\n*a) yes\nb) no")
+            .unwrap();
+        let gradebook = Grader::new(&assessment)
+            .with_student_column("name")
+            .grade_csv("name,1,2\nAda,a,3.14\n".as_bytes())
+            .unwrap();
+        assert_eq!(gradebook.labels, ["Q1", "Q2", "Q3"]);
+        assert_eq!(
+            gradebook.students[0].scores,
+            [Some(1.0), Some(1.0), Some(0.0)]
+        );
+        assert_eq!(gradebook.missing, ["Q3"]);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::builder::{substitute_variables, true_false_choices};
+use crate::math::{self, MathTarget};
+use crate::media::{self, escape_attribute};
+use crate::types::{AnswerType, Assessment, Choice, Question, QuestionType};
+use std::fmt::Write;
+
+const MATHJAX: &str = "https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-mml-chtml.js";
+
+const STYLE: &str = "
+body { font-family: Georgia, 'Times New Roman', serif; max-width: 46em; margin: 2em auto; padding: 0 1em; line-height: 1.5; color: #000; }
+h1 { font-size: 1.6em; margin-bottom: 0.2em; }
+.student { margin: 1em 0 2em; }
+.student span { display: inline-block; width: 18em; border-bottom: 1px solid #000; }
+.passage { border-left: 3px solid #999; padding-left: 1em; margin: 1.5em 0; }
+.group { font-style: italic; margin: 1.5em 0 0.5em; }
+.question { margin: 1.5em 0; break-inside: avoid; page-break-inside: avoid; }
+.question .points { float: right; font-size: 0.9em; }
+.number { font-weight: bold; margin-right: 0.3em; }
+ol.choices { list-style-type: lower-alpha; margin: 0.5em 0; }
+ol.choices li { margin: 0.2em 0; }
+.blank { border-bottom: 1px solid #000; width: 20em; height: 1.8em; margin: 0.8em 0 0 2em; }
+.essay { border: 1px solid #000; height: 12em; margin: 0.8em 0 0 2em; }
+.upload { border: 1px dashed #000; padding: 1em; margin: 0.8em 0 0 2em; }
+.answer-key { break-before: page; page-break-before: always; }
+.answer-key dt { font-weight: bold; margin-top: 1em; }
+.answer-key .feedback { font-style: italic; }
+@media print { body { margin: 0; max-width: none; } }
+";
+
+/// Renders an assessment as a standalone, print-styled HTML document
+pub struct HtmlRenderer {
+    math_target: MathTarget,
+    answer_key: bool,
+}
+
+impl HtmlRenderer {
+    pub fn new() -> Self {
+        Self {
+            math_target: MathTarget::default(),
+            answer_key: false,
+        }
+    }
+
+    pub fn with_math_target(mut self, target: MathTarget) -> Self {
+        self.math_target = target;
+        self
+    }
+
+    /// Follow the questions with an answer key page of answers, solutions and feedback
+    pub fn with_answer_key(mut self) -> Self {
+        self.answer_key = true;
+        self
+    }
+
+    /// Numbered questions with lettered choices, lines for short answers and boxes for essays
+    pub fn render(&self, assessment: &Assessment) -> String {
+        let mut body = String::new();
+        let title = escape_attribute(&assessment.title);
+        let _ = writeln!(body, "<h1>{}</h1>", title);
+        if let Some(description) = &assessment.description {
+            let _ = writeln!(body, "<p>{}</p>", self.text(description));
+        }
+        body.push_str("<p class=\"student\">Name: <span></span></p>\n");
+
+        let mut number = 0;
+        let mut passage: Option<&str> = None;
+        let mut group: Option<&str> = None;
+        let mut key = String::new();
+        for question in &assessment.questions {
+            if question.passage.as_deref() != passage {
+                passage = question.passage.as_deref();
+                if let Some(shared) = passage.and_then(|id| assessment.passage(id)) {
+                    let _ = writeln!(
+                        body,
+                        "<div class=\"passage\"><h2>{}</h2>{}</div>",
+                        escape_attribute(&shared.title),
+                        self.text(&shared.text)
+                    );
+                }
+            }
+            if question.group.as_deref() != group {
+                group = question.group.as_deref();
+                if let Some(drawn) = group.and_then(|id| assessment.group(id)) {
+                    let _ = writeln!(
+                        body,
+                        "<p class=\"group\">{}: a test draws {} of the following questions.</p>",
+                        escape_attribute(&drawn.title),
+                        drawn.pick
+                    );
+                }
+            }
+
+            if matches!(question.question_type, QuestionType::TextOnly) {
+                let _ = writeln!(
+                    body,
+                    "<div class=\"question\">{}</div>",
+                    self.text(&question.text)
+                );
+                continue;
+            }
+            number += 1;
+            body.push_str(&self.question(question, number));
+            key.push_str(&self.key_entry(question, number));
+        }
+
+        if self.answer_key {
+            let _ = write!(
+                body,
+                "<section class=\"answer-key\">\n<h1>Answer Key: {}</h1>\n<dl>\n{}</dl>\n</section>\n",
+                title, key
+            );
+        }
+
+        let script = if body.contains("\\(") || body.contains("\\[") {
+            format!("<script async src=\"{}\"></script>\n", MATHJAX)
+        } else {
+            String::new()
+        };
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{}</title>\n<style>{}</style>\n{}</head>\n<body>\n{}</body>\n</html>\n",
+            title, STYLE, script, body
+        )
+    }
+
+    fn question(&self, question: &Question, number: usize) -> String {
+        let mut html = String::from("<div class=\"question\">\n");
+        if question.earns_points() {
+            let _ = writeln!(
+                html,
+                "<span class=\"points\">({} {})</span>",
+                question.points,
+                if question.points == 1.0 { "pt" } else { "pts" }
+            );
+        }
+        let _ = writeln!(
+            html,
+            "<span class=\"number\">{}.</span>{}",
+            number,
+            self.text(&self.question_text(question))
+        );
+
+        match &question.question_type {
+            QuestionType::MultipleChoice { choices, .. }
+            | QuestionType::MultipleAnswer { choices, .. }
+            | QuestionType::Likert { scale: choices } => html.push_str(&self.choices(choices)),
+            QuestionType::TrueFalse { correct_answer } => {
+                html.push_str(&self.choices(&true_false_choices(question, *correct_answer)))
+            }
+            QuestionType::ShortAnswer { .. }
+            | QuestionType::Numerical { .. }
+            | QuestionType::Formula { .. } => html.push_str("<div class=\"blank\"></div>\n"),
+            QuestionType::Essay { .. } => html.push_str("<div class=\"essay\"></div>\n"),
+            QuestionType::FileUpload { .. } => {
+                html.push_str("<div class=\"upload\">Attach your file separately.</div>\n")
+            }
+            QuestionType::MultipleDropdowns { dropdowns } => {
+                html.push_str("<ol class=\"blanks\">\n");
+                for dropdown in dropdowns {
+                    let options: Vec<String> = dropdown
+                        .choices
+                        .iter()
+                        .map(|c| self.text(&c.text))
+                        .collect();
+                    let _ = writeln!(html, "<li>{}</li>", options.join(" / "));
+                }
+                html.push_str("</ol>\n");
+            }
+            QuestionType::TextOnly => {}
+        }
+        html.push_str("</div>\n");
+        html
+    }
+
+    /// Question text as printed: blanks for dropdowns, the first answer set's
+    /// values for formula variables
+    fn question_text(&self, question: &Question) -> String {
+        match &question.question_type {
+            QuestionType::MultipleDropdowns { dropdowns } => dropdowns.iter().enumerate().fold(
+                question.text.clone(),
+                |text, (index, dropdown)| {
+                    text.replace(
+                        &format!("[{}]", dropdown.name),
+                        &format!("({}) ________", index + 1),
+                    )
+                },
+            ),
+            QuestionType::Formula { answer_sets, .. } => match answer_sets.first() {
+                Some(set) => substitute_variables(&question.text, set),
+                None => question.text.clone(),
+            },
+            _ => question.text.clone(),
+        }
+    }
+
+    fn choices(&self, choices: &[Choice]) -> String {
+        let mut html = String::from("<ol class=\"choices\">\n");
+        for choice in choices {
+            let _ = writeln!(html, "<li>{}</li>", self.text(&choice.text));
+        }
+        html.push_str("</ol>\n");
+        html
+    }
+
+    fn key_entry(&self, question: &Question, number: usize) -> String {
+        let answer = match &question.question_type {
+            _ if question.participation => "Any answer".to_string(),
+            _ if !question.graded => "Not graded".to_string(),
+            QuestionType::MultipleChoice { choices, .. }
+            | QuestionType::MultipleAnswer { choices, .. } => self.correct_choices(choices),
+            QuestionType::TrueFalse { correct_answer } => {
+                self.correct_choices(&true_false_choices(question, *correct_answer))
+            }
+            QuestionType::ShortAnswer { answers, .. } => answers
+                .iter()
+                .map(|answer| {
+                    let text = match &answer.answer_type {
+                        AnswerType::Pattern(pattern) => {
+                            format!("<code>/{}/</code>", escape_attribute(pattern))
+                        }
+                        _ => self.text(&answer.text),
+                    };
+                    if answer.weight < 1.0 {
+                        format!("{} ({}% credit)", text, answer.weight * 100.0)
+                    } else {
+                        text
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+            QuestionType::Numerical {
+                answer,
+                margin,
+                min,
+                max,
+            } => match (min, max, margin) {
+                (Some(min), Some(max), _) => format!("{} to {}", min, max),
+                (_, _, Some(margin)) if *margin > 0.0 => format!("{} ± {}", answer, margin),
+                _ => answer.to_string(),
+            },
+            QuestionType::Formula {
+                answer_sets,
+                tolerance,
+                ..
+            } => match answer_sets.first() {
+                Some(set) if *tolerance > 0.0 => format!("{} ± {}", set.answer, tolerance),
+                Some(set) => set.answer.to_string(),
+                None => "No answer sets".to_string(),
+            },
+            QuestionType::MultipleDropdowns { dropdowns } => dropdowns
+                .iter()
+                .enumerate()
+                .map(|(index, dropdown)| {
+                    let correct = dropdown
+                        .choices
+                        .iter()
+                        .filter(|c| c.correct)
+                        .map(|c| self.text(&c.text))
+                        .collect::<Vec<_>>()
+                        .join(" or ");
+                    format!("({}) {}", index + 1, correct)
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+            QuestionType::Essay { .. } | QuestionType::FileUpload { .. } => {
+                "Graded by hand".to_string()
+            }
+            QuestionType::Likert { .. } | QuestionType::TextOnly => "Not graded".to_string(),
+        };
+
+        let mut html = format!("<dt>{}.</dt>\n<dd>{}</dd>\n", number, answer);
+        if let Some(solution) = &question.solution {
+            let _ = writeln!(html, "<dd>Solution: {}</dd>", self.text(solution));
+        }
+        if let Some(feedback) = &question.feedback {
+            for text in [&feedback.general, &feedback.correct, &feedback.incorrect]
+                .into_iter()
+                .flatten()
+                .filter(|text| !text.trim().is_empty())
+            {
+                let _ = writeln!(html, "<dd class=\"feedback\">{}</dd>", self.text(text));
+            }
+        }
+        html
+    }
+
+    /// The correct choices by letter and text, e.g. `b) Paris`
+    fn correct_choices(&self, choices: &[Choice]) -> String {
+        choices
+            .iter()
+            .enumerate()
+            .filter(|(_, choice)| choice.correct)
+            .map(|(index, choice)| format!("{}) {}", letter(index), self.text(&choice.text)))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    fn text(&self, text: &str) -> String {
+        media::render_html(&math::render(text, self.math_target), false)
+    }
+}
+
+fn letter(index: usize) -> char {
+    (b'a' + (index % 26) as u8) as char
+}
+
+impl Default for HtmlRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_printable() {
+        let assessment = crate::Parser::new()
+            .parse("title: Paper <Quiz>\n\n1. Capital of France?\na) Rome\n*b) Paris\nfeedback: Paris it is.\n\n2. Colour?\n* red\n\n3. Discuss $x^2$.\n___\n\n4. Roses are {*red|blue}.")
+            .unwrap();
+
+        let paper = HtmlRenderer::new().render(&assessment);
+        assert!(paper.starts_with("<!DOCTYPE html>"));
+        assert!(paper.contains("<title>Paper &lt;Quiz&gt;</title>"));
+        assert!(paper.contains("<span class=\"number\">1.</span>Capital of France?"));
+        assert!(paper.contains("<ol class=\"choices\">\n<li>Rome</li>\n<li>Paris</li>"));
+        assert!(paper.contains("<div class=\"blank\"></div>"));
+        assert!(paper.contains("<div class=\"essay\"></div>"));
+        assert!(paper.contains("Roses are (1) ________."));
+        assert!(paper.contains(MATHJAX));
+        assert!(!paper.contains("<section class=\"answer-key\">"));
+
+        let key = HtmlRenderer::new().with_answer_key().render(&assessment);
+        assert!(key.contains("<section class=\"answer-key\">"));
+        assert!(key.contains("<dt>1.</dt>\n<dd>b) Paris</dd>"));
+        assert!(key.contains("<dd class=\"feedback\">Paris it is.</dd>"));
+        assert!(key.contains("<dt>3.</dt>\n<dd>Graded by hand</dd>"));
+        assert!(key.contains("<dd>(1) red</dd>"));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::cartridge::{Cartridge, Module, ModuleItem};
+use crate::types::{Assessment, Choice, QuestionType};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Identifier derived from `name`, shaped like the random `<prefix>_<uuid>` ids
+pub fn stable_id(prefix: &str, name: &str) -> String {
+    format!(
+        "{}_{}",
+        prefix,
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+    )
+}
+
+/// Whether `id` is a `<prefix>_<uuid>` identifier rather than one chosen by the author
+pub fn is_generated(id: &str, prefix: &str) -> bool {
+    id.strip_prefix(prefix)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .is_some_and(|uuid| Uuid::parse_str(uuid).is_ok())
+}
+
+/// Whether `id` can be used as a QTI `ident` written in the text format
+pub fn is_valid_ident(id: &str) -> bool {
+    let mut chars = id.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Replace generated identifiers with ones derived from content.
+///
+/// The assessment id comes from its title, groups from the assessment id plus
+/// their title, passages and questions from the assessment id plus their
+/// text, and choices from their question plus their text. Repeated text is
+/// told apart by occurrence, not position, so adding or reordering questions
+/// leaves the others' ids unchanged. Ids written explicitly in the text are
+/// kept.
+pub fn assign_stable_ids(assessment: &mut Assessment) {
+    if is_generated(&assessment.identifier, "assessment") {
+        assessment.identifier = stable_id("assessment", &assessment.title);
+    }
+    let base = assessment.identifier.clone();
+
+    let mut seen = HashMap::new();
+    let mut passage_ids = HashMap::new();
+    for passage in &mut assessment.passages {
+        if is_generated(&passage.id, "passage") {
+            let name = occurrence(&mut seen, format!("{}/passage/{}", base, passage.text));
+            let id = stable_id("passage", &name);
+            passage_ids.insert(std::mem::replace(&mut passage.id, id.clone()), id);
+        }
+    }
+
+    let mut seen = HashMap::new();
+    let mut group_ids = HashMap::new();
+    for group in &mut assessment.groups {
+        if is_generated(&group.id, "group") {
+            let name = occurrence(&mut seen, format!("{}/group/{}", base, group.title));
+            let id = stable_id("group", &name);
+            group_ids.insert(std::mem::replace(&mut group.id, id.clone()), id);
+        }
+    }
+
+    let mut seen = HashMap::new();
+    for question in &mut assessment.questions {
+        if let Some(id) = question
+            .passage
+            .as_ref()
+            .and_then(|passage| passage_ids.get(passage))
+        {
+            question.passage = Some(id.clone());
+        }
+        if let Some(id) = question
+            .group
+            .as_ref()
+            .and_then(|group| group_ids.get(group))
+        {
+            question.group = Some(id.clone());
+        }
+
+        if is_generated(&question.id, "question") {
+            let name = occurrence(&mut seen, format!("{}/question/{}", base, question.text));
+            question.id = stable_id("question", &name);
+        }
+
+        let question_id = question.id.clone();
+        match &mut question.question_type {
+            QuestionType::MultipleChoice { choices, .. }
+            | QuestionType::MultipleAnswer { choices, .. }
+            | QuestionType::Likert { scale: choices } => {
+                assign_choice_ids(choices, &question_id);
+            }
+            QuestionType::MultipleDropdowns { dropdowns } => {
+                for dropdown in dropdowns {
+                    assign_choice_ids(
+                        &mut dropdown.choices,
+                        &format!("{}/{}", question_id, dropdown.name),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replace the generated cartridge, module and web content identifiers.
+///
+/// The cartridge id comes from its title, modules from their parent plus
+/// their title, and web content from its filename. Assessments are left to
+/// [`assign_stable_ids`].
+pub fn assign_cartridge_ids(cartridge: &mut Cartridge) {
+    if is_generated(&cartridge.identifier, "cartridge") {
+        cartridge.identifier = stable_id("cartridge", &cartridge.title);
+    }
+    let base = cartridge.identifier.clone();
+
+    let mut seen = HashMap::new();
+    for module in &mut cartridge.modules {
+        assign_module_ids(module, &base, &base, &mut seen);
+    }
+}
+
+fn assign_module_ids(
+    module: &mut Module,
+    parent: &str,
+    base: &str,
+    seen: &mut HashMap<String, usize>,
+) {
+    if is_generated(&module.id, "module") {
+        let name = occurrence(seen, format!("{}/module/{}", parent, module.title));
+        module.id = stable_id("module", &name);
+    }
+
+    let id = module.id.clone();
+    for item in &mut module.items {
+        match item {
+            ModuleItem::WebContent(content) if is_generated(&content.id, "webcontent") => {
+                let name = occurrence(seen, format!("{}/webcontent/{}", base, content.filename));
+                content.id = stable_id("webcontent", &name);
+            }
+            ModuleItem::Module(child) => assign_module_ids(child, &id, base, seen),
+            _ => {}
+        }
+    }
+}
+
+fn assign_choice_ids(choices: &mut [Choice], parent: &str) {
+    let mut seen = HashMap::new();
+    for choice in choices {
+        if is_generated(&choice.id, "choice") {
+            let name = occurrence(&mut seen, format!("{}/choice/{}", parent, choice.text));
+            choice.id = stable_id("choice", &name);
+        }
+    }
+}
+
+/// `name`, suffixed with its occurrence count when it repeats
+fn occurrence(seen: &mut HashMap<String, usize>, name: String) -> String {
+    let count = seen.entry(name.clone()).or_insert(0);
+    *count += 1;
+    if *count == 1 {
+        name
+    } else {
+        format!("{}#{}", name, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Passage, Question};
+
+    fn sample() -> Assessment {
+        let mut assessment = Assessment::new("Stable Quiz");
+        let passage = Passage::new("Story", "<p>Once upon a time</p>");
+        for text in ["What is 2 + 2?", "Pick one", "Pick one"] {
+            let mut question = Question::new(
+                text,
+                QuestionType::MultipleChoice {
+                    choices: vec![Choice::new("4", true), Choice::new("5", false)],
+                    shuffle: false,
+                },
+            );
+            question.passage = Some(passage.id.clone());
+            assessment.questions.push(question);
+        }
+        assessment.passages.push(passage);
+        assessment
+    }
+
+    #[test]
+    fn test_assign_stable_ids() {
+        let mut first = sample();
+        let mut second = sample();
+        second.questions[0].id = "explicit-id".to_string();
+        second.questions.swap(0, 2);
+        assign_stable_ids(&mut first);
+        assign_stable_ids(&mut second);
+
+        assert_eq!(first.identifier, second.identifier);
+        assert!(is_generated(&first.identifier, "assessment"));
+        assert_eq!(first.passages[0].id, second.passages[0].id);
+        assert_eq!(
+            first.questions[0].passage,
+            Some(first.passages[0].id.clone())
+        );
+
+        assert_eq!(second.questions[2].id, "explicit-id");
+        assert_ne!(first.questions[1].id, first.questions[2].id);
+        assert_eq!(first.questions[1].id, second.questions[0].id);
+        assert_eq!(first.questions[2].id, second.questions[1].id);
+
+        if let (
+            QuestionType::MultipleChoice { choices: a, .. },
+            QuestionType::MultipleChoice { choices: b, .. },
+        ) = (
+            &first.questions[1].question_type,
+            &second.questions[0].question_type,
+        ) {
+            assert_eq!(a[0].id, b[0].id);
+            assert_ne!(a[0].id, a[1].id);
+        }
+
+        assert!(is_valid_ident("q-1.a_b"));
+        assert!(!is_valid_ident("1st"));
+        assert!(!is_valid_ident("has space"));
+    }
+}
@@ -1,9 +1,24 @@
+pub mod analysis;
 pub mod builder;
+pub mod canvas;
+pub mod cartridge;
+pub mod dtd;
 pub mod error;
 pub mod exporter;
+pub mod formula;
 pub mod generator;
+pub mod grading;
+pub mod html;
+pub mod ids;
+pub mod lint;
+pub mod lite;
+pub mod math;
+pub mod media;
 pub mod parser;
+pub mod pattern;
+pub mod report;
 pub mod schema;
+pub mod scoring;
 pub mod types;
 pub mod validator;
 
@@ -0,0 +1,335 @@
+use crate::pattern::{self, PatternCondition};
+use crate::report::Severity;
+use crate::types::{AnswerType, Assessment, Choice, Question, QuestionType};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A content problem schema validation cannot see
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintIssue {
+    pub severity: Severity,
+    /// Stable rule code, e.g. `mc-correct-count`
+    pub code: &'static str,
+    pub question_id: Option<String>,
+    /// 1-based source line, for checks made on the input text
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn question(
+        severity: Severity,
+        code: &'static str,
+        question: &Question,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity,
+            code,
+            question_id: Some(question.id.clone()),
+            line: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}] ", self.severity, self.code)?;
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if let Some(id) = &self.question_id {
+            write!(f, "question {}: ", id)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Check an assessment's content, question by question
+pub fn lint(assessment: &Assessment) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut question_ids = HashSet::new();
+    let mut choice_ids = HashSet::new();
+
+    for question in &assessment.questions {
+        if !question_ids.insert(question.id.as_str()) {
+            issues.push(LintIssue::question(
+                Severity::Error,
+                "duplicate-ident",
+                question,
+                format!("Question ident '{}' is used more than once", question.id),
+            ));
+        }
+        for choice in choices(question) {
+            if !choice_ids.insert(choice.id.as_str()) {
+                issues.push(LintIssue::question(
+                    Severity::Error,
+                    "duplicate-ident",
+                    question,
+                    format!("Choice ident '{}' is used more than once", choice.id),
+                ));
+            }
+        }
+
+        lint_choices(assessment, question, &mut issues);
+        lint_answers(question, &mut issues);
+        lint_feedback(question, &mut issues);
+
+        let scored = !matches!(
+            question.question_type,
+            QuestionType::TextOnly | QuestionType::Likert { .. }
+        );
+        if question.graded && scored && question.points == 0.0 {
+            issues.push(LintIssue::question(
+                Severity::Warning,
+                "zero-points",
+                question,
+                "Graded question is worth 0 points",
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Questions in quiz text whose number does not follow the previous one
+pub fn lint_numbering(source: &str) -> Vec<LintIssue> {
+    let number = Regex::new(r"^(\d+)\.\s+").unwrap();
+    let mut issues = Vec::new();
+    let mut expected = 1;
+    for (index, line) in source.lines().enumerate() {
+        let Some(found) = number
+            .captures(line)
+            .and_then(|c| c[1].parse::<usize>().ok())
+        else {
+            continue;
+        };
+        if found != expected {
+            issues.push(LintIssue {
+                severity: Severity::Warning,
+                code: "numbering-gap",
+                question_id: None,
+                line: Some(index + 1),
+                message: format!(
+                    "Question numbered {} where {} was expected",
+                    found, expected
+                ),
+            });
+        }
+        expected = found + 1;
+    }
+    issues
+}
+
+/// Every choice a question offers, including those of its dropdowns
+fn choices(question: &Question) -> Vec<&Choice> {
+    match &question.question_type {
+        QuestionType::MultipleChoice { choices, .. }
+        | QuestionType::MultipleAnswer { choices, .. }
+        | QuestionType::Likert { scale: choices } => choices.iter().collect(),
+        QuestionType::MultipleDropdowns { dropdowns } => {
+            dropdowns.iter().flat_map(|d| &d.choices).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn lint_choices(assessment: &Assessment, question: &Question, issues: &mut Vec<LintIssue>) {
+    let lists: Vec<&[Choice]> = match &question.question_type {
+        QuestionType::MultipleChoice { choices, .. }
+        | QuestionType::MultipleAnswer { choices, .. }
+        | QuestionType::Likert { scale: choices } => vec![choices],
+        QuestionType::MultipleDropdowns { dropdowns } => {
+            dropdowns.iter().map(|d| d.choices.as_slice()).collect()
+        }
+        _ => return,
+    };
+    for choices in lists {
+        let mut seen = HashSet::new();
+        for choice in choices {
+            if !seen.insert(choice.text.trim().to_lowercase()) {
+                issues.push(LintIssue::question(
+                    Severity::Warning,
+                    "duplicate-choice",
+                    question,
+                    format!("Choice '{}' appears more than once", choice.text.trim()),
+                ));
+            }
+        }
+    }
+
+    let shuffled = match &question.question_type {
+        QuestionType::MultipleChoice { shuffle, .. } => {
+            *shuffle || assessment.metadata.shuffle_answers
+        }
+        QuestionType::MultipleAnswer { .. } => assessment.metadata.shuffle_answers,
+        _ => false,
+    };
+    if shuffled {
+        let positional =
+            Regex::new(r"(?i)^(all|none|both|neither) of the (above|previous)").unwrap();
+        for choice in choices(question) {
+            if positional.is_match(choice.text.trim()) {
+                issues.push(LintIssue::question(
+                    Severity::Warning,
+                    "positional-choice-shuffled",
+                    question,
+                    format!(
+                        "'{}' refers to other choices but answers are shuffled",
+                        choice.text.trim()
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn lint_answers(question: &Question, issues: &mut Vec<LintIssue>) {
+    match &question.question_type {
+        QuestionType::MultipleChoice { choices, .. } if question.graded => {
+            let correct = choices.iter().filter(|c| c.correct).count();
+            if correct != 1 {
+                issues.push(LintIssue::question(
+                    Severity::Error,
+                    "mc-correct-count",
+                    question,
+                    format!("Multiple choice question has {} correct answers", correct),
+                ));
+            }
+        }
+        QuestionType::MultipleAnswer { choices, .. }
+            if question.graded && !choices.iter().any(|c| c.correct) =>
+        {
+            issues.push(LintIssue::question(
+                Severity::Error,
+                "ma-no-correct",
+                question,
+                "Multiple answer question has no correct answers",
+            ));
+        }
+        QuestionType::Numerical {
+            min: Some(min),
+            max: Some(max),
+            ..
+        } if min > max => {
+            issues.push(LintIssue::question(
+                Severity::Error,
+                "numeric-range",
+                question,
+                format!("Numeric range minimum {} is above maximum {}", min, max),
+            ));
+        }
+        QuestionType::ShortAnswer {
+            answers,
+            case_sensitive,
+        } if question.graded => {
+            for answer in answers {
+                let AnswerType::Pattern(regex) = &answer.answer_type else {
+                    continue;
+                };
+                if pattern::to_condition(regex, *case_sensitive) == PatternCondition::Unsupported {
+                    issues.push(LintIssue::question(
+                        Severity::Warning,
+                        "pattern-unsupported",
+                        question,
+                        format!(
+                            "Answer pattern /{}/ has no QTI equivalent and would score 0 points in the LMS",
+                            regex
+                        ),
+                    ));
+                }
+            }
+        }
+        QuestionType::Formula { variables, .. } => {
+            for variable in variables.iter().filter(|v| v.min > v.max) {
+                issues.push(LintIssue::question(
+                    Severity::Error,
+                    "numeric-range",
+                    question,
+                    format!(
+                        "Variable '{}' minimum {} is above maximum {}",
+                        variable.name, variable.min, variable.max
+                    ),
+                ));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn lint_feedback(question: &Question, issues: &mut Vec<LintIssue>) {
+    let mut empty = Vec::new();
+    if let Some(feedback) = &question.feedback {
+        for (kind, text) in [
+            ("correct", &feedback.correct),
+            ("incorrect", &feedback.incorrect),
+            ("general", &feedback.general),
+        ] {
+            if text.as_ref().is_some_and(|t| t.trim().is_empty()) {
+                empty.push(format!("{} feedback", kind));
+            }
+        }
+    }
+    for choice in choices(question) {
+        if choice
+            .feedback
+            .as_ref()
+            .is_some_and(|t| t.trim().is_empty())
+        {
+            empty.push(format!("feedback for choice '{}'", choice.text.trim()));
+        }
+    }
+    for what in empty {
+        issues.push(LintIssue::question(
+            Severity::Warning,
+            "empty-feedback",
+            question,
+            format!("Empty {}", what),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_assessment() {
+        let source = "title: Lint\nshuffle_answers: yes\n\n1. Pick one\n*a) Red\nb) Blue\nc) red\n\n3. Pick some\n[*] x\n[ ] All of the above\ncorrect:\n\n4. Any word?\n* /[a-z]+/\n";
+        let mut assessment = crate::Parser::new().parse(source).unwrap();
+        if let QuestionType::MultipleChoice { choices, .. } =
+            &mut assessment.questions[0].question_type
+        {
+            choices[1].correct = true;
+        }
+        if let QuestionType::MultipleAnswer { choices, .. } =
+            &mut assessment.questions[1].question_type
+        {
+            choices[0].correct = false;
+        }
+        assessment.questions[1].id = assessment.questions[0].id.clone();
+        assessment.questions[1].points = 0.0;
+
+        let codes: Vec<_> = lint(&assessment).iter().map(|i| i.code).collect();
+        assert_eq!(
+            codes,
+            [
+                "duplicate-choice",
+                "mc-correct-count",
+                "duplicate-ident",
+                "positional-choice-shuffled",
+                "ma-no-correct",
+                "empty-feedback",
+                "zero-points",
+                "pattern-unsupported"
+            ]
+        );
+
+        let numbering = lint_numbering(source);
+        assert_eq!(numbering.len(), 1);
+        assert_eq!(numbering[0].line, Some(9));
+    }
+}
@@ -0,0 +1,335 @@
+use crate::builder::{item_title, true_false_choices};
+use crate::error::{QtiError, Result};
+use crate::math::{self, MathTarget};
+use crate::media;
+use crate::types::{Assessment, Choice, Question, QuestionType};
+use xmltree::{Element, XMLNode};
+
+/// A question QTI-Lite cannot fully express
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteIssue {
+    pub question_id: String,
+    pub reason: String,
+    /// Whether the question was left out, rather than exported without the feature
+    pub omitted: bool,
+}
+
+/// QTI-Lite output: a `questestinterop` of single-choice items
+#[derive(Debug, Clone)]
+pub struct LiteExport {
+    pub xml: String,
+    pub issues: Vec<LiteIssue>,
+}
+
+/// Build a QTI-Lite document: one item per multiple choice, true/false or
+/// Likert question, with any passage shown before the question. Other
+/// question types are left out and reported.
+pub fn build_questestinterop(
+    assessment: &Assessment,
+    math_target: MathTarget,
+) -> Result<(Element, Vec<LiteIssue>)> {
+    let mut root = Element::new("questestinterop");
+    root.attributes.insert(
+        "xmlns".to_string(),
+        "http://www.imsglobal.org/xsd/ims_qtilitev1p2".to_string(),
+    );
+    root.attributes.insert(
+        "xmlns:xsi".to_string(),
+        "http://www.w3.org/2001/XMLSchema-instance".to_string(),
+    );
+    root.attributes.insert(
+        "xsi:schemaLocation".to_string(),
+        "http://www.imsglobal.org/xsd/ims_qtilitev1p2 http://www.imsglobal.org/xsd/ims_qtilitev1p2p1.xsd".to_string(),
+    );
+
+    let mut issues = Vec::new();
+    for question in &assessment.questions {
+        let choices = match lite_choices(question) {
+            Ok(choices) => choices,
+            Err(reason) => {
+                issues.push(LiteIssue {
+                    question_id: question.id.clone(),
+                    reason: reason.to_string(),
+                    omitted: true,
+                });
+                continue;
+            }
+        };
+
+        if let Some(group) = question.group.as_ref().and_then(|id| assessment.group(id)) {
+            issues.push(LiteIssue {
+                question_id: question.id.clone(),
+                reason: format!(
+                    "QTI-Lite has no random selection; exported from group '{}' as a fixed item",
+                    group.title
+                ),
+                omitted: false,
+            });
+        }
+
+        let passage = question
+            .passage
+            .as_ref()
+            .and_then(|id| assessment.passage(id))
+            .map(|passage| passage.text.as_str());
+        root.children.push(XMLNode::Element(build_item(
+            question,
+            &choices,
+            passage,
+            math_target,
+        )));
+    }
+
+    if root.children.is_empty() {
+        return Err(QtiError::ValidationError(
+            "No question can be represented in QTI-Lite".to_string(),
+        ));
+    }
+    Ok((root, issues))
+}
+
+/// The single-choice options of a question, or why Lite cannot hold it
+fn lite_choices(question: &Question) -> std::result::Result<Vec<Choice>, &'static str> {
+    let choices = match &question.question_type {
+        QuestionType::MultipleChoice { choices, .. } => choices.clone(),
+        QuestionType::Likert { scale } => scale.clone(),
+        QuestionType::TrueFalse { correct_answer } => true_false_choices(question, *correct_answer),
+        QuestionType::MultipleAnswer { .. } => {
+            return Err("QTI-Lite allows a single response; multiple answer is not supported")
+        }
+        QuestionType::MultipleDropdowns { .. } => {
+            return Err("QTI-Lite allows one response per item; dropdowns are not supported")
+        }
+        QuestionType::ShortAnswer { .. }
+        | QuestionType::Essay { .. }
+        | QuestionType::FileUpload { .. } => return Err("QTI-Lite has no text responses"),
+        QuestionType::Numerical { .. } | QuestionType::Formula { .. } => {
+            return Err("QTI-Lite has no numeric responses")
+        }
+        QuestionType::TextOnly => return Err("QTI-Lite items must ask for a response"),
+    };
+
+    if question.graded && choices.iter().filter(|c| c.correct).count() > 1 {
+        return Err("QTI-Lite scores one correct choice; this question has several");
+    }
+    if question.graded && question.points.fract() != 0.0 {
+        return Err("QTI-Lite scores are integers; this question has fractional points");
+    }
+    Ok(choices)
+}
+
+fn build_item(
+    question: &Question,
+    choices: &[Choice],
+    passage: Option<&str>,
+    math_target: MathTarget,
+) -> Element {
+    let mut item = Element::new("item");
+    item.attributes
+        .insert("ident".to_string(), question.id.clone());
+    item.attributes
+        .insert("title".to_string(), item_title(question));
+
+    let mut presentation = Element::new("presentation");
+    if let Some(passage) = passage {
+        presentation
+            .children
+            .push(XMLNode::Element(material(passage, math_target)));
+    }
+    presentation
+        .children
+        .push(XMLNode::Element(material(&question.text, math_target)));
+
+    let shuffle = matches!(
+        question.question_type,
+        QuestionType::MultipleChoice { shuffle: true, .. }
+    );
+    let mut render_choice = Element::new("render_choice");
+    render_choice.attributes.insert(
+        "shuffle".to_string(),
+        if shuffle { "Yes" } else { "No" }.to_string(),
+    );
+    for choice in choices {
+        let mut label = Element::new("response_label");
+        label
+            .attributes
+            .insert("ident".to_string(), choice.id.clone());
+        label
+            .children
+            .push(XMLNode::Element(material(&choice.text, math_target)));
+        render_choice.children.push(XMLNode::Element(label));
+    }
+    let mut response = Element::new("response_lid");
+    response
+        .attributes
+        .insert("ident".to_string(), response_ident(question));
+    response
+        .attributes
+        .insert("rcardinality".to_string(), "Single".to_string());
+    response.children.push(XMLNode::Element(render_choice));
+    presentation.children.push(XMLNode::Element(response));
+    item.children.push(XMLNode::Element(presentation));
+
+    let feedback = question.feedback.clone().unwrap_or_default();
+    if question.graded {
+        item.children
+            .push(XMLNode::Element(build_resprocessing(question, choices)));
+    }
+
+    for (ident, text) in [
+        ("correct", &feedback.correct),
+        ("incorrect", &feedback.incorrect),
+        ("general", &feedback.general),
+    ] {
+        if let (Some(text), true) = (text, question.graded || ident == "general") {
+            let mut itemfeedback = Element::new("itemfeedback");
+            itemfeedback
+                .attributes
+                .insert("ident".to_string(), ident.to_string());
+            itemfeedback
+                .children
+                .push(XMLNode::Element(material(text, math_target)));
+            item.children.push(XMLNode::Element(itemfeedback));
+        }
+    }
+
+    item
+}
+
+/// Integer SCORE set by whichever choice was picked, with its feedback
+fn build_resprocessing(question: &Question, choices: &[Choice]) -> Element {
+    let feedback = question.feedback.clone().unwrap_or_default();
+    let mut resprocessing = Element::new("resprocessing");
+
+    let mut decvar = Element::new("decvar");
+    decvar
+        .attributes
+        .insert("varname".to_string(), "SCORE".to_string());
+    decvar
+        .attributes
+        .insert("vartype".to_string(), "Integer".to_string());
+    decvar
+        .attributes
+        .insert("defaultval".to_string(), "0".to_string());
+    let mut outcomes = Element::new("outcomes");
+    outcomes.children.push(XMLNode::Element(decvar));
+    resprocessing.children.push(XMLNode::Element(outcomes));
+
+    for choice in choices {
+        let mut varequal = Element::new("varequal");
+        varequal
+            .attributes
+            .insert("respident".to_string(), response_ident(question));
+        varequal.children.push(XMLNode::Text(choice.id.clone()));
+        let mut conditionvar = Element::new("conditionvar");
+        conditionvar.children.push(XMLNode::Element(varequal));
+
+        let score = if choice.correct {
+            question.points as i64
+        } else {
+            0
+        };
+        let mut setvar = Element::new("setvar");
+        setvar
+            .attributes
+            .insert("varname".to_string(), "SCORE".to_string());
+        setvar
+            .attributes
+            .insert("action".to_string(), "Set".to_string());
+        setvar.children.push(XMLNode::Text(score.to_string()));
+
+        let mut condition = Element::new("respcondition");
+        condition.children.push(XMLNode::Element(conditionvar));
+        condition.children.push(XMLNode::Element(setvar));
+
+        let shown = if choice.correct {
+            feedback.correct.as_ref().map(|_| "correct")
+        } else {
+            feedback.incorrect.as_ref().map(|_| "incorrect")
+        };
+        for linkrefid in shown
+            .into_iter()
+            .chain(feedback.general.as_ref().map(|_| "general"))
+        {
+            let mut display = Element::new("displayfeedback");
+            display
+                .attributes
+                .insert("feedbacktype".to_string(), "Response".to_string());
+            display
+                .attributes
+                .insert("linkrefid".to_string(), linkrefid.to_string());
+            condition.children.push(XMLNode::Element(display));
+        }
+        resprocessing.children.push(XMLNode::Element(condition));
+    }
+
+    resprocessing
+}
+
+fn response_ident(question: &Question) -> String {
+    format!("response_{}", question.id)
+}
+
+/// Lite material is text and images only, so media and math go into one HTML `mattext`
+fn material(text: &str, math_target: MathTarget) -> Element {
+    let mut mattext = Element::new("mattext");
+    mattext
+        .attributes
+        .insert("texttype".to_string(), "text/html".to_string());
+    mattext.children.push(XMLNode::Text(media::render_html(
+        &math::render(text, math_target),
+        false,
+    )));
+    let mut material = Element::new("material");
+    material.children.push(XMLNode::Element(mattext));
+    material
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::Validator;
+    use crate::Generator;
+
+    #[test]
+    fn test_generate_lite() {
+        let input = r#"title: Lite Quiz
+
+1. What is 2 + 2?
+a) 3
+*b) 4
+correct: Right
+incorrect: No
+
+2. Name a primary colour.
+* red
+
+3. Rate the course.
+likert
+"#;
+        let mut assessment = crate::Parser::new().parse(input).unwrap();
+        assessment.questions.push(Question::new(
+            "The sky is blue.",
+            QuestionType::TrueFalse {
+                correct_answer: true,
+            },
+        ));
+        let export = Generator::new()
+            .generate_lite(&assessment)
+            .expect("Should generate QTI-Lite");
+
+        assert_eq!(export.xml.matches("<item ").count(), 3);
+        assert!(export.xml.contains("vartype=\"Integer\""));
+        assert!(export.xml.contains("linkrefid=\"incorrect\""));
+        assert_eq!(export.issues.len(), 1);
+        assert_eq!(export.issues[0].question_id, assessment.questions[1].id);
+        assert!(export.issues[0].omitted);
+
+        Validator::qti_lite()
+            .validate_xml(&export.xml)
+            .expect("Should be valid QTI-Lite");
+
+        let full = Generator::new().generate(&assessment).unwrap();
+        assert!(Validator::qti_lite().validate_xml(&full).is_err());
+    }
+}
@@ -0,0 +1,545 @@
+use crate::error::{QtiError, Result};
+use std::ops::Range;
+
+/// What `$...$` and `$$...$$` math in question text is converted into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MathTarget {
+    /// `\( \)` and `\[ \]` delimiters, left for MathJax to typeset
+    #[default]
+    MathJax,
+    /// Canvas equation editor images (`/equation_images/...`)
+    CanvasEquation,
+    /// MathML embedded in the HTML
+    MathMl,
+}
+
+impl std::str::FromStr for MathTarget {
+    type Err = QtiError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "mathjax" => Ok(Self::MathJax),
+            "canvas" | "canvas_equation" => Ok(Self::CanvasEquation),
+            "mathml" => Ok(Self::MathMl),
+            other => Err(QtiError::ParseError(format!(
+                "Unknown math target '{}': expected mathjax, canvas or mathml",
+                other
+            ))),
+        }
+    }
+}
+
+/// A math span found in text
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment<'a> {
+    Text(String),
+    Math { latex: &'a str, display: bool },
+}
+
+/// Split text on math delimiters.
+///
+/// `$$...$$` is display math. `$...$` is inline math only when the opening
+/// `$` is followed by a non-space, the closing `$` is preceded by a non-space
+/// and not followed by a digit, so prices like "$5 and $10" stay text.
+/// `\$` is a literal dollar sign.
+pub fn split(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(pos) = rest.find(['$', '\\']) {
+        plain.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+
+        if let Some(after) = tail.strip_prefix("\\$") {
+            plain.push('$');
+            rest = after;
+            continue;
+        }
+        if let Some(after) = tail.strip_prefix('\\') {
+            plain.push('\\');
+            rest = after;
+            continue;
+        }
+
+        match dollar_math(tail) {
+            Some((latex, display, consumed)) => {
+                if !plain.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut plain)));
+                }
+                segments.push(Segment::Math {
+                    latex: latex.trim(),
+                    display,
+                });
+                rest = &tail[consumed..];
+            }
+            None => {
+                plain.push('$');
+                rest = &tail[1..];
+            }
+        }
+    }
+
+    plain.push_str(rest);
+    if !plain.is_empty() {
+        segments.push(Segment::Text(plain));
+    }
+    segments
+}
+
+/// The math starting at the `$` that begins `tail`: its LaTeX, whether it is
+/// display math, and the bytes it takes up with its delimiters
+fn dollar_math(tail: &str) -> Option<(&str, bool, usize)> {
+    if let Some(body) = tail.strip_prefix("$$") {
+        body.find("$$")
+            .filter(|&end| !body[..end].trim().is_empty())
+            .map(|end| (&body[..end], true, end + 4))
+    } else {
+        find_inline_end(&tail[1..]).map(|end| (&tail[1..end + 1], false, end + 2))
+    }
+}
+
+/// Byte offset of the closing `$` of inline math starting at `body`
+fn find_inline_end(body: &str) -> Option<usize> {
+    if body.starts_with(char::is_whitespace) || body.starts_with('$') {
+        return None;
+    }
+
+    let bytes = body.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            // Inline math never spans the start of display math
+            b'$' if bytes.get(i + 1) == Some(&b'$') => return None,
+            b'$' => {
+                // The body never starts with `$`, so `i > 0` here
+                let after_space = bytes[i - 1].is_ascii_whitespace();
+                let before_digit = bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit());
+                if !after_space && !before_digit {
+                    return Some(i);
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Replace math spans with `target` markup
+pub fn render(text: &str, target: MathTarget) -> String {
+    split(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text,
+            Segment::Math { latex, display } => render_math(latex, display, target),
+        })
+        .collect()
+}
+
+/// Byte ranges of the math spans in `text`, delimiters included, for syntax
+/// checks that must ignore math: the `$` math `split` finds, and `\(...\)` or
+/// `\[...\]` already written for MathJax
+pub fn math_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = text[pos..].find(['$', '\\']) {
+        let start = pos + found;
+        let tail = &text[start..];
+
+        let delimited = [("\\(", "\\)"), ("\\[", "\\]")]
+            .into_iter()
+            .find_map(|(open, close)| {
+                let body = tail.strip_prefix(open)?;
+                body.find(close).map(|end| open.len() + end + close.len())
+            });
+        let consumed = if let Some(consumed) = delimited {
+            ranges.push(start..start + consumed);
+            consumed
+        } else if tail.starts_with("\\$") {
+            2
+        } else if tail.starts_with('\\') {
+            1
+        } else if let Some((_, _, consumed)) = dollar_math(tail) {
+            ranges.push(start..start + consumed);
+            consumed
+        } else {
+            1
+        };
+        pos = start + consumed;
+    }
+    ranges
+}
+
+fn render_math(latex: &str, display: bool, target: MathTarget) -> String {
+    match target {
+        MathTarget::MathJax => {
+            let (open, close) = if display {
+                ("\\[", "\\]")
+            } else {
+                ("\\(", "\\)")
+            };
+            format!("{}{}{}", open, escape(latex), close)
+        }
+        MathTarget::CanvasEquation => {
+            let encoded = percent_encode(&percent_encode(latex));
+            let image = format!(
+                "<img class=\"equation_image\" title=\"{0}\" src=\"/equation_images/{1}?scale=1\" alt=\"LaTeX: {0}\" data-equation-content=\"{0}\">",
+                escape(latex),
+                encoded
+            );
+            if display {
+                format!("<p style=\"text-align: center;\">{}</p>", image)
+            } else {
+                image
+            }
+        }
+        MathTarget::MathMl => to_mathml(latex, display),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Convert a LaTeX expression to MathML.
+///
+/// Covers the subset used in quiz questions: scripts, `\frac`, `\sqrt`,
+/// Greek letters, common operators and functions, `\text` and `\left`/`\right`.
+/// Unknown commands are kept as identifiers.
+pub fn to_mathml(latex: &str, display: bool) -> String {
+    let mut parser = LatexParser {
+        chars: latex.chars().collect(),
+        pos: 0,
+    };
+    let body = parser.parse_row(None);
+    format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"{}\"><mrow>{}</mrow></math>",
+        if display { "block" } else { "inline" },
+        body
+    )
+}
+
+struct LatexParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl LatexParser {
+    /// Parse atoms until the end of input or `close`
+    fn parse_row(&mut self, close: Option<char>) -> String {
+        let mut row = String::new();
+        while let Some(c) = self.peek() {
+            if Some(c) == close {
+                self.pos += 1;
+                break;
+            }
+            if let Some(atom) = self.parse_scripted() {
+                row.push_str(&atom);
+            }
+        }
+        row
+    }
+
+    /// An atom followed by optional `^` / `_` scripts
+    fn parse_scripted(&mut self) -> Option<String> {
+        let base = self.parse_atom()?;
+        let mut sub = None;
+        let mut sup = None;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('_') if sub.is_none() => {
+                    self.pos += 1;
+                    sub = Some(self.parse_argument());
+                }
+                Some('^') if sup.is_none() => {
+                    self.pos += 1;
+                    sup = Some(self.parse_argument());
+                }
+                _ => break,
+            }
+        }
+
+        Some(match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<msub>{}{}</msub>", base, sub),
+            (None, Some(sup)) => format!("<msup>{}{}</msup>", base, sup),
+            (Some(sub), Some(sup)) => format!("<msubsup>{}{}{}</msubsup>", base, sub, sup),
+        })
+    }
+
+    /// A single argument: a braced group or one atom, wrapped in `<mrow>`
+    fn parse_argument(&mut self) -> String {
+        self.skip_whitespace();
+        if self.peek() == Some('{') {
+            self.pos += 1;
+            format!("<mrow>{}</mrow>", self.parse_row(Some('}')))
+        } else {
+            format!("<mrow>{}</mrow>", self.parse_atom().unwrap_or_default())
+        }
+    }
+
+    fn parse_atom(&mut self) -> Option<String> {
+        let c = self.peek()?;
+        self.pos += 1;
+
+        Some(match c {
+            c if c.is_whitespace() => return None,
+            '{' => format!("<mrow>{}</mrow>", self.parse_row(Some('}'))),
+            '}' => return None,
+            '\\' => self.parse_command(),
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(d) = self.peek().filter(|d| d.is_ascii_digit() || *d == '.') {
+                    number.push(d);
+                    self.pos += 1;
+                }
+                format!("<mn>{}</mn>", number)
+            }
+            c if c.is_alphabetic() => format!("<mi>{}</mi>", c),
+            '\'' => "<mo>′</mo>".to_string(),
+            '-' => "<mo>−</mo>".to_string(),
+            c => format!("<mo>{}</mo>", escape(&c.to_string())),
+        })
+    }
+
+    fn parse_command(&mut self) -> String {
+        let name = self.peek_command();
+        self.pos += name.chars().count().max(1);
+
+        if name.is_empty() {
+            // Escaped symbol or spacing command such as `\,` or `\{`
+            return match self.chars.get(self.pos - 1) {
+                Some(',') | Some(';') | Some(':') | Some(' ') | Some('!') => {
+                    "<mspace width=\"0.2em\"/>".to_string()
+                }
+                Some(&c) => format!("<mo>{}</mo>", escape(&c.to_string())),
+                None => String::new(),
+            };
+        }
+
+        match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.parse_argument();
+                let denominator = self.parse_argument();
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                if self.peek() == Some('[') {
+                    self.pos += 1;
+                    let index = format!("<mrow>{}</mrow>", self.parse_row(Some(']')));
+                    let radicand = self.parse_argument();
+                    format!("<mroot>{}{}</mroot>", radicand, index)
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.parse_argument())
+                }
+            }
+            "text" | "mathrm" | "textrm" | "mbox" => {
+                self.skip_whitespace();
+                let mut text = String::new();
+                if self.peek() == Some('{') {
+                    self.pos += 1;
+                    while let Some(c) = self.peek() {
+                        self.pos += 1;
+                        if c == '}' {
+                            break;
+                        }
+                        text.push(c);
+                    }
+                }
+                format!("<mtext>{}</mtext>", escape(&text))
+            }
+            "left" | "right" => {
+                self.skip_whitespace();
+                match self.peek() {
+                    Some('.') => {
+                        self.pos += 1;
+                        String::new()
+                    }
+                    Some('\\') => {
+                        self.pos += 1;
+                        self.parse_command()
+                    }
+                    Some(c) => {
+                        self.pos += 1;
+                        format!("<mo>{}</mo>", escape(&c.to_string()))
+                    }
+                    None => String::new(),
+                }
+            }
+            "quad" | "qquad" => "<mspace width=\"1em\"/>".to_string(),
+            _ => {
+                if let Some(symbol) = greek(&name) {
+                    format!("<mi>{}</mi>", symbol)
+                } else if let Some(symbol) = operator(&name) {
+                    format!("<mo>{}</mo>", symbol)
+                } else {
+                    format!("<mi>{}</mi>", name)
+                }
+            }
+        }
+    }
+
+    /// Letters of the command name starting at the current position
+    fn peek_command(&self) -> String {
+        self.chars[self.pos..]
+            .iter()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+}
+
+fn greek(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" | "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "rho" => "ρ",
+        "sigma" => "σ",
+        "tau" => "τ",
+        "phi" | "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        _ => return None,
+    })
+}
+
+fn operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "int" => "∫",
+        "iint" => "∬",
+        "oint" => "∮",
+        "sum" => "∑",
+        "prod" => "∏",
+        "cdot" => "⋅",
+        "times" => "×",
+        "div" => "÷",
+        "pm" => "±",
+        "mp" => "∓",
+        "le" | "leq" => "≤",
+        "ge" | "geq" => "≥",
+        "ne" | "neq" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "propto" => "∝",
+        "to" | "rightarrow" => "→",
+        "leftarrow" => "←",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftrightarrow" | "iff" => "⇔",
+        "in" => "∈",
+        "notin" => "∉",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "cup" => "∪",
+        "cap" => "∩",
+        "forall" => "∀",
+        "exists" => "∃",
+        "angle" => "∠",
+        "circ" => "∘",
+        "degree" => "°",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "lbrace" => "{",
+        "rbrace" => "}",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_math() {
+        assert_eq!(
+            render(
+                "Solve $x^2 = 4$ for $5 and $$\\int f$$.",
+                MathTarget::MathJax
+            ),
+            "Solve \\(x^2 = 4\\) for $5 and \\[\\int f\\]."
+        );
+        assert_eq!(
+            render("costs $5 or $10", MathTarget::MathJax),
+            "costs $5 or $10"
+        );
+        assert_eq!(render("a \\$x\\$ b", MathTarget::MathJax), "a $x$ b");
+        assert_eq!(render("$a<b$", MathTarget::MathJax), "\\(a&lt;b\\)");
+
+        let canvas = render("$x^2$", MathTarget::CanvasEquation);
+        assert!(canvas.contains("src=\"/equation_images/x%255E2?scale=1\""));
+        assert!(canvas.contains("data-equation-content=\"x^2\""));
+
+        assert_eq!(
+            to_mathml("\\frac{a}{2} + x_1^2", false),
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"inline\"><mrow>\
+             <mfrac><mrow><mi>a</mi></mrow><mrow><mn>2</mn></mrow></mfrac><mo>+</mo>\
+             <msubsup><mi>x</mi><mrow><mn>1</mn></mrow><mrow><mn>2</mn></mrow></msubsup>\
+             </mrow></math>"
+        );
+        assert!(to_mathml("\\sqrt{\\pi} \\le 2", true)
+            .contains("<msqrt><mrow><mi>π</mi></mrow></msqrt><mo>≤</mo>"));
+        let text = "Set $\\{x | x > 0\\}$ now";
+        let spans: Vec<&str> = math_ranges(text).into_iter().map(|r| &text[r]).collect();
+        assert_eq!(spans, ["$\\{x | x > 0\\}$"]);
+        assert_eq!(
+            math_ranges("$5 or \\$x\\$, \\(a|b\\) and $$c$$"),
+            [13..20, 25..30]
+        );
+    }
+}
@@ -0,0 +1,430 @@
+use crate::error::{QtiError, Result};
+use crate::types::{Assessment, MediaFile};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::path::{Component, Path};
+
+/// Folder inside the package that holds media files
+pub const MEDIA_FOLDER: &str = "media";
+
+/// Canvas rewrites this prefix to the imported course's files folder
+pub const CANVAS_FILE_BASE: &str = "$IMS-CC-FILEBASE$";
+
+/// How media files travel with an exported assessment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MediaMode {
+    /// Packaged media stay separate files; `data:` URIs are left inline
+    #[default]
+    Files,
+    /// Packaged media are embedded into the text as `data:` URIs
+    Inline,
+    /// `data:` URIs in the text are decoded into separate packaged files
+    Extract,
+}
+
+/// A `![alt](src)` reference found in question text
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRef {
+    pub alt: String,
+    pub src: String,
+}
+
+fn media_pattern() -> Regex {
+    Regex::new(r"!\[([^\]]*)\]\(([^)\s]+)\)").unwrap()
+}
+
+/// The `src` of an HTML `<img>`, split around its value
+fn html_img_pattern() -> Regex {
+    Regex::new(r#"(<img\b[^>]*?\bsrc=")([^"]+)(")"#).unwrap()
+}
+
+/// References that are neither remote, inline nor already packaged
+fn is_local(src: &str) -> bool {
+    !src.contains("://") && !src.starts_with("data:") && !src.starts_with(CANVAS_FILE_BASE)
+}
+
+/// Read every local media file referenced from the assessment's text,
+/// resolving paths against `base_dir`, add them to `assessment.media` under
+/// the media folder and point the references at their packaged copies.
+pub fn embed_local_files(assessment: &mut Assessment, base_dir: impl AsRef<Path>) -> Result<()> {
+    let base_dir = base_dir.as_ref();
+    let pattern = media_pattern();
+    let mut packaged: HashMap<String, String> = HashMap::new();
+    let mut media = std::mem::take(&mut assessment.media);
+    let mut error = None;
+
+    assessment.visit_text_mut(|text| {
+        if error.is_some() || !pattern.is_match(text) {
+            return;
+        }
+
+        *text = pattern
+            .replace_all(text, |captures: &Captures| {
+                let src = &captures[2];
+                let already_packaged = media.iter().any(|m| m.filename == src);
+                if !is_local(src) || already_packaged || error.is_some() {
+                    return captures[0].to_string();
+                }
+
+                if let Some(filename) = packaged.get(src) {
+                    return format!("![{}]({})", &captures[1], filename);
+                }
+
+                // Only files under base_dir may be packaged
+                let escapes = Path::new(src)
+                    .components()
+                    .any(|part| !matches!(part, Component::Normal(_) | Component::CurDir));
+                if escapes || src.contains('\\') {
+                    error = Some(QtiError::ValidationError(format!(
+                        "Media path must be relative to the quiz file: {}",
+                        src
+                    )));
+                    return captures[0].to_string();
+                }
+
+                let path = base_dir.join(src);
+                let content = match std::fs::read(&path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        error = Some(QtiError::IoError(std::io::Error::new(
+                            e.kind(),
+                            format!("Cannot read media file {}: {}", path.display(), e),
+                        )));
+                        return captures[0].to_string();
+                    }
+                };
+
+                let filename = unique_filename(&media, src);
+                packaged.insert(src.to_string(), filename.clone());
+                media.push(MediaFile {
+                    filename: filename.clone(),
+                    content,
+                });
+                format!("![{}]({})", &captures[1], filename)
+            })
+            .to_string();
+    });
+
+    assessment.media = media;
+    match error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Replace references to packaged media with base64 `data:` URIs and drop
+/// the now-embedded files from `assessment.media`
+pub fn inline_media(assessment: &mut Assessment) {
+    let pattern = media_pattern();
+    let media = std::mem::take(&mut assessment.media);
+
+    assessment.visit_text_mut(|text| {
+        if !pattern.is_match(text) {
+            return;
+        }
+        *text = pattern
+            .replace_all(text, |captures: &Captures| {
+                match media.iter().find(|m| m.filename == captures[2]) {
+                    Some(file) => format!(
+                        "![{}](data:{};base64,{})",
+                        &captures[1],
+                        mime_type(&file.filename),
+                        BASE64.encode(&file.content)
+                    ),
+                    None => captures[0].to_string(),
+                }
+            })
+            .to_string();
+    });
+}
+
+/// Decode base64 `data:` URIs, written as `![alt](data:...)` or as the `src`
+/// of an HTML `<img>`, into files under the media folder
+pub fn extract_data_uris(assessment: &mut Assessment) -> Result<()> {
+    let markdown = Regex::new(r"(!\[[^\]]*\]\()(data:[^)\s]+)(\))").unwrap();
+    let html = html_img_pattern();
+    let mut media = std::mem::take(&mut assessment.media);
+    // The same image pasted twice is packaged once
+    let mut extracted: HashMap<String, String> = HashMap::new();
+    let mut error = None;
+
+    assessment.visit_text_mut(|text| {
+        for pattern in [&markdown, &html] {
+            if error.is_some() || !pattern.is_match(text) {
+                continue;
+            }
+            *text = pattern
+                .replace_all(text, |captures: &Captures| {
+                    if let Some(filename) = extracted.get(&captures[2]) {
+                        return format!("{}{}{}", &captures[1], filename, &captures[3]);
+                    }
+                    if !captures[2].starts_with("data:") {
+                        return captures[0].to_string();
+                    }
+                    match decode_data_uri(&captures[2]) {
+                        Ok(Some((mime, content))) => {
+                            let filename =
+                                unique_filename(&media, &format!("image.{}", extension(mime)));
+                            media.push(MediaFile {
+                                filename: filename.clone(),
+                                content,
+                            });
+                            extracted.insert(captures[2].to_string(), filename.clone());
+                            format!("{}{}{}", &captures[1], filename, &captures[3])
+                        }
+                        Ok(None) => captures[0].to_string(),
+                        Err(e) => {
+                            error.get_or_insert(e);
+                            captures[0].to_string()
+                        }
+                    }
+                })
+                .to_string();
+        }
+    });
+
+    assessment.media = media;
+    match error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// MIME type and bytes of a base64 `data:` URI; `None` for other encodings
+fn decode_data_uri(uri: &str) -> Result<Option<(&'static str, Vec<u8>)>> {
+    let Some((header, data)) = uri.split_once(',') else {
+        return Ok(None);
+    };
+    if !header.ends_with(";base64") {
+        return Ok(None);
+    }
+
+    let content = BASE64
+        .decode(data)
+        .map_err(|e| QtiError::ParseError(format!("Invalid base64 data URI: {}", e)))?;
+    Ok(Some((mime_type(uri), content)))
+}
+
+fn extension(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        _ => "bin",
+    }
+}
+
+/// `media/<name>`, suffixed with a counter if that name is already taken
+fn unique_filename(media: &[MediaFile], src: &str) -> String {
+    let name = Path::new(src)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("file");
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (name, String::new()),
+    };
+
+    let mut candidate = format!("{}/{}", MEDIA_FOLDER, name);
+    let mut counter = 1;
+    while media.iter().any(|m| m.filename == candidate) {
+        counter += 1;
+        candidate = format!("{}/{}_{}{}", MEDIA_FOLDER, stem, counter, extension);
+    }
+    candidate
+}
+
+/// Path a packaged file is referenced by from QTI content
+pub fn resolve_src(src: &str, canvas: bool) -> String {
+    if canvas && src.starts_with(&format!("{}/", MEDIA_FOLDER)) {
+        format!("{}/{}", CANVAS_FILE_BASE, src)
+    } else {
+        src.to_string()
+    }
+}
+
+/// Replace `![alt](src)` with HTML: `<img>` for images, a link otherwise.
+/// Packaged files already referenced by an HTML `<img>` are resolved as well.
+pub fn render_html(text: &str, canvas: bool) -> String {
+    let text = html_img_pattern().replace_all(text, |captures: &Captures| {
+        format!(
+            "{}{}{}",
+            &captures[1],
+            resolve_src(&captures[2], canvas),
+            &captures[3]
+        )
+    });
+    media_pattern()
+        .replace_all(&text, |captures: &Captures| {
+            let alt = escape_attribute(&captures[1]);
+            let src = escape_attribute(&resolve_src(&captures[2], canvas));
+            if mime_type(&captures[2]).starts_with("image/") || captures[2].starts_with("data:") {
+                format!("<img src=\"{}\" alt=\"{}\">", src, alt)
+            } else {
+                let label = if alt.is_empty() { src.clone() } else { alt };
+                format!("<a href=\"{}\">{}</a>", src, label)
+            }
+        })
+        .to_string()
+}
+
+/// Remove media markup from `text`, returning the remaining text and the
+/// references in order of appearance. Inline `data:` images stay in the text.
+pub fn extract_media(text: &str) -> (String, Vec<MediaRef>) {
+    let pattern = media_pattern();
+    let mut refs = Vec::new();
+    let remaining = pattern
+        .replace_all(text, |captures: &Captures| {
+            if captures[2].starts_with("data:") {
+                return captures[0].to_string();
+            }
+            refs.push(MediaRef {
+                alt: captures[1].to_string(),
+                src: captures[2].to_string(),
+            });
+            String::new()
+        })
+        .trim()
+        .to_string();
+    (remaining, refs)
+}
+
+/// MIME type guessed from a file name's extension
+pub fn mime_type(src: &str) -> &'static str {
+    if let Some(header) = src.strip_prefix("data:") {
+        return match header.split([';', ',']).next() {
+            Some("image/png") => "image/png",
+            Some("image/jpeg") => "image/jpeg",
+            Some("image/gif") => "image/gif",
+            Some("image/svg+xml") => "image/svg+xml",
+            _ => "application/octet-stream",
+        };
+    }
+
+    let extension = src
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+pub(crate) fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Question, QuestionType};
+
+    #[test]
+    fn test_embed_local_files() {
+        let dir = tempfile::tempdir().expect("Should create temp dir");
+        std::fs::create_dir(dir.path().join("img")).unwrap();
+        std::fs::write(dir.path().join("diagram.png"), b"png").unwrap();
+        std::fs::write(dir.path().join("img/diagram.png"), b"other").unwrap();
+
+        let mut assessment = Assessment::new("Media");
+        assessment.questions.push(Question::new(
+            "See ![A diagram](diagram.png) and ![Another](img/diagram.png) and ![](diagram.png) and ![Web](https://example.com/a.png)",
+            QuestionType::Essay {
+                expected_length: None,
+                rich_text: true,
+            },
+        ));
+
+        embed_local_files(&mut assessment, dir.path()).expect("Should embed media");
+
+        assert_eq!(assessment.media.len(), 2);
+        assert_eq!(assessment.media[0].filename, "media/diagram.png");
+        assert_eq!(assessment.media[1].filename, "media/diagram_2.png");
+        assert_eq!(assessment.media[1].content, b"other");
+        assert_eq!(
+            assessment.questions[0].text,
+            "See ![A diagram](media/diagram.png) and ![Another](media/diagram_2.png) and ![](media/diagram.png) and ![Web](https://example.com/a.png)"
+        );
+
+        let html = render_html(&assessment.questions[0].text, true);
+        assert!(
+            html.contains("<img src=\"$IMS-CC-FILEBASE$/media/diagram.png\" alt=\"A diagram\">")
+        );
+        assert!(html.contains("<img src=\"https://example.com/a.png\" alt=\"Web\">"));
+
+        inline_media(&mut assessment);
+        assert!(assessment.media.is_empty());
+        assert!(assessment.questions[0]
+            .text
+            .contains("![A diagram](data:image/png;base64,cG5n)"));
+
+        extract_data_uris(&mut assessment).expect("Should extract data URIs");
+        assert_eq!(assessment.media.len(), 2);
+        assert_eq!(assessment.media[0].filename, "media/image.png");
+        assert_eq!(assessment.media[0].content, b"png");
+        assert!(assessment.questions[0]
+            .text
+            .starts_with("See ![A diagram](media/image.png)"));
+        assert!(assessment.questions[0]
+            .text
+            .contains("and ![](media/image.png)"));
+
+        let mut html = Assessment::new("Html");
+        html.questions.push(Question::new(
+            "<img alt=\"dot\" src=\"data:image/gif;base64,R0lG\"> <img src=\"data:image/gif;base64,R0lG\">",
+            QuestionType::TextOnly,
+        ));
+        extract_data_uris(&mut html).expect("Should extract data URIs");
+        assert_eq!(html.media.len(), 1);
+        assert_eq!(
+            html.questions[0].text,
+            "<img alt=\"dot\" src=\"media/image.gif\"> <img src=\"media/image.gif\">"
+        );
+        assert_eq!(
+            render_html(&html.questions[0].text, true),
+            "<img alt=\"dot\" src=\"$IMS-CC-FILEBASE$/media/image.gif\"> <img src=\"$IMS-CC-FILEBASE$/media/image.gif\">"
+        );
+
+        let mut missing = Assessment::new("Missing");
+        missing
+            .questions
+            .push(Question::new("![x](nope.png)", QuestionType::TextOnly));
+        assert!(embed_local_files(&mut missing, dir.path()).is_err());
+
+        std::fs::write(dir.path().join("secret.png"), b"secret").unwrap();
+        let base = dir.path().join("img");
+        for src in ["../secret.png", "img/../../secret.png"]
+            .map(String::from)
+            .into_iter()
+            .chain([dir.path().join("secret.png").display().to_string()])
+        {
+            let mut outside = Assessment::new("Outside");
+            outside.questions.push(Question::new(
+                format!("![x]({})", src),
+                QuestionType::TextOnly,
+            ));
+            let error = embed_local_files(&mut outside, &base).unwrap_err();
+            assert!(error.to_string().contains("must be relative"), "{}", src);
+            assert!(outside.media.is_empty());
+        }
+    }
+}
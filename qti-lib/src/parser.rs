@@ -1,6 +1,17 @@
 use crate::error::{QtiError, Result};
-use crate::types::{AcceptableAnswer, Assessment, Choice, Feedback, Question, QuestionType};
+use crate::formula;
+use crate::ids;
+use crate::math;
+use crate::media;
+use crate::pattern;
+use crate::types::{
+    AcceptableAnswer, Assessment, Attempts, Choice, Dropdown, Feedback, FormulaVariable, Passage,
+    Question, QuestionBank, QuestionGroup, QuestionType, QuizType,
+};
 use regex::Regex;
+use std::collections::{hash_map::Entry, HashMap};
+use std::ops::Range;
+use std::path::Path;
 
 pub struct Parser {
     question_pattern: Regex,
@@ -12,6 +23,22 @@ pub struct Parser {
     numerical_pattern: Regex,
     essay_pattern: Regex,
     upload_pattern: Regex,
+    dropdown_pattern: Regex,
+    formula_pattern: Regex,
+    formula_var_pattern: Regex,
+    formula_option_pattern: Regex,
+    passage_pattern: Regex,
+    passage_end_pattern: Regex,
+    group_pattern: Regex,
+    group_option_pattern: Regex,
+    group_end_pattern: Regex,
+    bank_from_pattern: Regex,
+    bank_pull_pattern: Regex,
+    text_only_pattern: Regex,
+    likert_pattern: Regex,
+    quiz_type_pattern: Regex,
+    setting_pattern: Regex,
+    banks: HashMap<String, QuestionBank>, // Question banks by the name quizzes refer to them with
 }
 
 impl Parser {
@@ -27,11 +54,126 @@ impl Parser {
                 .unwrap(),
             essay_pattern: Regex::new(r"^_{3,}$").unwrap(),
             upload_pattern: Regex::new(r"^\^{3,}$").unwrap(),
+            dropdown_pattern: Regex::new(r"\{([^{}]*\|[^{}]*)\}").unwrap(),
+            formula_pattern: Regex::new(r"^formula:\s*([^±]+?)\s*(?:±\s*(\d*\.?\d+))?\s*$")
+                .unwrap(),
+            formula_var_pattern: Regex::new(
+                r"^var\s+([A-Za-z_]\w*)\s*:\s*([-+]?\d*\.?\d+)\s*\.\.\s*([-+]?\d*\.?\d+)\s*$",
+            )
+            .unwrap(),
+            formula_option_pattern: Regex::new(r"^(decimals|sets):\s*(\d+)\s*$").unwrap(),
+            passage_pattern: Regex::new(r"(?i)^passage:\s*(.*)$").unwrap(),
+            passage_end_pattern: Regex::new(r"(?i)^end passage\s*$").unwrap(),
+            group_pattern: Regex::new(r"(?i)^group:\s*(.*)$").unwrap(),
+            group_option_pattern: Regex::new(
+                r"(?i)^(?:pick\s+(\d+)|(\d*\.?\d+)\s*(?:points?|pts?))$",
+            )
+            .unwrap(),
+            group_end_pattern: Regex::new(r"(?i)^end group\s*$").unwrap(),
+            bank_from_pattern: Regex::new(r"(?i)^from:\s*(.+?)(?:\s+tagged\s+(.+?))?\s*$").unwrap(),
+            bank_pull_pattern: Regex::new(
+                r"(?i)^pull:\s*(?:(\d+)\s+from\s+)?(.+?)(?:\s+tagged\s+(.+?))?\s*$",
+            )
+            .unwrap(),
+            text_only_pattern: Regex::new(r"(?i)^text:\s*(.*)$").unwrap(),
+            likert_pattern: Regex::new(r"(?i)^likert(?::\s*(.*))?$").unwrap(),
+            quiz_type_pattern: Regex::new(r"(?i)^quiz[_ ]type:\s*(.+?)\s*$").unwrap(),
+            setting_pattern: Regex::new(
+                r"(?i)^(time_limit|attempts|shuffle_answers|shuffle_questions|show_correct_answers|one_question_at_a_time|cant_go_back|access_code):\s*(.+?)\s*$",
+            )
+            .unwrap(),
+            banks: HashMap::new(),
+        }
+    }
+
+    /// Make a question bank available to `from:` and `pull:` lines as `name`
+    pub fn with_bank(mut self, name: impl Into<String>, bank: QuestionBank) -> Self {
+        self.banks.insert(name.into(), bank);
+        self
+    }
+
+    /// Parse a text file, packaging any local media it references.
+    ///
+    /// Media paths are resolved relative to the file's directory.
+    pub fn parse_file(&self, path: impl AsRef<Path>) -> Result<Assessment> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        // Banks named in `from:` / `pull:` lines are files next to the quiz
+        let mut banks = self.banks.clone();
+        for line in content.lines() {
+            let name = self
+                .bank_from_pattern
+                .captures(line)
+                .map(|captures| captures[1].to_string())
+                .or_else(|| {
+                    self.bank_pull_pattern
+                        .captures(line)
+                        .map(|captures| captures[2].to_string())
+                });
+            if let Some(name) = name {
+                if let Entry::Vacant(entry) = banks.entry(name) {
+                    let bank = self.parse_bank_file(base_dir.join(entry.key()))?;
+                    entry.insert(bank);
+                }
+            }
+        }
+
+        let mut assessment = self.parse_with_banks(&content, &banks)?;
+        media::embed_local_files(&mut assessment, base_dir)?;
+        Ok(assessment)
+    }
+
+    /// Parse a question bank file
+    pub fn parse_bank_file(&self, path: impl AsRef<Path>) -> Result<QuestionBank> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            QtiError::IoError(std::io::Error::new(
+                e.kind(),
+                format!("Cannot read question bank {}: {}", path.display(), e),
+            ))
+        })?;
+        self.parse_bank_from(&content, true)
+    }
+
+    /// Parse a question bank: the quiz format, with optional `tags:` lines per question.
+    ///
+    /// Bank questions get content-derived ids so every quiz shares them.
+    pub fn parse_bank(&self, input: &str) -> Result<QuestionBank> {
+        self.parse_bank_from(input, false)
+    }
+
+    /// Parse a question bank; a file's bank id also depends on its text
+    fn parse_bank_from(&self, input: &str, from_file: bool) -> Result<QuestionBank> {
+        let mut assessment = self.parse_with_banks(input, &HashMap::new())?;
+        if !assessment.groups.is_empty() || !assessment.passages.is_empty() {
+            return Err(QtiError::ParseError(
+                "Question banks cannot contain groups or passages".to_string(),
+            ));
         }
+
+        let mut bank = if from_file {
+            QuestionBank::from_source(assessment.title.clone(), input)
+        } else {
+            QuestionBank::new(assessment.title.clone())
+        };
+        assessment.identifier = bank.id.clone();
+        ids::assign_stable_ids(&mut assessment);
+        bank.questions = assessment.questions;
+        Ok(bank)
     }
 
     /// Parse text input into an Assessment
     pub fn parse(&self, input: &str) -> Result<Assessment> {
+        self.parse_with_banks(input, &self.banks)
+    }
+
+    fn parse_with_banks(
+        &self,
+        input: &str,
+        banks: &HashMap<String, QuestionBank>,
+    ) -> Result<Assessment> {
         let mut assessment = Assessment::new("Untitled Assessment");
         let lines: Vec<&str> = input.lines().collect();
         let mut i = 0;
@@ -40,9 +182,144 @@ impl Parser {
             assessment.title = title;
         }
 
+        if let Some(identifier) = self.extract_identifier(&lines)? {
+            assessment.identifier = identifier;
+        }
+
+        if let Some(quiz_type) = self.extract_quiz_type(&lines)? {
+            assessment.metadata.quiz_type = quiz_type;
+        }
+        self.extract_settings(&lines, &mut assessment)?;
+
+        let mut current_passage: Option<String> = None;
+        let mut current_group: Option<String> = None;
+        let mut group_points: Vec<(String, Option<f32>)> = Vec::new();
+
         while i < lines.len() {
+            let in_group = |message: &str| QtiError::InvalidFormat {
+                line: i + 1,
+                message: message.to_string(),
+            };
+
+            let bank = |name: &str| {
+                banks.get(name).ok_or_else(|| QtiError::InvalidFormat {
+                    line: i + 1,
+                    message: format!("Unknown question bank '{}'", name),
+                })
+            };
+            let current_bank_group = current_group
+                .as_deref()
+                .and_then(|id| assessment.group(id))
+                .is_some_and(|group| group.bank.is_some());
+
             if self.question_pattern.is_match(lines[i]) {
-                let question = self.parse_question(&lines, &mut i)?;
+                if current_bank_group {
+                    return Err(in_group(
+                        "A question group drawing from a bank cannot also list questions",
+                    ));
+                }
+                let mut question = self.parse_question(&lines, &mut i)?;
+                question.passage = current_passage.clone();
+                question.group = current_group.clone();
+                assessment.questions.push(question);
+            } else if let Some(captures) = self.group_pattern.captures(lines[i]) {
+                if current_group.is_some() {
+                    return Err(in_group("Question groups cannot be nested"));
+                }
+                let (group, points) = self.parse_group_header(&captures[1], i + 1)?;
+                current_group = Some(group.id.clone());
+                group_points.push((group.id.clone(), points));
+                assessment.groups.push(group);
+                i += 1;
+            } else if let Some(captures) = self.bank_from_pattern.captures(lines[i]) {
+                let Some(group_id) = current_group.clone() else {
+                    return Err(in_group("'from:' must be inside a question group"));
+                };
+                if current_bank_group
+                    || assessment
+                        .questions
+                        .iter()
+                        .any(|q| q.group.as_ref() == Some(&group_id))
+                {
+                    return Err(in_group(
+                        "A question group drawing from a bank cannot also list questions",
+                    ));
+                }
+
+                let bank = bank(&captures[1])?;
+                let tags = split_tags(captures.get(2).map(|m| m.as_str()));
+                let selected = bank.select(&tags);
+                if selected.is_empty() {
+                    return Err(in_group("No questions in the bank match the tags"));
+                }
+
+                let group = assessment.groups.last_mut().unwrap();
+                if group.title == DEFAULT_GROUP_TITLE {
+                    group.title = bank.selection_title(&tags);
+                }
+                group.bank = Some(bank.bank_ref(&tags));
+                for question in selected {
+                    let mut question = assessment.copy_from_bank(bank, question);
+                    question.group = Some(group_id.clone());
+                    assessment.questions.push(question);
+                }
+                i += 1;
+            } else if let Some(captures) = self.bank_pull_pattern.captures(lines[i]) {
+                if current_group.is_some() {
+                    return Err(in_group("Use 'from:' to draw a question group from a bank"));
+                }
+                let bank = bank(&captures[2])?;
+                let tags = split_tags(captures.get(3).map(|m| m.as_str()));
+                let count = captures.get(1).and_then(|m| m.as_str().parse().ok());
+                let start = assessment.questions.len();
+                assessment.pull_from_bank(bank, &tags, count);
+                if assessment.questions.len() == start {
+                    return Err(in_group("No questions in the bank match the tags"));
+                }
+                for question in &mut assessment.questions[start..] {
+                    question.passage = current_passage.clone();
+                }
+                i += 1;
+            } else if self.group_end_pattern.is_match(lines[i]) {
+                current_group = None;
+                i += 1;
+            } else if current_group.is_some()
+                && (self.passage_pattern.is_match(lines[i])
+                    || self.passage_end_pattern.is_match(lines[i])
+                    || self.text_only_pattern.is_match(lines[i]))
+            {
+                return Err(in_group(
+                    "Passages and text items cannot be placed inside a question group",
+                ));
+            } else if let Some(captures) = self.passage_pattern.captures(lines[i]) {
+                let title = captures[1].trim().to_string();
+                let line = i + 1;
+                i += 1;
+                let text = self.parse_block(&lines, &mut i);
+                if text.is_empty() {
+                    return Err(QtiError::InvalidFormat {
+                        line,
+                        message: "Passage has no text".to_string(),
+                    });
+                }
+                let passage = Passage::new(title, text);
+                current_passage = Some(passage.id.clone());
+                assessment.passages.push(passage);
+            } else if self.passage_end_pattern.is_match(lines[i]) {
+                current_passage = None;
+                i += 1;
+            } else if let Some(captures) = self.text_only_pattern.captures(lines[i]) {
+                let first = captures[1].trim().to_string();
+                i += 1;
+                let rest = self.parse_block(&lines, &mut i);
+                let text = match (first.is_empty(), rest.is_empty()) {
+                    (false, false) => format!("<p>{}</p>{}", first, rest),
+                    (false, true) => first,
+                    _ => rest,
+                };
+                let mut question = Question::new(text, QuestionType::TextOnly);
+                question.points = 0.0;
+                question.passage = current_passage.clone();
                 assessment.questions.push(question);
             } else {
                 i += 1;
@@ -55,6 +332,31 @@ impl Parser {
             ));
         }
 
+        // Surveys have no right answers; a graded survey still gives points for answering
+        match assessment.metadata.quiz_type {
+            QuizType::Survey => {
+                for question in &mut assessment.questions {
+                    question.graded = false;
+                    question.points = 0.0;
+                }
+            }
+            QuizType::GradedSurvey => {
+                for question in &mut assessment.questions {
+                    question.participation = question.graded
+                        && !matches!(question.question_type, QuestionType::TextOnly);
+                    question.graded = false;
+                }
+            }
+            _ => {}
+        }
+
+        for question in &assessment.questions {
+            self.check_graded(question)?;
+        }
+
+        self.check_ids(&assessment)?;
+        self.apply_groups(&mut assessment, &group_points)?;
+
         Ok(assessment)
     }
 
@@ -70,11 +372,309 @@ impl Parser {
         None
     }
 
+    /// Graded multiple choice questions need a correct answer to score against
+    fn check_graded(&self, question: &Question) -> Result<()> {
+        if let QuestionType::MultipleChoice { choices, .. } = &question.question_type {
+            let correct_count = choices.iter().filter(|c| c.correct).count();
+            if question.graded && correct_count != 1 {
+                return Err(QtiError::ParseError(format!(
+                    "Multiple choice question must have exactly 1 correct answer, found {}",
+                    correct_count
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read `Title, pick N, P points` from a `group:` line; both options are optional
+    fn parse_group_header(
+        &self,
+        header: &str,
+        line: usize,
+    ) -> Result<(QuestionGroup, Option<f32>)> {
+        let mut parts: Vec<&str> = header.split(',').map(str::trim).collect();
+        let mut pick = 0;
+        let mut points = None;
+
+        while parts.len() > 1 {
+            let Some(captures) = self.group_option_pattern.captures(parts[parts.len() - 1]) else {
+                break;
+            };
+            if let Some(count) = captures.get(1) {
+                pick = count.as_str().parse().unwrap_or(0);
+                if pick == 0 {
+                    return Err(QtiError::InvalidFormat {
+                        line,
+                        message: "A question group must pick at least 1 question".to_string(),
+                    });
+                }
+            } else if let Some(value) = captures.get(2) {
+                points = value.as_str().parse().ok();
+            }
+            parts.pop();
+        }
+
+        let title = parts.join(", ");
+        let title = if title.is_empty() {
+            DEFAULT_GROUP_TITLE.to_string()
+        } else {
+            title
+        };
+        Ok((
+            QuestionGroup::new(title, pick, points.unwrap_or(1.0)),
+            points,
+        ))
+    }
+
+    /// Fill in each group's defaults and give its questions the group's points
+    fn apply_groups(
+        &self,
+        assessment: &mut Assessment,
+        group_points: &[(String, Option<f32>)],
+    ) -> Result<()> {
+        for (group, (id, points)) in assessment.groups.iter_mut().zip(group_points) {
+            let members: Vec<&mut Question> = assessment
+                .questions
+                .iter_mut()
+                .filter(|q| q.group.as_ref() == Some(id))
+                .collect();
+
+            if members.is_empty() {
+                return Err(QtiError::ParseError(format!(
+                    "Question group '{}' has no questions",
+                    group.title
+                )));
+            }
+            if group.pick == 0 {
+                group.pick = members.len();
+            }
+            if group.pick > members.len() {
+                return Err(QtiError::ParseError(format!(
+                    "Question group '{}' picks {} questions but only has {}",
+                    group.title,
+                    group.pick,
+                    members.len()
+                )));
+            }
+
+            match points {
+                Some(points) => {
+                    for question in members.into_iter().filter(|q| q.earns_points()) {
+                        question.points = *points;
+                    }
+                }
+                None => {
+                    // Every drawn question is worth the same, so members must agree
+                    let mut scored = members.iter().filter(|q| q.earns_points());
+                    let first = scored.next().map_or(members[0].points, |q| q.points);
+                    if scored.any(|q| q.points != first) {
+                        return Err(QtiError::ParseError(format!(
+                            "Question group '{}' mixes questions worth different points; give the group a points value",
+                            group.title
+                        )));
+                    }
+                    group.points_per_question = first;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ids given with `id:` must be usable as QTI idents and unique
+    fn check_ids(&self, assessment: &Assessment) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for question in &assessment.questions {
+            if ids::is_generated(&question.id, "question") {
+                continue;
+            }
+            if !ids::is_valid_ident(&question.id) {
+                return Err(QtiError::ParseError(format!(
+                    "Invalid question id '{}': use letters, digits, '_', '-' or '.', starting with a letter",
+                    question.id
+                )));
+            }
+            if !seen.insert(question.id.as_str()) {
+                return Err(QtiError::ParseError(format!(
+                    "Duplicate question id '{}'",
+                    question.id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an `id:` header line, which must come before any question or block
+    fn extract_identifier(&self, lines: &[&str]) -> Result<Option<String>> {
+        for (index, line) in lines.iter().enumerate() {
+            if self.question_pattern.is_match(line)
+                || self.passage_pattern.is_match(line)
+                || self.text_only_pattern.is_match(line)
+                || self.group_pattern.is_match(line)
+            {
+                break;
+            }
+            if let Some(id) = line
+                .strip_prefix("id:")
+                .or_else(|| line.strip_prefix("ID:"))
+            {
+                let id = id.trim();
+                if !ids::is_valid_ident(id) {
+                    return Err(QtiError::InvalidFormat {
+                        line: index + 1,
+                        message: format!(
+                            "Invalid quiz id '{}': use letters, digits, '_', '-' or '.', starting with a letter",
+                            id
+                        ),
+                    });
+                }
+                return Ok(Some(id.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read a `quiz_type:` header line from before the first question
+    fn extract_quiz_type(&self, lines: &[&str]) -> Result<Option<QuizType>> {
+        for (index, line) in lines.iter().enumerate() {
+            if self.question_pattern.is_match(line) {
+                break;
+            }
+            if let Some(captures) = self.quiz_type_pattern.captures(line) {
+                let quiz_type = match captures[1].to_lowercase().replace(' ', "_").as_str() {
+                    "assignment" => QuizType::Assignment,
+                    "practice_quiz" | "practice" => QuizType::PracticeQuiz,
+                    "graded_survey" => QuizType::GradedSurvey,
+                    "survey" => QuizType::Survey,
+                    other => {
+                        return Err(QtiError::InvalidFormat {
+                            line: index + 1,
+                            message: format!("Unknown quiz type '{}'", other),
+                        })
+                    }
+                };
+                return Ok(Some(quiz_type));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read quiz setting header lines (`attempts: 3`, `access_code: ...`) from
+    /// before the first question
+    fn extract_settings(&self, lines: &[&str], assessment: &mut Assessment) -> Result<()> {
+        for (index, line) in lines.iter().enumerate() {
+            if self.question_pattern.is_match(line) {
+                break;
+            }
+            let Some(captures) = self.setting_pattern.captures(line) else {
+                continue;
+            };
+
+            let value = &captures[2];
+            let invalid = |expected: &str| QtiError::InvalidFormat {
+                line: index + 1,
+                message: format!(
+                    "Invalid value '{}' for {}: expected {}",
+                    value, &captures[1], expected
+                ),
+            };
+            let flag = || match value.to_lowercase().as_str() {
+                "yes" | "true" | "on" => Ok(true),
+                "no" | "false" | "off" => Ok(false),
+                _ => Err(invalid("yes or no")),
+            };
+
+            let metadata = &mut assessment.metadata;
+            match captures[1].to_lowercase().as_str() {
+                "time_limit" => {
+                    assessment.time_limit = Some(value.parse().map_err(|_| invalid("minutes"))?);
+                }
+                "attempts" => {
+                    metadata.allowed_attempts = Some(if value.eq_ignore_ascii_case("unlimited") {
+                        Attempts::Unlimited
+                    } else {
+                        match value.parse() {
+                            Ok(0) | Err(_) => {
+                                return Err(invalid("a positive number or unlimited"))
+                            }
+                            Ok(attempts) => Attempts::Limited(attempts),
+                        }
+                    });
+                }
+                "shuffle_answers" => metadata.shuffle_answers = flag()?,
+                "shuffle_questions" => metadata.shuffle_questions = flag()?,
+                "show_correct_answers" => metadata.show_correct_answers = flag()?,
+                "one_question_at_a_time" => metadata.one_question_at_a_time = flag()?,
+                "cant_go_back" => metadata.cant_go_back = flag()?,
+                _ => metadata.access_code = Some(value.to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect free-form lines up to the next question or directive, one
+    /// `<p>` per blank-line separated paragraph
+    fn parse_block(&self, lines: &[&str], i: &mut usize) -> String {
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+
+        while *i < lines.len() {
+            let line = lines[*i];
+            if self.question_pattern.is_match(line)
+                || self.passage_pattern.is_match(line)
+                || self.passage_end_pattern.is_match(line)
+                || self.text_only_pattern.is_match(line)
+                || self.group_pattern.is_match(line)
+                || self.group_end_pattern.is_match(line)
+            {
+                break;
+            }
+
+            if line.trim().is_empty() {
+                if !current.is_empty() {
+                    paragraphs.push(current.join(" "));
+                    current.clear();
+                }
+            } else {
+                current.push(line.trim());
+            }
+            *i += 1;
+        }
+
+        if !current.is_empty() {
+            paragraphs.push(current.join(" "));
+        }
+
+        paragraphs
+            .iter()
+            .map(|p| format!("<p>{}</p>", p))
+            .collect::<String>()
+    }
+
     fn parse_question(&self, lines: &[&str], i: &mut usize) -> Result<Question> {
         let question_line = lines[*i];
         let text = self.question_pattern.replace(question_line, "").to_string();
         *i += 1;
 
+        // Braces inside math such as `$\{x | x > 0\}$` are not dropdowns, and
+        // unstarred braces are only menus when no choices or answers follow
+        let math = math::math_ranges(&text);
+        let menus: Vec<_> = self
+            .dropdown_pattern
+            .captures_iter(&text)
+            .filter(|captures| !overlaps(&math, &captures.get(0).unwrap()))
+            .collect();
+        let starred = menus.iter().any(|captures| {
+            captures[1]
+                .split('|')
+                .any(|option| option.trim().starts_with('*'))
+        });
+        if !menus.is_empty() && (starred || self.determine_question_type(lines, *i).is_err()) {
+            let (text, question_type) = self.parse_multiple_dropdowns(&text, *i)?;
+            let mut question = Question::new(text, question_type);
+            self.parse_feedback_and_solution(lines, i, &mut question);
+            return Ok(question);
+        }
+
         let question_type_hint = self.determine_question_type(lines, *i)?;
 
         let question_type = match question_type_hint {
@@ -82,6 +682,8 @@ impl Parser {
             QuestionTypeHint::MultipleAnswer => self.parse_multiple_answer(lines, i)?,
             QuestionTypeHint::ShortAnswer => self.parse_short_answer(lines, i)?,
             QuestionTypeHint::Numerical => self.parse_numerical(lines, i)?,
+            QuestionTypeHint::Formula => self.parse_formula(lines, i, &text)?,
+            QuestionTypeHint::Likert => self.parse_likert(lines, i),
             QuestionTypeHint::Essay => {
                 *i += 1; // Skip the ___ line
                 QuestionType::Essay {
@@ -102,6 +704,10 @@ impl Parser {
         };
 
         let mut question = Question::new(text, question_type);
+        if matches!(question.question_type, QuestionType::Likert { .. }) {
+            question.graded = false;
+            question.points = 0.0;
+        }
 
         self.parse_feedback_and_solution(lines, i, &mut question);
 
@@ -115,7 +721,12 @@ impl Parser {
 
         let line = lines[start];
 
-        if self.mc_correct_pattern.is_match(line) || self.mc_incorrect_pattern.is_match(line) {
+        if self.formula_pattern.is_match(line) {
+            Ok(QuestionTypeHint::Formula)
+        } else if self.likert_pattern.is_match(line) {
+            Ok(QuestionTypeHint::Likert)
+        } else if self.mc_correct_pattern.is_match(line) || self.mc_incorrect_pattern.is_match(line)
+        {
             Ok(QuestionTypeHint::MultipleChoice)
         } else if self.ma_correct_pattern.is_match(line) || self.ma_incorrect_pattern.is_match(line)
         {
@@ -130,7 +741,7 @@ impl Parser {
             Ok(QuestionTypeHint::FileUpload)
         } else {
             Err(QtiError::InvalidFormat {
-                line: start,
+                line: start + 1,
                 message: format!("Cannot determine question type from: {}", line),
             })
         }
@@ -166,8 +777,9 @@ impl Parser {
             ));
         }
 
+        // Zero correct answers is allowed for ungraded questions; see `check_graded`
         let correct_count = choices.iter().filter(|c| c.correct).count();
-        if correct_count != 1 {
+        if correct_count > 1 {
             return Err(QtiError::ParseError(format!(
                 "Multiple choice question must have exactly 1 correct answer, found {}",
                 correct_count
@@ -222,8 +834,18 @@ impl Parser {
             let line = lines[*i];
 
             if self.shortans_pattern.is_match(line) {
-                let text = self.shortans_pattern.replace(line, "").to_string();
-                answers.push(AcceptableAnswer::new(text));
+                let text = self.shortans_pattern.replace(line, "").trim().to_string();
+                // `/regex/` accepts any response matching the whole pattern
+                if text.len() > 2 && text.starts_with('/') && text.ends_with('/') {
+                    let regex = &text[1..text.len() - 1];
+                    pattern::validate(regex).map_err(|e| QtiError::InvalidFormat {
+                        line: *i + 1,
+                        message: e.to_string(),
+                    })?;
+                    answers.push(AcceptableAnswer::pattern(regex));
+                } else {
+                    answers.push(AcceptableAnswer::new(text));
+                }
                 *i += 1;
             } else if self.question_pattern.is_match(line) {
                 break;
@@ -274,6 +896,171 @@ impl Parser {
         }
     }
 
+    /// Parse `likert` (five-point agreement scale) or `likert: Low | ... | High`
+    fn parse_likert(&self, lines: &[&str], i: &mut usize) -> QuestionType {
+        let labels: Vec<String> = self
+            .likert_pattern
+            .captures(lines[*i])
+            .and_then(|captures| captures.get(1))
+            .map(|m| {
+                m.as_str()
+                    .split('|')
+                    .map(|label| label.trim().to_string())
+                    .filter(|label| !label.is_empty())
+                    .collect()
+            })
+            .filter(|labels: &Vec<String>| !labels.is_empty())
+            .unwrap_or_else(|| {
+                [
+                    "Strongly disagree",
+                    "Disagree",
+                    "Neutral",
+                    "Agree",
+                    "Strongly agree",
+                ]
+                .iter()
+                .map(|label| label.to_string())
+                .collect()
+            });
+        *i += 1;
+
+        QuestionType::Likert {
+            scale: labels
+                .into_iter()
+                .map(|label| Choice::new(label, false))
+                .collect(),
+        }
+    }
+
+    /// Parse a `formula:` line followed by `var name: min .. max` declarations.
+    ///
+    /// Variables take as many decimal places as their range literals use, and
+    /// optional `decimals:` / `sets:` lines control answer rounding and how many
+    /// answer sets are pre-generated.
+    fn parse_formula(&self, lines: &[&str], i: &mut usize, seed: &str) -> Result<QuestionType> {
+        let start = *i;
+        let captures = self
+            .formula_pattern
+            .captures(lines[*i])
+            .ok_or_else(|| QtiError::ParseError("Invalid formula format".to_string()))?;
+        let formula = captures[1].trim().to_string();
+        let tolerance = captures
+            .get(2)
+            .and_then(|m| m.as_str().parse::<f64>().ok())
+            .unwrap_or(0.0);
+        *i += 1;
+
+        let mut variables = Vec::new();
+        let mut decimals = 2;
+        let mut set_count = 10;
+
+        while *i < lines.len() {
+            let line = lines[*i].trim();
+
+            if let Some(captures) = self.formula_var_pattern.captures(line) {
+                let min_literal = &captures[2];
+                let max_literal = &captures[3];
+                let min = min_literal.parse::<f64>().map_err(|_| {
+                    QtiError::ParseError(format!("Invalid variable minimum '{}'", min_literal))
+                })?;
+                let max = max_literal.parse::<f64>().map_err(|_| {
+                    QtiError::ParseError(format!("Invalid variable maximum '{}'", max_literal))
+                })?;
+                if min > max {
+                    return Err(QtiError::InvalidFormat {
+                        line: *i + 1,
+                        message: format!("Variable '{}' has min greater than max", &captures[1]),
+                    });
+                }
+                let places = count_decimals(min_literal).max(count_decimals(max_literal));
+                variables.push(FormulaVariable::new(&captures[1], min, max, places));
+                *i += 1;
+            } else if let Some(captures) = self.formula_option_pattern.captures(line) {
+                let value = captures[2]
+                    .parse::<u32>()
+                    .map_err(|_| QtiError::InvalidFormat {
+                        line: *i + 1,
+                        message: format!("Invalid {} value", &captures[1]),
+                    })?;
+                match &captures[1] {
+                    "decimals" => decimals = value,
+                    _ => set_count = value.max(1) as usize,
+                }
+                *i += 1;
+            } else if line.is_empty() {
+                *i += 1;
+            } else {
+                break;
+            }
+        }
+
+        if variables.is_empty() {
+            return Err(QtiError::InvalidFormat {
+                line: start + 1,
+                message: "Formula question must declare at least one variable".to_string(),
+            });
+        }
+
+        let answer_sets =
+            formula::generate_answer_sets(&formula, &variables, decimals, set_count, seed)?;
+
+        Ok(QuestionType::Formula {
+            formula,
+            variables,
+            answer_sets,
+            tolerance,
+            decimals,
+        })
+    }
+
+    /// Replace each inline `{a|*b|c}` menu with a `[dropdownN]` placeholder
+    fn parse_multiple_dropdowns(&self, text: &str, line: usize) -> Result<(String, QuestionType)> {
+        let mut dropdowns = Vec::new();
+        let mut error = None;
+        let math = math::math_ranges(text);
+
+        let text = self
+            .dropdown_pattern
+            .replace_all(text, |captures: &regex::Captures| {
+                if overlaps(&math, &captures.get(0).unwrap()) {
+                    return captures[0].to_string();
+                }
+                let name = format!("dropdown{}", dropdowns.len() + 1);
+                let choices: Vec<Choice> = captures[1]
+                    .split('|')
+                    .map(|option| {
+                        let option = option.trim();
+                        match option.strip_prefix('*') {
+                            Some(correct) => Choice::new(correct.trim(), true),
+                            None => Choice::new(option, false),
+                        }
+                    })
+                    .collect();
+
+                let correct_count = choices.iter().filter(|c| c.correct).count();
+                if correct_count != 1 && error.is_none() {
+                    error = Some(QtiError::InvalidFormat {
+                        line,
+                        message: format!(
+                            "Dropdown '{}' must have exactly 1 correct answer, found {}",
+                            &captures[0], correct_count
+                        ),
+                    });
+                }
+
+                let placeholder = format!("[{}]", name);
+                dropdowns.push(Dropdown::new(name, choices));
+                placeholder
+            })
+            .to_string();
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        Ok((text, QuestionType::MultipleDropdowns { dropdowns }))
+    }
+
     fn parse_feedback_and_solution(&self, lines: &[&str], i: &mut usize, question: &mut Question) {
         let mut feedback = Feedback {
             correct: None,
@@ -296,6 +1083,16 @@ impl Parser {
             } else if line.starts_with("Solution:") || line.starts_with("solution:") {
                 question.solution = Some(line[9..].trim().to_string());
                 *i += 1;
+            } else if line.starts_with("tags:") || line.starts_with("Tags:") {
+                question.tags = split_tags(Some(&line[5..]));
+                *i += 1;
+            } else if line.starts_with("id:") || line.starts_with("ID:") {
+                question.id = line[3..].trim().to_string();
+                *i += 1;
+            } else if line.trim().eq_ignore_ascii_case("ungraded") {
+                question.graded = false;
+                question.points = 0.0;
+                *i += 1;
             } else if self.question_pattern.is_match(line) {
                 break;
             } else if line.trim().is_empty() {
@@ -312,6 +1109,28 @@ impl Parser {
     }
 }
 
+/// Title of a group declared without one
+const DEFAULT_GROUP_TITLE: &str = "Question Group";
+
+/// Comma-separated tag list
+fn split_tags(tags: Option<&str>) -> Vec<String> {
+    tags.map(|tags| {
+        tags.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn count_decimals(literal: &str) -> u32 {
+    literal
+        .split_once('.')
+        .map(|(_, fraction)| fraction.len() as u32)
+        .unwrap_or(0)
+}
+
 #[derive(Debug)]
 enum QuestionTypeHint {
     MultipleChoice,
@@ -320,6 +1139,8 @@ enum QuestionTypeHint {
     Numerical,
     Essay,
     FileUpload,
+    Formula,
+    Likert,
 }
 
 impl Default for Parser {
@@ -328,9 +1149,17 @@ impl Default for Parser {
     }
 }
 
+/// Whether a match falls (partly) inside one of `ranges`
+fn overlaps(ranges: &[Range<usize>], found: &regex::Match) -> bool {
+    ranges
+        .iter()
+        .any(|range| found.start() < range.end && range.start < found.end())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::AnswerType;
 
     #[test]
     fn test_parse_multiple_choice() {
@@ -396,4 +1225,448 @@ d) Madrid
             panic!("Expected ShortAnswer question type");
         }
     }
+
+    #[test]
+    fn test_parse_pattern_answer() {
+        let parser = Parser::new();
+        let assessment = parser
+            .parse("1. What color is the sky?\n* /colou?r of the sky|blue/\n* sky blue")
+            .unwrap();
+
+        if let QuestionType::ShortAnswer { answers, .. } = &assessment.questions[0].question_type {
+            assert!(matches!(answers[0].answer_type, AnswerType::Pattern(_)));
+            assert!(answers[0].matches("Blue", false));
+            assert!(answers[0].matches("colour of the sky", false));
+            assert!(!answers[0].matches("green", false));
+            assert!(matches!(answers[1].answer_type, AnswerType::Single(_)));
+        } else {
+            panic!("Expected ShortAnswer question type");
+        }
+
+        assert!(parser.parse("1. Broken?\n* /colo(u?r/").is_err());
+    }
+
+    #[test]
+    fn test_parse_multiple_dropdowns() {
+        let input = r#"
+1. Roses are {*red|blue|green}, violets are {red|*blue}.
+feedback: A classic.
+"#;
+
+        let parser = Parser::new();
+        let assessment = parser.parse(input).unwrap();
+
+        let q1 = &assessment.questions[0];
+        assert_eq!(q1.text, "Roses are [dropdown1], violets are [dropdown2].");
+        assert!(q1.feedback.is_some());
+        if let QuestionType::MultipleDropdowns { dropdowns } = &q1.question_type {
+            assert_eq!(dropdowns.len(), 2);
+            assert_eq!(dropdowns[0].name, "dropdown1");
+            assert_eq!(dropdowns[0].choices.len(), 3);
+            assert!(dropdowns[0].choices[0].correct);
+            assert_eq!(dropdowns[1].choices[1].text, "blue");
+            assert!(dropdowns[1].choices[1].correct);
+        } else {
+            panic!("Expected MultipleDropdowns question type");
+        }
+
+        let error = parser.parse("1. Pick {a|b}.").unwrap_err().to_string();
+        assert!(error.starts_with("Invalid question format at line 1:"));
+
+        // Unstarred braces followed by choices are part of the stem
+        let bash = parser
+            .parse(
+                "1. In bash, what does {a|b} expand to?
+a) a|b
+*b) a b",
+            )
+            .unwrap();
+        assert_eq!(
+            bash.questions[0].text,
+            "In bash, what does {a|b} expand to?"
+        );
+        assert!(matches!(
+            bash.questions[0].question_type,
+            QuestionType::MultipleChoice { .. }
+        ));
+
+        let math = parser
+            .parse("1. How many elements are in $\\{x | x^2 = 4\\}$?\n= 2")
+            .unwrap();
+        assert!(matches!(
+            math.questions[0].question_type,
+            QuestionType::Numerical { .. }
+        ));
+
+        let mixed = parser
+            .parse("1. The set $\\{x|x>0\\}$ is {*open|closed}, and so is \\(\\{y|y<0\\}\\).")
+            .unwrap();
+        let question = &mixed.questions[0];
+        assert_eq!(
+            question.text,
+            "The set $\\{x|x>0\\}$ is [dropdown1], and so is \\(\\{y|y<0\\}\\)."
+        );
+        let QuestionType::MultipleDropdowns { dropdowns } = &question.question_type else {
+            panic!("Expected MultipleDropdowns question type");
+        };
+        assert_eq!(dropdowns.len(), 1);
+        assert_eq!(dropdowns[0].choices[0].text, "open");
+    }
+
+    #[test]
+    fn test_parse_formula() {
+        let input = r#"
+1. A car travels [d] km in [t] h. What is its speed in km/h?
+formula: d / t ± 0.5
+var d: 10 .. 200
+var t: 1.0 .. 5.0
+sets: 4
+feedback: Speed is distance over time.
+"#;
+
+        let parser = Parser::new();
+        let assessment = parser.parse(input).unwrap();
+
+        let q1 = &assessment.questions[0];
+        assert!(q1.feedback.is_some());
+        if let QuestionType::Formula {
+            formula,
+            variables,
+            answer_sets,
+            tolerance,
+            decimals,
+        } = &q1.question_type
+        {
+            assert_eq!(formula, "d / t");
+            assert_eq!(*tolerance, 0.5);
+            assert_eq!(*decimals, 2);
+            assert_eq!(variables.len(), 2);
+            assert_eq!(variables[0].decimals, 0);
+            assert_eq!(variables[1].decimals, 1);
+            assert_eq!(answer_sets.len(), 4);
+        } else {
+            panic!("Expected Formula question type");
+        }
+
+        assert!(parser
+            .parse("1. Broken\nformula: d / x\nvar d: 1 .. 2")
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_passage_and_text_only() {
+        let input = r#"
+title: Reading
+
+text: Read the passage carefully.
+
+passage: The Crash
+Stock prices fell sharply
+in October 1929.
+
+Banks failed soon after.
+
+1. When did prices fall?
+*a) 1929
+b) 1935
+
+2. What failed soon after?
+* Banks
+
+end passage
+
+3. Unrelated question?
+* yes
+"#;
+
+        let parser = Parser::new();
+        let assessment = parser.parse(input).unwrap();
+
+        assert_eq!(assessment.questions.len(), 4);
+        assert!(matches!(
+            assessment.questions[0].question_type,
+            QuestionType::TextOnly
+        ));
+        assert_eq!(assessment.questions[0].points, 0.0);
+        assert_eq!(assessment.questions[0].text, "Read the passage carefully.");
+
+        assert_eq!(assessment.passages.len(), 1);
+        let passage = &assessment.passages[0];
+        assert_eq!(passage.title, "The Crash");
+        assert_eq!(
+            passage.text,
+            "<p>Stock prices fell sharply in October 1929.</p><p>Banks failed soon after.</p>"
+        );
+
+        assert_eq!(assessment.questions[1].passage.as_ref(), Some(&passage.id));
+        assert_eq!(assessment.questions[2].passage.as_ref(), Some(&passage.id));
+        assert!(assessment.questions[3].passage.is_none());
+    }
+
+    #[test]
+    fn test_parse_explicit_ids() {
+        let input = r#"
+1. What is 2 + 2?
+*a) 4
+b) 5
+id: addition-basics
+
+2. Explain.
+___
+"#;
+
+        let parser = Parser::new();
+        let assessment = parser.parse(input).unwrap();
+        assert_eq!(assessment.questions[0].id, "addition-basics");
+        assert!(ids::is_generated(&assessment.questions[1].id, "question"));
+
+        assert!(parser
+            .parse(
+                "1. A
+___
+id: 9lives"
+            )
+            .is_err());
+        assert!(parser
+            .parse(
+                "1. A
+___
+id: same
+2. B
+___
+id: same"
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_question_groups() {
+        let input = r#"
+group: Chapter 3, review, pick 2, 5 points
+1. First?
+* one
+
+2. Second?
+* two
+
+3. Third?
+* three
+end group
+
+4. Outside?
+* four
+"#;
+
+        let parser = Parser::new();
+        let assessment = parser.parse(input).unwrap();
+
+        assert_eq!(assessment.groups.len(), 1);
+        let group = &assessment.groups[0];
+        assert_eq!(group.title, "Chapter 3, review");
+        assert_eq!(group.pick, 2);
+        assert_eq!(group.points_per_question, 5.0);
+        assert_eq!(assessment.questions[2].group, Some(group.id.clone()));
+        assert_eq!(assessment.questions[2].points, 5.0);
+        assert_eq!(assessment.questions[3].group, None);
+
+        assert!(parser.parse("group: G, pick 3\n1. A?\n* a").is_err());
+        assert!(parser.parse("group: G\ntext: no\n1. A?\n* a").is_err());
+
+        // Without a points value, the group takes the scored questions' points
+        let ungraded_first = parser
+            .parse("group: G\n1. A?\n* a\nungraded\n\n2. B?\n* b\nend group")
+            .unwrap();
+        assert_eq!(ungraded_first.groups[0].points_per_question, 1.0);
+
+        let mut bank = parser.parse_bank("1. A?\n* a\n\n2. B?\n* b").unwrap();
+        bank.questions[1].points = 2.0;
+        let parser = Parser::new().with_bank("mixed", bank);
+        assert!(parser.parse("group: G\nfrom: mixed\nend group").is_err());
+        let priced = parser
+            .parse("group: G, 2 points\nfrom: mixed\nend group")
+            .unwrap();
+        assert_eq!(priced.groups[0].points_per_question, 2.0);
+    }
+
+    #[test]
+    fn test_parse_question_banks() {
+        let bank_input = r#"
+title: Algebra Bank
+1. Solve x + 1 = 2.
+= 1
+tags: linear
+
+2. Solve 2x = 6.
+= 3
+tags: linear, easy
+
+3. Factor x^2 - 1.
+* (x-1)(x+1)
+tags: quadratic
+"#;
+
+        let parser = Parser::new();
+        let bank = parser.parse_bank(bank_input).unwrap();
+        assert_eq!(bank.title, "Algebra Bank");
+        assert_eq!(bank.questions[1].tags, vec!["linear", "easy"]);
+        assert_eq!(
+            bank.questions[0].id,
+            parser.parse_bank(bank_input).unwrap().questions[0].id
+        );
+
+        let parser = parser.with_bank("algebra.txt", bank);
+        let input = r#"
+pull: 1 from algebra.txt tagged quadratic
+
+group: , pick 1, 3 points
+from: algebra.txt tagged linear
+end group
+"#;
+        let assessment = parser.parse(input).unwrap();
+        assert_eq!(assessment.questions.len(), 3);
+        assert_eq!(assessment.questions[0].text, "Factor x^2 - 1.");
+        assert_eq!(assessment.questions[0].group, None);
+
+        let group = &assessment.groups[0];
+        assert_eq!(group.title, "Algebra Bank (linear)");
+        assert_eq!(group.pick, 1);
+        assert!(group.bank.is_some());
+        assert_eq!(assessment.questions[2].points, 3.0);
+
+        assert!(parser
+            .parse("group: G\nfrom: missing.txt\nend group")
+            .is_err());
+        assert!(parser.parse("from: algebra.txt").is_err());
+        assert!(parser
+            .parse("group: G\nfrom: algebra.txt\n1. Extra?\n* x\nend group")
+            .is_err());
+
+        // Same-titled banks in different files are different banks, and a
+        // file keeps its id however its path is written
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("week1")).unwrap();
+        std::fs::write(dir.path().join("bank.txt"), bank_input).unwrap();
+        std::fs::write(
+            dir.path().join("week1/bank.txt"),
+            format!("{}\n4. Week one?\n* yes", bank_input),
+        )
+        .unwrap();
+        let top = parser.parse_bank_file(dir.path().join("bank.txt")).unwrap();
+        let nested = parser
+            .parse_bank_file(dir.path().join("week1/bank.txt"))
+            .unwrap();
+        let via_parent = parser
+            .parse_bank_file(dir.path().join("week1/../bank.txt"))
+            .unwrap();
+        assert_eq!(top.title, nested.title);
+        assert_ne!(top.id, nested.id);
+        assert_ne!(top.questions[0].id, nested.questions[0].id);
+        assert_eq!(top.id, via_parent.id);
+
+        let quiz = "group: G\nfrom: bank.txt\nend group";
+        std::fs::write(dir.path().join("quiz.txt"), quiz).unwrap();
+        std::fs::write(
+            dir.path().join("week1/quiz.txt"),
+            "group: G\nfrom: ../bank.txt\nend group",
+        )
+        .unwrap();
+        let bank_of = |path: std::path::PathBuf| {
+            let assessment = parser.parse_file(path).unwrap();
+            assessment.groups[0].bank.clone().unwrap().id
+        };
+        assert_eq!(bank_of(dir.path().join("quiz.txt")), top.id);
+        assert_eq!(bank_of(dir.path().join("week1/quiz.txt")), top.id);
+    }
+
+    #[test]
+    fn test_parse_quiz_settings() {
+        let input = r#"
+title: Settings
+id: settings-quiz
+attempts: 3
+time_limit: 45
+show_correct_answers: no
+one_question_at_a_time: yes
+access_code: open sesame
+
+1. Question?
+* answer
+"#;
+
+        let parser = Parser::new();
+        let assessment = parser.parse(input).unwrap();
+        let metadata = &assessment.metadata;
+        assert_eq!(metadata.allowed_attempts, Some(Attempts::Limited(3)));
+        assert_eq!(assessment.time_limit, Some(45));
+        assert!(!metadata.show_correct_answers);
+        assert!(metadata.one_question_at_a_time);
+        assert!(!metadata.cant_go_back);
+        assert_eq!(metadata.access_code.as_deref(), Some("open sesame"));
+        assert_eq!(assessment.identifier, "settings-quiz");
+        assert!(parser.parse("id: week 1\n1. Q?\n* a").is_err());
+
+        let unlimited = parser.parse("attempts: unlimited\n1. Q?\n* a").unwrap();
+        assert_eq!(
+            unlimited.metadata.allowed_attempts,
+            Some(Attempts::Unlimited)
+        );
+        let unset = parser.parse("1. Q?\n* a").unwrap();
+        assert_eq!(unset.metadata.allowed_attempts, None);
+        assert!(parser.parse("attempts: 0\n1. Q?\n* a").is_err());
+        assert!(parser.parse("cant_go_back: maybe\n1. Q?\n* a").is_err());
+    }
+
+    #[test]
+    fn test_parse_survey() {
+        let input = r#"
+title: Course Evaluation
+
+1. The pacing of the course was appropriate.
+likert
+
+2. How would you rate the textbook?
+likert: Poor | Fair | Good
+
+3. Which topic did you enjoy most?
+a) Algebra
+b) Geometry
+ungraded
+
+4. Any other comments?
+___
+ungraded
+"#;
+
+        let parser = Parser::new();
+        let assessment = parser.parse(input).unwrap();
+
+        assert_eq!(assessment.metadata.quiz_type, QuizType::Assignment);
+        assert!(assessment.questions.iter().all(|q| !q.graded));
+        assert!(assessment.questions.iter().all(|q| q.points == 0.0));
+
+        if let QuestionType::Likert { scale } = &assessment.questions[0].question_type {
+            assert_eq!(scale.len(), 5);
+        } else {
+            panic!("Expected Likert question type");
+        }
+        if let QuestionType::Likert { scale } = &assessment.questions[1].question_type {
+            assert_eq!(scale[2].text, "Good");
+        } else {
+            panic!("Expected Likert question type");
+        }
+
+        let survey = parser
+            .parse("quiz_type: survey\n\n1. Favorite color?\n* blue")
+            .unwrap();
+        assert_eq!(survey.metadata.quiz_type, QuizType::Survey);
+        assert!(!survey.questions[0].graded);
+        assert_eq!(survey.points_possible(), 0.0);
+
+        let graded = parser
+            .parse("quiz_type: graded_survey\n\n1. Favorite color?\n* blue")
+            .unwrap();
+        assert!(!graded.questions[0].graded);
+        assert!(graded.questions[0].participation);
+        assert_eq!(graded.points_possible(), 1.0);
+    }
 }
@@ -0,0 +1,240 @@
+use crate::error::{QtiError, Result};
+use regex::RegexBuilder;
+use regex_syntax::hir::{Class, Dot, Hir, HirKind, Look};
+
+/// Largest number of literal strings a pattern is expanded into for QTI output
+const MAX_EXPANSION: usize = 32;
+
+/// How a short-answer regex pattern can be expressed with QTI 1.2 conditions
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternCondition {
+    /// Matches exactly one of these strings (`varequal` each)
+    Exact(Vec<String>),
+    /// Matches any response containing one of these strings (`varsubstring` each)
+    Substring(Vec<String>),
+    /// Cannot be represented; the item scores it 0 and grading leaves it to a person
+    Unsupported,
+}
+
+/// Check that `pattern` compiles
+pub fn validate(pattern: &str) -> Result<()> {
+    RegexBuilder::new(pattern)
+        .build()
+        .map(|_| ())
+        .map_err(|e| QtiError::ParseError(format!("Invalid answer pattern '{}': {}", pattern, e)))
+}
+
+/// Whether the whole (trimmed) response matches `pattern`
+pub fn is_match(pattern: &str, response: &str, case_sensitive: bool) -> bool {
+    RegexBuilder::new(&format!("^(?:{})$", pattern))
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map(|re| re.is_match(response.trim()))
+        .unwrap_or(false)
+}
+
+/// Translate a pattern into the closest QTI condition.
+///
+/// Patterns with a small finite language (`colou?r`, `(gray|grey)`) become a
+/// list of exact answers; `.*word.*` becomes a substring match. Anything
+/// the conditions would accept more loosely than the pattern is unsupported.
+pub fn to_condition(pattern: &str, case_sensitive: bool) -> PatternCondition {
+    let hir = match regex_syntax::ParserBuilder::new()
+        .case_insensitive(false)
+        .build()
+        .parse(pattern)
+    {
+        Ok(hir) => hir,
+        Err(_) => return PatternCondition::Unsupported,
+    };
+    let hir = strip_anchors(hir);
+
+    if let Some(strings) = expand(&hir) {
+        return PatternCondition::Exact(dedupe(strings, case_sensitive));
+    }
+
+    if let HirKind::Concat(subs) = hir.kind() {
+        let is_wildcard = |h: &Hir| {
+            matches!(h.kind(), HirKind::Repetition(r)
+                if r.min == 0
+                    && r.max.is_none()
+                    && (*r.sub == Hir::dot(Dot::AnyChar) || *r.sub == Hir::dot(Dot::AnyCharExceptLF)))
+        };
+        if subs.len() >= 3 && is_wildcard(&subs[0]) && is_wildcard(&subs[subs.len() - 1]) {
+            let middle = Hir::concat(subs[1..subs.len() - 1].to_vec());
+            if let Some(strings) = expand(&middle) {
+                if strings.iter().all(|s| !s.is_empty()) {
+                    return PatternCondition::Substring(dedupe(strings, case_sensitive));
+                }
+            }
+        }
+    }
+
+    PatternCondition::Unsupported
+}
+
+/// Drop a leading `^` and trailing `$`; the whole response is matched anyway
+fn strip_anchors(hir: Hir) -> Hir {
+    let is_anchor = |h: &Hir, look: Look| matches!(h.kind(), HirKind::Look(l) if *l == look);
+    match hir.kind() {
+        HirKind::Look(Look::Start | Look::End) => Hir::empty(),
+        HirKind::Concat(subs) => {
+            let mut subs = subs.as_slice();
+            if subs.first().is_some_and(|h| is_anchor(h, Look::Start)) {
+                subs = &subs[1..];
+            }
+            if subs.last().is_some_and(|h| is_anchor(h, Look::End)) {
+                subs = &subs[..subs.len() - 1];
+            }
+            Hir::concat(subs.to_vec())
+        }
+        _ => hir,
+    }
+}
+
+fn dedupe(strings: Vec<String>, case_sensitive: bool) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for s in strings {
+        let seen = unique.iter().any(|u| {
+            if case_sensitive {
+                *u == s
+            } else {
+                u.eq_ignore_ascii_case(&s)
+            }
+        });
+        if !seen {
+            unique.push(s);
+        }
+    }
+    unique
+}
+
+/// Enumerate every string the expression matches, if there are few enough
+fn expand(hir: &Hir) -> Option<Vec<String>> {
+    let strings = match hir.kind() {
+        HirKind::Empty => vec![String::new()],
+        // Word boundaries and inner anchors have no QTI equivalent
+        HirKind::Look(_) => return None,
+        HirKind::Literal(literal) => vec![String::from_utf8(literal.0.to_vec()).ok()?],
+        HirKind::Class(Class::Unicode(class)) => {
+            let mut chars = Vec::new();
+            for range in class.iter() {
+                for c in range.start()..=range.end() {
+                    chars.push(c.to_string());
+                    if chars.len() > MAX_EXPANSION {
+                        return None;
+                    }
+                }
+            }
+            chars
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            let mut chars = Vec::new();
+            for range in class.iter() {
+                for b in range.start()..=range.end() {
+                    chars.push((b as char).to_string());
+                    if chars.len() > MAX_EXPANSION {
+                        return None;
+                    }
+                }
+            }
+            chars
+        }
+        HirKind::Repetition(repetition) => {
+            let max = repetition.max?;
+            let sub = expand(&repetition.sub)?;
+            let mut strings = Vec::new();
+            for count in repetition.min..=max {
+                let mut repeated = vec![String::new()];
+                for _ in 0..count {
+                    repeated = product(&repeated, &sub)?;
+                }
+                strings.extend(repeated);
+                if strings.len() > MAX_EXPANSION {
+                    return None;
+                }
+            }
+            strings
+        }
+        HirKind::Capture(capture) => expand(&capture.sub)?,
+        HirKind::Concat(subs) => {
+            let mut strings = vec![String::new()];
+            for sub in subs {
+                strings = product(&strings, &expand(sub)?)?;
+            }
+            strings
+        }
+        HirKind::Alternation(subs) => {
+            let mut strings = Vec::new();
+            for sub in subs {
+                strings.extend(expand(sub)?);
+                if strings.len() > MAX_EXPANSION {
+                    return None;
+                }
+            }
+            strings
+        }
+    };
+
+    if strings.len() > MAX_EXPANSION {
+        None
+    } else {
+        Some(strings)
+    }
+}
+
+fn product(prefixes: &[String], suffixes: &[String]) -> Option<Vec<String>> {
+    if prefixes.len() * suffixes.len() > MAX_EXPANSION {
+        return None;
+    }
+    Some(
+        prefixes
+            .iter()
+            .flat_map(|p| suffixes.iter().map(move |s| format!("{}{}", p, s)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_conditions() {
+        assert_eq!(
+            to_condition("colou?r", false),
+            PatternCondition::Exact(vec!["color".to_string(), "colour".to_string()])
+        );
+        assert_eq!(
+            to_condition("^(gr[ae]y|GRAY)$", false),
+            PatternCondition::Exact(vec!["gray".to_string(), "grey".to_string()])
+        );
+        assert_eq!(
+            to_condition(".*photo.*", false),
+            PatternCondition::Substring(vec!["photo".to_string()])
+        );
+        assert_eq!(to_condition("[a-z]+", false), PatternCondition::Unsupported);
+        assert_eq!(
+            to_condition("^.*photo.*$", false),
+            PatternCondition::Substring(vec!["photo".to_string()])
+        );
+
+        // "cat 42!" and "Concatenate" contain "cat" but do not match these
+        assert_eq!(
+            to_condition("[a-z]*cat[a-z]*", false),
+            PatternCondition::Unsupported
+        );
+        assert!(!is_match("[a-z]*cat[a-z]*", "cat 42!", true));
+        assert!(!is_match("[a-z]*cat[a-z]*", "Concatenate", true));
+        assert_eq!(
+            to_condition(r"\bcat\b", false),
+            PatternCondition::Unsupported
+        );
+        assert_eq!(to_condition("a$|b", false), PatternCondition::Unsupported);
+
+        assert!(is_match("colou?r", " Colour ", false));
+        assert!(!is_match("colou?r", "Colour", true));
+        assert!(!is_match("colou?r", "watercolor", false));
+        assert!(validate("(unclosed").is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::error::{QtiError, Result};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use xmltree::{Element, XMLNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// One problem found in a document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    pub severity: Severity,
+    /// Stable rule code, e.g. `child-order`
+    pub code: &'static str,
+    /// File inside a package the violation was found in
+    pub file: Option<String>,
+    /// Element path, e.g. `questestinterop/assessment/section/item[@ident='q3']/presentation`
+    pub path: String,
+    /// 1-based position of the element's start tag, when validated from source
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}] ", self.severity, self.code)?;
+        let mut location: Vec<String> = self.file.iter().cloned().collect();
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            location.extend([line.to_string(), column.to_string()]);
+        }
+        let location = [location.join(":"), self.path.clone()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if location.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", location, self.message)
+        }
+    }
+}
+
+/// Every violation found while validating a document
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub violations: Vec<Violation>,
+    /// Whether warnings fail validation too
+    pub warnings_as_errors: bool,
+}
+
+impl ValidationReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Violation> {
+        self.violations
+            .iter()
+            .filter(|v| v.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Violation> {
+        self.violations
+            .iter()
+            .filter(|v| v.severity == Severity::Warning)
+    }
+
+    /// Whether the document passes, counting warnings when `warnings_as_errors` is set
+    pub fn is_valid(&self) -> bool {
+        self.violations.iter().all(|v| !self.fails(v))
+    }
+
+    /// The first failing violation as an error, for callers that only need pass/fail
+    pub fn into_result(self) -> Result<()> {
+        match self.violations.iter().find(|v| self.fails(v)) {
+            Some(violation) => Err(QtiError::ValidationError(match &violation.file {
+                Some(file) => format!("{} (at {}:{})", violation.message, file, violation.path),
+                None => format!("{} (at {})", violation.message, violation.path),
+            })),
+            None => Ok(()),
+        }
+    }
+
+    fn fails(&self, violation: &Violation) -> bool {
+        violation.severity == Severity::Error || self.warnings_as_errors
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        source: &SourceMap,
+        element: &Element,
+        path: &str,
+        severity: Severity,
+        code: &'static str,
+        message: impl Into<String>,
+    ) {
+        let position = source.position(element);
+        self.violations.push(Violation {
+            severity,
+            code,
+            file: None,
+            path: path.to_string(),
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+            message: message.into(),
+        });
+    }
+
+    pub(crate) fn error(
+        &mut self,
+        source: &SourceMap,
+        element: &Element,
+        path: &str,
+        code: &'static str,
+        message: impl Into<String>,
+    ) {
+        self.push(source, element, path, Severity::Error, code, message);
+    }
+
+    /// Record a problem with a package file as a whole rather than one of its elements
+    pub(crate) fn file_violation(
+        &mut self,
+        file: &str,
+        severity: Severity,
+        code: &'static str,
+        message: impl Into<String>,
+    ) {
+        self.violations.push(Violation {
+            severity,
+            code,
+            file: Some(file.to_string()),
+            path: String::new(),
+            line: None,
+            column: None,
+            message: message.into(),
+        });
+    }
+
+    /// Take over the violations of a report made for one package file
+    pub(crate) fn merge(&mut self, file: &str, report: ValidationReport) {
+        self.violations
+            .extend(report.violations.into_iter().map(|violation| Violation {
+                file: Some(file.to_string()),
+                ..violation
+            }));
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for violation in &self.violations {
+            writeln!(f, "{}", violation)?;
+        }
+        write!(
+            f,
+            "{} error(s), {} warning(s)",
+            self.errors().count(),
+            self.warnings().count()
+        )
+    }
+}
+
+/// Source line and column of each element of a parsed tree
+#[derive(Debug, Default)]
+pub(crate) struct SourceMap {
+    // Keyed by element address; the tree must not change while the map is in use
+    positions: HashMap<usize, (usize, usize)>,
+}
+
+impl SourceMap {
+    pub(crate) fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Pair each element of `root`, parsed from `xml`, with its start tag's position
+    pub(crate) fn new(root: &Element, xml: &str) -> Self {
+        let mut elements = Vec::new();
+        preorder(root, &mut elements);
+
+        let mut starts = Vec::new();
+        let mut reader = Reader::from_str(xml);
+        loop {
+            let offset = reader.buffer_position() as usize;
+            match reader.read_event() {
+                Ok(Event::Start(_)) | Ok(Event::Empty(_)) => starts.push(offset),
+                Ok(Event::Eof) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+
+        let positions = elements
+            .into_iter()
+            .zip(starts)
+            .map(|(element, offset)| (element, line_column(xml, offset)))
+            .collect();
+        Self { positions }
+    }
+
+    fn position(&self, element: &Element) -> Option<(usize, usize)> {
+        self.positions
+            .get(&(element as *const Element as usize))
+            .copied()
+    }
+}
+
+fn preorder(element: &Element, elements: &mut Vec<usize>) {
+    elements.push(element as *const Element as usize);
+    for child in &element.children {
+        if let XMLNode::Element(child) = child {
+            preorder(child, elements);
+        }
+    }
+}
+
+fn line_column(xml: &str, offset: usize) -> (usize, usize) {
+    let before = &xml[..offset.min(xml.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |rest| rest.chars().count())
+        + 1;
+    (line, column)
+}
+
+/// Path segment for a child: `item[@ident='q3']`, `resource[@identifier='r1']`,
+/// `material[2]`, or just the name
+pub(crate) fn child_path(parent_path: &str, parent: &Element, child: &Element) -> String {
+    let key = ["ident", "identifier"]
+        .into_iter()
+        .find_map(|key| child.attributes.get(key).map(|value| (key, value)));
+    let segment = match key {
+        Some((key, value)) => format!("{}[@{}='{}']", child.name, key, value),
+        None => {
+            let siblings: Vec<&Element> = parent
+                .children
+                .iter()
+                .filter_map(|node| match node {
+                    XMLNode::Element(e) if e.name == child.name => Some(e),
+                    _ => None,
+                })
+                .collect();
+            match siblings.iter().position(|e| std::ptr::eq(*e, child)) {
+                Some(index) if siblings.len() > 1 => format!("{}[{}]", child.name, index + 1),
+                _ => child.name.clone(),
+            }
+        }
+    };
+    format!("{}/{}", parent_path, segment)
+}
+
+/// Call `f` on `element` and each descendant with its path
+pub(crate) fn visit(element: &Element, path: &str, f: &mut impl FnMut(&Element, &str)) {
+    f(element, path);
+    for child in &element.children {
+        if let XMLNode::Element(child) = child {
+            visit(child, &child_path(path, element, child), f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_positions_and_paths() {
+        let xml = "<?xml version=\"1.0\"?>\n<a>\n  <b ident=\"x\"/>\n  <c><d/></c>\n  <c/>\n</a>";
+        let root = Element::parse(xml.as_bytes()).unwrap();
+        let source = SourceMap::new(&root, xml);
+        let c = root.get_child("c").unwrap();
+        let d = c.get_child("d").unwrap();
+
+        let mut report = ValidationReport::new();
+        report.error(
+            &source,
+            d,
+            &child_path(&child_path("a", &root, c), c, d),
+            "x",
+            "bad",
+        );
+        report.push(&source, &root, "a", Severity::Warning, "y", "meh");
+        assert_eq!(report.violations[0].path, "a/c[1]/d");
+        assert_eq!(
+            (report.violations[0].line, report.violations[0].column),
+            (Some(4), Some(6))
+        );
+        assert_eq!(
+            child_path("a", &root, root.get_child("b").unwrap()),
+            "a/b[@ident='x']"
+        );
+
+        assert!(!report.is_valid());
+        report.violations.remove(0);
+        assert!(report.is_valid());
+        report.warnings_as_errors = true;
+        assert!(report.into_result().is_err());
+    }
+}
@@ -1,13 +1,28 @@
 use crate::error::{QtiError, Result};
+use crate::report::{child_path, Severity, SourceMap, ValidationReport};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
+use std::sync::OnceLock;
 use xmltree::{Element, XMLNode};
 
+/// The QTI 1.2.1 ASI schema shipped in `spec/`
+const QTI_1_2_XSD: &str = include_str!("../spec/1.2/ims_qtiasiv1p2p1.xsd");
+
+/// The QTI-Lite 1.2.1 schema shipped in `spec/`
+const QTI_LITE_XSD: &str = include_str!("../spec/1.2/ims_qtilitev1p2p1.xsd");
+
+/// The QTI 1.2.1 ASI DTD shipped in `spec/`
+const QTI_1_2_DTD: &str = include_str!("../spec/1.2/ims_qtiasiv1p2p1.dtd");
+
 /// XSD-based schema definitions for QTI 1.2
+#[derive(Debug, Clone)]
 pub struct QtiSchema {
     /// Root element name
     pub root: String,
     /// Valid elements and their attributes
     pub elements: Vec<ElementDef>,
+    /// Reject attributes the element does not declare
+    pub strict_attributes: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -15,7 +30,10 @@ pub struct ElementDef {
     pub name: String,
     pub attributes: Vec<AttributeDef>,
     pub required: bool,
+    /// Every element allowed as a child, in declaration order
     pub children: Vec<String>,
+    /// Order and cardinality of the children; `None` only checks membership in `children`
+    pub content: Option<Particle>,
 }
 
 #[derive(Debug, Clone)]
@@ -25,406 +43,236 @@ pub struct AttributeDef {
     pub values: Option<Vec<String>>, // Enumerated values if applicable
 }
 
+/// One term of a content model with its `minOccurs`/`maxOccurs`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particle {
+    pub term: Term,
+    pub min_occurs: usize,
+    /// `None` for `unbounded`
+    pub max_occurs: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Element(String),
+    Sequence(Vec<Particle>),
+    Choice(Vec<Particle>),
+    /// `xs:any`: any element, validated only if the schema knows it
+    Any,
+}
+
+impl Particle {
+    /// Content model of an element with no element children
+    pub fn empty() -> Self {
+        Self {
+            term: Term::Sequence(Vec::new()),
+            min_occurs: 1,
+            max_occurs: Some(1),
+        }
+    }
+
+    fn allows_any(&self) -> bool {
+        match &self.term {
+            Term::Any => true,
+            Term::Element(_) => false,
+            Term::Sequence(items) | Term::Choice(items) => items.iter().any(Particle::allows_any),
+        }
+    }
+
+    fn element_names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match &self.term {
+            Term::Element(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Term::Sequence(items) | Term::Choice(items) => {
+                for item in items {
+                    item.element_names(names);
+                }
+            }
+            Term::Any => {}
+        }
+    }
+
+    /// Positions in `names` where a match of this particle starting at `start` can end
+    fn match_from(&self, names: &[&str], start: usize) -> BTreeSet<usize> {
+        let mut ends = BTreeSet::new();
+        if self.min_occurs == 0 {
+            ends.insert(start);
+        }
+
+        let mut frontier = BTreeSet::from([start]);
+        let mut count = 0;
+        while self.max_occurs.is_none_or(|max| count < max) {
+            count += 1;
+            let next: BTreeSet<usize> = frontier
+                .iter()
+                .flat_map(|&position| self.term.match_from(names, position))
+                .collect();
+            if count < self.min_occurs {
+                frontier = next;
+                continue;
+            }
+
+            // Stop once another repetition reaches nothing new
+            let new: BTreeSet<usize> = next.difference(&ends).copied().collect();
+            if new.is_empty() {
+                break;
+            }
+            ends.extend(&new);
+            frontier = new;
+        }
+        ends
+    }
+}
+
+impl Term {
+    fn match_from(&self, names: &[&str], start: usize) -> BTreeSet<usize> {
+        match self {
+            Term::Element(name) => match names.get(start) {
+                Some(found) if found == name => BTreeSet::from([start + 1]),
+                _ => BTreeSet::new(),
+            },
+            Term::Any if start < names.len() => BTreeSet::from([start + 1]),
+            Term::Any => BTreeSet::new(),
+            Term::Sequence(items) => {
+                items
+                    .iter()
+                    .fold(BTreeSet::from([start]), |positions, item| {
+                        positions
+                            .iter()
+                            .flat_map(|&position| item.match_from(names, position))
+                            .collect()
+                    })
+            }
+            Term::Choice(items) => items
+                .iter()
+                .flat_map(|item| item.match_from(names, start))
+                .collect(),
+        }
+    }
+}
+
 impl QtiSchema {
     pub fn from_xsd_file(path: impl AsRef<Path>) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
         Self::from_xsd_string(&content)
     }
 
-    /// Currently returns hardcoded schema; full XSD parsing not yet implemented
-    pub fn from_xsd_string(_xsd: &str) -> Result<Self> {
-        Ok(Self::qti_1_2_schema())
+    /// Build a schema from an XSD, supporting the subset the QTI schemas use:
+    /// global and local element declarations, named and anonymous complex
+    /// types with nested sequences and choices, `minOccurs`/`maxOccurs`,
+    /// `xs:any`, simple content, and attributes with enumerated values.
+    /// The root is the first global element.
+    pub fn from_xsd_string(xsd: &str) -> Result<Self> {
+        let document = Element::parse(xsd.as_bytes())
+            .map_err(|e| QtiError::XmlError(format!("Failed to parse XSD: {}", e)))?;
+        if document.name != "schema" {
+            return Err(QtiError::ValidationError(format!(
+                "Expected an xs:schema document, found '{}'",
+                document.name
+            )));
+        }
+        XsdReader::new(&document).read()
     }
 
-    /// QTI 1.2 schema definitions based on ims_qtiasiv1p2p1.xsd specification
+    /// QTI 1.2 schema definitions from ims_qtiasiv1p2p1.xsd
     pub fn qti_1_2_schema() -> Self {
-        Self {
-            root: "questestinterop".to_string(),
-            elements: vec![
-                ElementDef {
-                    name: "questestinterop".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "xmlns".to_string(),
-                            required: true,
-                            values: Some(vec![
-                                "http://www.imsglobal.org/xsd/ims_qtiasiv1p2".to_string()
-                            ]),
-                        },
-                        AttributeDef {
-                            name: "xmlns:xsi".to_string(),
-                            required: true,
-                            values: Some(vec![
-                                "http://www.w3.org/2001/XMLSchema-instance".to_string()
-                            ]),
-                        },
-                    ],
-                    required: true,
-                    children: vec!["assessment".to_string()],
-                },
-                ElementDef {
-                    name: "qtimetadata".to_string(),
-                    attributes: vec![],
-                    required: false,
-                    children: vec!["qtimetadatafield".to_string()],
-                },
-                ElementDef {
-                    name: "itemmetadata".to_string(),
-                    attributes: vec![],
-                    required: false,
-                    children: vec!["qtimetadatafield".to_string()],
-                },
-                ElementDef {
-                    name: "qtimetadatafield".to_string(),
-                    attributes: vec![],
-                    required: false,
-                    children: vec!["fieldlabel".to_string(), "fieldentry".to_string()],
-                },
-                ElementDef {
-                    name: "fieldlabel".to_string(),
-                    attributes: vec![],
-                    required: false,
-                    children: vec![],
-                },
-                ElementDef {
-                    name: "fieldentry".to_string(),
-                    attributes: vec![],
-                    required: false,
-                    children: vec![],
-                },
-                ElementDef {
-                    name: "assessment".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "ident".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "title".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                    ],
-                    required: true,
-                    children: vec!["qtimetadata".to_string(), "section".to_string()],
-                },
-                ElementDef {
-                    name: "section".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "ident".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "title".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                    ],
-                    required: true,
-                    children: vec!["item".to_string()],
-                },
-                ElementDef {
-                    name: "item".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "ident".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "title".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "maxattempts".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                    ],
-                    required: true,
-                    children: vec![
-                        "itemmetadata".to_string(),
-                        "presentation".to_string(),
-                        "resprocessing".to_string(),
-                        "itemfeedback".to_string(),
-                    ],
-                },
-                ElementDef {
-                    name: "presentation".to_string(),
-                    attributes: vec![],
-                    required: true,
-                    children: vec![
-                        "material".to_string(),
-                        "response_lid".to_string(),
-                        "response_str".to_string(),
-                        "response_num".to_string(),
-                        "response_grp".to_string(),
-                    ],
-                },
-                ElementDef {
-                    name: "response_lid".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "ident".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "rcardinality".to_string(),
-                            required: false,
-                            values: Some(vec!["Single".to_string(), "Multiple".to_string()]),
-                        },
-                        AttributeDef {
-                            name: "rtiming".to_string(),
-                            required: false,
-                            values: Some(vec!["No".to_string(), "Yes".to_string()]),
-                        },
-                    ],
-                    required: false,
-                    children: vec!["render_choice".to_string()],
-                },
-                ElementDef {
-                    name: "render_choice".to_string(),
-                    attributes: vec![AttributeDef {
-                        name: "shuffle".to_string(),
-                        required: false,
-                        values: Some(vec!["Yes".to_string(), "No".to_string()]),
-                    }],
-                    required: false,
-                    children: vec!["response_label".to_string()],
-                },
-                ElementDef {
-                    name: "response_label".to_string(),
-                    attributes: vec![AttributeDef {
-                        name: "ident".to_string(),
-                        required: true,
-                        values: None,
-                    }],
-                    required: false,
-                    children: vec!["material".to_string()],
-                },
-                ElementDef {
-                    name: "material".to_string(),
-                    attributes: vec![],
-                    required: false,
-                    children: vec!["mattext".to_string(), "matimage".to_string()],
-                },
-                ElementDef {
-                    name: "mattext".to_string(),
-                    attributes: vec![AttributeDef {
-                        name: "texttype".to_string(),
-                        required: false,
-                        values: Some(vec!["text/plain".to_string(), "text/html".to_string()]),
-                    }],
-                    required: false,
-                    children: vec![],
-                },
-                ElementDef {
-                    name: "resprocessing".to_string(),
-                    attributes: vec![],
-                    required: true,
-                    children: vec!["outcomes".to_string(), "respcondition".to_string()],
-                },
-                ElementDef {
-                    name: "outcomes".to_string(),
-                    attributes: vec![],
-                    required: false,
-                    children: vec!["decvar".to_string()],
-                },
-                ElementDef {
-                    name: "decvar".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "varname".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "vartype".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "minvalue".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "maxvalue".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                    ],
-                    required: false,
-                    children: vec![],
-                },
-                ElementDef {
-                    name: "respcondition".to_string(),
-                    attributes: vec![AttributeDef {
-                        name: "continue".to_string(),
-                        required: false,
-                        values: Some(vec!["Yes".to_string(), "No".to_string()]),
-                    }],
-                    required: false,
-                    children: vec![
-                        "conditionvar".to_string(),
-                        "setvar".to_string(),
-                        "displayfeedback".to_string(),
-                    ],
-                },
-                ElementDef {
-                    name: "conditionvar".to_string(),
-                    attributes: vec![],
-                    required: false,
-                    children: vec![
-                        "varequal".to_string(),
-                        "and".to_string(),
-                        "or".to_string(),
-                        "not".to_string(),
-                    ],
-                },
-                ElementDef {
-                    name: "varequal".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "respident".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "case".to_string(),
-                            required: false,
-                            values: Some(vec!["Yes".to_string(), "No".to_string()]),
-                        },
-                    ],
-                    required: false,
-                    children: vec![],
-                },
-                ElementDef {
-                    name: "setvar".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "varname".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "action".to_string(),
-                            required: false,
-                            values: Some(vec![
-                                "Set".to_string(),
-                                "Add".to_string(),
-                                "Subtract".to_string(),
-                            ]),
-                        },
-                    ],
-                    required: false,
-                    children: vec![],
-                },
-                ElementDef {
-                    name: "displayfeedback".to_string(),
-                    attributes: vec![AttributeDef {
-                        name: "linkrefid".to_string(),
-                        required: true,
-                        values: None,
-                    }],
-                    required: false,
-                    children: vec![],
-                },
-                ElementDef {
-                    name: "itemfeedback".to_string(),
-                    attributes: vec![AttributeDef {
-                        name: "ident".to_string(),
-                        required: true,
-                        values: None,
-                    }],
-                    required: false,
-                    children: vec!["material".to_string()],
-                },
-                ElementDef {
-                    name: "response_str".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "ident".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "rcardinality".to_string(),
-                            required: false,
-                            values: Some(vec!["Single".to_string(), "Multiple".to_string()]),
-                        },
-                    ],
-                    required: false,
-                    children: vec!["render_fib".to_string()],
-                },
-                ElementDef {
-                    name: "response_num".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "ident".to_string(),
-                            required: true,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "rcardinality".to_string(),
-                            required: false,
-                            values: Some(vec!["Single".to_string(), "Multiple".to_string()]),
-                        },
-                        AttributeDef {
-                            name: "numtype".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                    ],
-                    required: false,
-                    children: vec!["render_fib".to_string()],
-                },
-                ElementDef {
-                    name: "render_fib".to_string(),
-                    attributes: vec![
-                        AttributeDef {
-                            name: "rows".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                        AttributeDef {
-                            name: "columns".to_string(),
-                            required: false,
-                            values: None,
-                        },
-                    ],
-                    required: false,
-                    children: vec![],
-                },
-            ],
-        }
+        Self::bundled_qti_1_2().clone()
+    }
+
+    /// The bundled QTI 1.2 schema, parsed on first use
+    pub(crate) fn bundled_qti_1_2() -> &'static Self {
+        static SCHEMA: OnceLock<QtiSchema> = OnceLock::new();
+        SCHEMA.get_or_init(|| {
+            Self::from_xsd_string(QTI_1_2_XSD).expect("bundled QTI 1.2 XSD should parse")
+        })
+    }
+
+    /// QTI-Lite definitions from ims_qtilitev1p2p1.xsd: single-choice items only
+    pub fn qti_lite_schema() -> Self {
+        Self::bundled_qti_lite().clone()
+    }
+
+    /// The bundled QTI-Lite schema, parsed on first use
+    pub(crate) fn bundled_qti_lite() -> &'static Self {
+        static SCHEMA: OnceLock<QtiSchema> = OnceLock::new();
+        SCHEMA.get_or_init(|| {
+            Self::from_xsd_string(QTI_LITE_XSD).expect("bundled QTI-Lite XSD should parse")
+        })
+    }
+
+    pub fn from_dtd_file(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_dtd_string(&content)
+    }
+
+    /// Build a schema from a DTD's element and attribute list declarations
+    pub fn from_dtd_string(dtd: &str) -> Result<Self> {
+        crate::dtd::parse(dtd)
+    }
+
+    /// QTI 1.2 schema definitions from ims_qtiasiv1p2p1.dtd
+    pub fn qti_1_2_dtd() -> Self {
+        Self::bundled_qti_1_2_dtd().clone()
+    }
+
+    /// The bundled QTI 1.2 DTD, parsed on first use
+    pub(crate) fn bundled_qti_1_2_dtd() -> &'static Self {
+        static SCHEMA: OnceLock<QtiSchema> = OnceLock::new();
+        SCHEMA.get_or_init(|| {
+            Self::from_dtd_string(QTI_1_2_DTD).expect("bundled QTI 1.2 DTD should parse")
+        })
+    }
+
+    pub fn element(&self, name: &str) -> Option<&ElementDef> {
+        self.elements.iter().find(|e| e.name == name)
     }
 
     pub fn validate(&self, element: &Element) -> Result<()> {
-        self.validate_element(element, &self.root)?;
-        Ok(())
+        let mut report = ValidationReport::new();
+        self.check(element, &SourceMap::empty(), &mut report);
+        report.into_result()
     }
 
-    fn validate_element(&self, element: &Element, expected_name: &str) -> Result<()> {
-        if element.name != expected_name {
-            return Err(QtiError::ValidationError(format!(
-                "Expected element '{}', found '{}'",
-                expected_name, element.name
-            )));
+    /// Record every violation in `element` and its descendants
+    pub(crate) fn check(
+        &self,
+        element: &Element,
+        source: &SourceMap,
+        report: &mut ValidationReport,
+    ) {
+        if element.name != self.root {
+            report.error(
+                source,
+                element,
+                &element.name,
+                "wrong-root",
+                format!("Expected element '{}', found '{}'", self.root, element.name),
+            );
+            return;
         }
+        self.check_element(element, &element.name, source, report);
+    }
 
-        let element_def = self
-            .elements
-            .iter()
-            .find(|e| e.name == element.name)
-            .ok_or_else(|| {
-                QtiError::ValidationError(format!("Unknown element: {}", element.name))
-            })?;
+    fn check_element(
+        &self,
+        element: &Element,
+        path: &str,
+        source: &SourceMap,
+        report: &mut ValidationReport,
+    ) {
+        let Some(element_def) = self.element(&element.name) else {
+            report.error(
+                source,
+                element,
+                path,
+                "unknown-element",
+                format!("Unknown element: {}", element.name),
+            );
+            return;
+        };
 
         for attr_def in &element_def.attributes {
             // Namespace attributes are handled differently by XML parsers
@@ -432,39 +280,403 @@ impl QtiSchema {
                 continue;
             }
 
-            if attr_def.required && !element.attributes.contains_key(&attr_def.name) {
-                return Err(QtiError::ValidationError(format!(
-                    "Missing required attribute '{}' on element '{}'",
-                    attr_def.name, element.name
-                )));
+            // Parsed attributes are keyed by local name, so `xml:lang` is `lang`
+            let key = local_name(&attr_def.name);
+            if attr_def.required && !element.attributes.contains_key(key) {
+                report.error(
+                    source,
+                    element,
+                    path,
+                    "missing-attribute",
+                    format!(
+                        "Missing required attribute '{}' on element '{}'",
+                        attr_def.name, element.name
+                    ),
+                );
             }
 
-            if let Some(value) = element.attributes.get(&attr_def.name) {
-                if let Some(ref valid_values) = attr_def.values {
-                    if !valid_values.contains(value) {
-                        return Err(QtiError::ValidationError(
-                            format!("Invalid value '{}' for attribute '{}' on element '{}'. Valid values: {:?}",
-                                value, attr_def.name, element.name, valid_values)
-                        ));
-                    }
+            if let (Some(value), Some(valid_values)) =
+                (element.attributes.get(key), &attr_def.values)
+            {
+                if !valid_values.contains(value) {
+                    report.error(
+                        source,
+                        element,
+                        path,
+                        "invalid-attribute-value",
+                        format!(
+                            "Invalid value '{}' for attribute '{}' on element '{}'. Valid values: {:?}",
+                            value, attr_def.name, element.name, valid_values
+                        ),
+                    );
                 }
             }
         }
 
-        for child in &element.children {
-            if let XMLNode::Element(child_element) = child {
-                if !element_def.children.contains(&child_element.name) {
-                    return Err(QtiError::ValidationError(format!(
+        // Undeclared attributes are tolerated by lax schemas, but still worth a warning
+        let severity = if self.strict_attributes {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        for name in element.attributes.keys() {
+            let declared = element_def
+                .attributes
+                .iter()
+                .any(|a| local_name(&a.name) == name);
+            if !declared && !SCHEMA_LOCATION_ATTRIBUTES.contains(&name.as_str()) {
+                report.push(
+                    source,
+                    element,
+                    path,
+                    severity,
+                    "undeclared-attribute",
+                    format!(
+                        "Undeclared attribute '{}' on element '{}'",
+                        name, element.name
+                    ),
+                );
+            }
+        }
+
+        let open = element_def
+            .content
+            .as_ref()
+            .is_some_and(Particle::allows_any);
+        let children: Vec<&Element> = element
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                XMLNode::Element(child_element) => Some(child_element),
+                _ => None,
+            })
+            .collect();
+
+        let mut unexpected = false;
+        for child_element in &children {
+            if !element_def.children.contains(&child_element.name) && !open {
+                unexpected = true;
+                report.error(
+                    source,
+                    child_element,
+                    &child_path(path, element, child_element),
+                    "unexpected-child",
+                    format!(
                         "Unexpected child element '{}' in '{}'",
                         child_element.name, element.name
-                    )));
+                    ),
+                );
+            }
+        }
+
+        // Unexpected children already explain why the content model fails
+        if let (Some(content), false) = (&element_def.content, unexpected) {
+            let names: Vec<&str> = children.iter().map(|c| c.name.as_str()).collect();
+            let ends = content.match_from(&names, 0);
+            if !ends.contains(&names.len()) {
+                match ends.last().filter(|&&end| end < names.len()) {
+                    Some(&end) => report.error(
+                        source,
+                        children[end],
+                        &child_path(path, element, children[end]),
+                        "child-order",
+                        format!(
+                            "Child element '{}' in '{}' is out of order or repeated too often",
+                            names[end], element.name
+                        ),
+                    ),
+                    None => report.error(
+                        source,
+                        element,
+                        path,
+                        "missing-children",
+                        format!(
+                            "Element '{}' is missing required child elements",
+                            element.name
+                        ),
+                    ),
+                }
+            }
+        }
+
+        for child_element in children {
+            // Elements matched by xs:any are only checked when the schema knows them
+            if open && self.element(&child_element.name).is_none() {
+                continue;
+            }
+            if !element_def.children.contains(&child_element.name) && !open {
+                continue;
+            }
+            let child = child_path(path, element, child_element);
+            self.check_element(child_element, &child, source, report);
+        }
+    }
+}
+
+/// `xsi:` attributes that point at a schema rather than describe the element
+const SCHEMA_LOCATION_ATTRIBUTES: [&str; 2] = ["schemaLocation", "noNamespaceSchemaLocation"];
+
+/// The type an element declaration refers to
+enum TypeRef<'a> {
+    Complex(&'a Element),
+    Simple,
+}
+
+/// Turns the declarations of an `xs:schema` document into element definitions
+struct XsdReader<'a> {
+    complex_types: HashMap<&'a str, &'a Element>,
+    simple_types: HashMap<&'a str, &'a Element>,
+    global_elements: Vec<&'a Element>,
+    elements: Vec<ElementDef>,
+    defined: HashSet<String>,
+}
+
+impl<'a> XsdReader<'a> {
+    fn new(document: &'a Element) -> Self {
+        let mut complex_types = HashMap::new();
+        let mut simple_types = HashMap::new();
+        let mut global_elements = Vec::new();
+        for child in xs_children(document) {
+            let name = child.attributes.get("name").map(String::as_str);
+            match (child.name.as_str(), name) {
+                ("complexType", Some(name)) => {
+                    complex_types.insert(name, child);
                 }
-                self.validate_element(child_element, &child_element.name)?;
+                ("simpleType", Some(name)) => {
+                    simple_types.insert(name, child);
+                }
+                ("element", Some(_)) => global_elements.push(child),
+                _ => {}
             }
         }
+        Self {
+            complex_types,
+            simple_types,
+            global_elements,
+            elements: Vec::new(),
+            defined: HashSet::new(),
+        }
+    }
+
+    fn read(mut self) -> Result<QtiSchema> {
+        let root = self
+            .global_elements
+            .first()
+            .copied()
+            .ok_or_else(|| QtiError::ValidationError("XSD declares no elements".to_string()))?;
+        let root_name = attribute(root, "name")?.to_string();
+
+        for declaration in self.global_elements.clone() {
+            self.define(declaration, false)?;
+        }
+        if let Some(root_def) = self.elements.iter_mut().find(|e| e.name == root_name) {
+            root_def.required = true;
+        }
+
+        Ok(QtiSchema {
+            root: root_name,
+            elements: self.elements,
+            strict_attributes: false,
+        })
+    }
+
+    fn global_element(&self, name: &str) -> Result<&'a Element> {
+        self.global_elements
+            .iter()
+            .copied()
+            .find(|e| e.attributes.get("name").is_some_and(|n| n == name))
+            .ok_or_else(|| {
+                QtiError::ValidationError(format!("XSD references undeclared element '{}'", name))
+            })
+    }
+
+    fn type_of(&self, declaration: &'a Element) -> TypeRef<'a> {
+        if let Some(inline) = xs_children(declaration).find(|c| c.name == "complexType") {
+            return TypeRef::Complex(inline);
+        }
+        match declaration
+            .attributes
+            .get("type")
+            .and_then(|type_name| self.complex_types.get(local_name(type_name)))
+        {
+            Some(complex) => TypeRef::Complex(complex),
+            None => TypeRef::Simple,
+        }
+    }
+
+    /// Add the definition of a declared element, once per element name
+    fn define(&mut self, declaration: &'a Element, required: bool) -> Result<()> {
+        let name = attribute(declaration, "name")?.to_string();
+        if !self.defined.insert(name.clone()) {
+            return Ok(());
+        }
 
+        let (attributes, content) = match self.type_of(declaration) {
+            TypeRef::Complex(complex) => self.complex_type(complex)?,
+            TypeRef::Simple => (Vec::new(), Particle::empty()),
+        };
+        let mut children = Vec::new();
+        content.element_names(&mut children);
+        let children = children.into_iter().map(String::from).collect();
+
+        self.elements.push(ElementDef {
+            name,
+            attributes,
+            required,
+            children,
+            content: Some(content),
+        });
         Ok(())
     }
+
+    fn complex_type(&mut self, complex: &'a Element) -> Result<(Vec<AttributeDef>, Particle)> {
+        let mut attributes = Vec::new();
+        let mut content = Particle::empty();
+        for child in xs_children(complex) {
+            match child.name.as_str() {
+                "sequence" | "choice" => content = self.particle(child)?,
+                "attribute" => attributes.push(self.attribute_def(child)?),
+                "simpleContent" => {
+                    // Text with attributes: `xs:extension` carries the attributes
+                    for extension in xs_children(child) {
+                        for attr in xs_children(extension).filter(|c| c.name == "attribute") {
+                            attributes.push(self.attribute_def(attr)?);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok((attributes, content))
+    }
+
+    fn particle(&mut self, node: &'a Element) -> Result<Particle> {
+        let min_occurs = occurs(node, "minOccurs")?.unwrap_or(1);
+        let max_occurs = match node.attributes.get("maxOccurs").map(String::as_str) {
+            Some("unbounded") => None,
+            _ => Some(occurs(node, "maxOccurs")?.unwrap_or(1)),
+        };
+
+        let term = match node.name.as_str() {
+            "sequence" | "choice" => {
+                let mut items = Vec::new();
+                for child in xs_children(node) {
+                    if matches!(
+                        child.name.as_str(),
+                        "element" | "sequence" | "choice" | "any"
+                    ) {
+                        items.push(self.particle(child)?);
+                    }
+                }
+                if node.name == "sequence" {
+                    Term::Sequence(items)
+                } else {
+                    Term::Choice(items)
+                }
+            }
+            "any" => Term::Any,
+            "element" => {
+                let declaration = match node.attributes.get("ref") {
+                    Some(reference) => self.global_element(local_name(reference))?,
+                    None => node,
+                };
+                self.define(declaration, min_occurs > 0)?;
+                Term::Element(attribute(declaration, "name")?.to_string())
+            }
+            other => {
+                return Err(QtiError::ValidationError(format!(
+                    "Unsupported XSD particle 'xs:{}'",
+                    other
+                )))
+            }
+        };
+
+        Ok(Particle {
+            term,
+            min_occurs,
+            max_occurs,
+        })
+    }
+
+    fn attribute_def(&self, node: &Element) -> Result<AttributeDef> {
+        let name = match (node.attributes.get("name"), node.attributes.get("ref")) {
+            (Some(name), _) | (None, Some(name)) => name.clone(),
+            (None, None) => {
+                return Err(QtiError::ValidationError(
+                    "XSD attribute without a name or ref".to_string(),
+                ))
+            }
+        };
+
+        let simple_type = xs_children(node)
+            .find(|c| c.name == "simpleType")
+            .or_else(|| {
+                node.attributes
+                    .get("type")
+                    .and_then(|t| self.simple_types.get(local_name(t)).copied())
+            });
+
+        Ok(AttributeDef {
+            name,
+            required: node.attributes.get("use").is_some_and(|u| u == "required"),
+            values: match node.attributes.get("fixed") {
+                Some(fixed) => Some(vec![fixed.clone()]),
+                None => simple_type.and_then(|t| self.enumeration(t)),
+            },
+        })
+    }
+
+    /// Enumerated values of a simple type, following restrictions of named types
+    fn enumeration(&self, simple_type: &Element) -> Option<Vec<String>> {
+        let restriction = xs_children(simple_type).find(|c| c.name == "restriction")?;
+        let values: Vec<String> = xs_children(restriction)
+            .filter(|c| c.name == "enumeration")
+            .filter_map(|c| c.attributes.get("value").cloned())
+            .collect();
+        if !values.is_empty() {
+            return Some(values);
+        }
+        let base = self
+            .simple_types
+            .get(local_name(restriction.attributes.get("base")?))?;
+        self.enumeration(base)
+    }
+}
+
+fn xs_children(element: &Element) -> impl Iterator<Item = &Element> {
+    element.children.iter().filter_map(|node| match node {
+        XMLNode::Element(child) => Some(child),
+        _ => None,
+    })
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn attribute<'e>(element: &'e Element, name: &str) -> Result<&'e str> {
+    element
+        .attributes
+        .get(name)
+        .map(String::as_str)
+        .ok_or_else(|| {
+            QtiError::ValidationError(format!(
+                "XSD xs:{} is missing its '{}' attribute",
+                element.name, name
+            ))
+        })
+}
+
+fn occurs(element: &Element, name: &str) -> Result<Option<usize>> {
+    element
+        .attributes
+        .get(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| QtiError::ValidationError(format!("Invalid XSD {} '{}'", name, value)))
+        })
+        .transpose()
 }
 
 #[cfg(test)]
@@ -475,15 +687,70 @@ mod tests {
     fn test_qti_schema_creation() {
         let schema = QtiSchema::qti_1_2_schema();
         assert_eq!(schema.root, "questestinterop");
-        assert!(!schema.elements.is_empty());
+        assert!(schema.elements.len() > 100);
 
         let assessment = schema
-            .elements
-            .iter()
-            .find(|e| e.name == "assessment")
+            .element("assessment")
             .expect("assessment element should be defined");
 
         assert!(assessment.attributes.iter().any(|a| a.name == "ident"));
         assert!(assessment.attributes.iter().any(|a| a.name == "title"));
+
+        let render_choice = schema.element("render_choice").unwrap();
+        let shuffle = render_choice
+            .attributes
+            .iter()
+            .find(|a| a.name == "shuffle")
+            .unwrap();
+        assert_eq!(
+            shuffle.values,
+            Some(vec!["Yes".to_string(), "No".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_xsd_ordering_and_cardinality() {
+        let schema = QtiSchema::qti_1_2_schema();
+        let parse = |xml: &str| Element::parse(xml.as_bytes()).unwrap();
+        let wrap = |item: &str| {
+            format!(
+                "<questestinterop><assessment ident=\"a\"><section ident=\"s\">{}</section></assessment></questestinterop>",
+                item
+            )
+        };
+        let metadata = "<itemmetadata><qtimetadata><qtimetadatafield><fieldlabel>a</fieldlabel><fieldentry>b</fieldentry></qtimetadatafield></qtimetadata></itemmetadata>";
+        let presentation = "<presentation><material><mattext>Q</mattext></material></presentation>";
+
+        let valid = wrap(&format!(
+            "<item ident=\"i\">{}{}</item>",
+            metadata, presentation
+        ));
+        schema.validate(&parse(&valid)).expect("Should be valid");
+
+        let reordered = wrap(&format!(
+            "<item ident=\"i\">{}{}</item>",
+            presentation, metadata
+        ));
+        let error = schema.validate(&parse(&reordered)).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("'itemmetadata' in 'item' is out of order"));
+
+        let twice = wrap(&format!(
+            "<item ident=\"i\">{}{}</item>",
+            presentation, presentation
+        ));
+        assert!(schema.validate(&parse(&twice)).is_err());
+
+        let no_fields = wrap(
+            "<item ident=\"i\"><itemmetadata><qtimetadata></qtimetadata></itemmetadata></item>",
+        );
+        let error = schema.validate(&parse(&no_fields)).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("'qtimetadata' is missing required child"));
+
+        let no_section = "<questestinterop><assessment ident=\"a\"></assessment></questestinterop>";
+        assert!(schema.validate(&parse(no_section)).is_err());
     }
 }
@@ -0,0 +1,607 @@
+use crate::builder::{points_possible, true_false_choices};
+use crate::error::{QtiError, Result};
+use crate::types::{AnswerType, Choice, Question, QuestionType};
+use std::collections::HashMap;
+use std::fmt;
+use xmltree::{Element, XMLNode};
+
+/// A learner's answer to an item: the values given for each response ident
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Response {
+    values: HashMap<String, Vec<String>>,
+}
+
+impl Response {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value for `respident`; call repeatedly for multiple-selection responses
+    pub fn with(mut self, respident: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values
+            .entry(respident.into())
+            .or_default()
+            .push(value.into());
+        self
+    }
+
+    fn values(&self, respident: &str) -> &[String] {
+        self.values.get(respident).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The outcome of an item's response processing
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemScore {
+    pub score: f64,
+    /// `linkrefid`s of the feedback the response triggered, in order
+    pub feedback: Vec<String>,
+}
+
+/// The `item` with the given ident anywhere in a QTI document
+pub fn find_item<'a>(document: &'a Element, ident: &str) -> Option<&'a Element> {
+    if document.name == "item"
+        && document.attributes.get("ident").map(String::as_str) == Some(ident)
+    {
+        return Some(document);
+    }
+    document.children.iter().find_map(|node| match node {
+        XMLNode::Element(child) => find_item(child, ident),
+        _ => None,
+    })
+}
+
+/// Run an item's `resprocessing` on a response, as an LMS would, returning the
+/// `SCORE` outcome and the feedback shown
+pub fn score_item(item: &Element, response: &Response) -> Result<ItemScore> {
+    let Some(resprocessing) = item.get_child("resprocessing") else {
+        return Ok(ItemScore {
+            score: 0.0,
+            feedback: Vec::new(),
+        });
+    };
+
+    let mut outcomes = HashMap::new();
+    let mut bounds = HashMap::new();
+    for decvar in resprocessing
+        .get_child("outcomes")
+        .into_iter()
+        .flat_map(|outcomes| child_elements(outcomes, "decvar"))
+    {
+        let name = varname(decvar);
+        let default = number_attribute(decvar, "defaultval")?.unwrap_or(0.0);
+        outcomes.insert(name.clone(), default);
+        bounds.insert(
+            name,
+            (
+                number_attribute(decvar, "minvalue")?,
+                number_attribute(decvar, "maxvalue")?,
+            ),
+        );
+    }
+
+    let mut feedback = Vec::new();
+    for condition in child_elements(resprocessing, "respcondition") {
+        let matched = match condition.get_child("conditionvar") {
+            Some(conditionvar) => all(conditionvar, response)?,
+            None => false,
+        };
+        if !matched {
+            continue;
+        }
+
+        for action in element_children(condition) {
+            match action.name.as_str() {
+                "setvar" => apply_setvar(action, &mut outcomes)?,
+                "displayfeedback" => {
+                    if let Some(link) = action.attributes.get("linkrefid") {
+                        feedback.push(link.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // QTI stops at the first matching condition unless it says to continue
+        if condition.attributes.get("continue").map(String::as_str) != Some("Yes") {
+            break;
+        }
+    }
+
+    let mut score = outcomes.get("SCORE").copied().unwrap_or(0.0);
+    if let Some((min, max)) = bounds.get("SCORE") {
+        if let Some(max) = max {
+            score = score.min(*max);
+        }
+        if let Some(min) = min {
+            score = score.max(*min);
+        }
+    }
+    Ok(ItemScore { score, feedback })
+}
+
+/// A question whose response processing disagrees with its answer key
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnswerKeyProblem {
+    pub question_id: String,
+    pub message: String,
+}
+
+impl fmt::Display for AnswerKeyProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "question {}: {}", self.question_id, self.message)
+    }
+}
+
+// Points are written as f32, so sums of partial credit are only this close
+const SCORE_TOLERANCE: f64 = 1e-4;
+
+/// Score the correct answer of `question` and each distractor against `item`, the
+/// question as built, reporting correct answers short of full points and
+/// distractors that earn any
+pub fn check_answer_key(question: &Question, item: &Element) -> Result<Vec<AnswerKeyProblem>> {
+    let points = points_possible(question) as f64;
+    let respident = format!("response_{}", question.id);
+    // (description, response, expected score)
+    let mut cases: Vec<(String, Response, f64)> = Vec::new();
+    // (description, response) that must score less than full points
+    let mut partial: Vec<(String, Response)> = Vec::new();
+
+    match &question.question_type {
+        // Surveys have no answer key, and these types are graded by hand
+        _ if !question.graded => {}
+        QuestionType::Essay { .. }
+        | QuestionType::FileUpload { .. }
+        | QuestionType::TextOnly
+        | QuestionType::Likert { .. } => {}
+        QuestionType::MultipleChoice { choices, .. } => {
+            single_choice_cases(choices, &respident, points, &mut cases)
+        }
+        QuestionType::TrueFalse { correct_answer } => single_choice_cases(
+            &true_false_choices(question, *correct_answer),
+            &respident,
+            points,
+            &mut cases,
+        ),
+        QuestionType::MultipleAnswer {
+            choices,
+            partial_credit,
+        } => {
+            let correct: Vec<_> = choices.iter().filter(|c| c.correct).collect();
+            let key = correct.iter().fold(Response::new(), |response, choice| {
+                response.with(&respident, &choice.id)
+            });
+            cases.push(("the correct choices".to_string(), key, points));
+            if correct.len() < choices.len() {
+                let every = choices.iter().fold(Response::new(), |response, choice| {
+                    response.with(&respident, &choice.id)
+                });
+                partial.push(("every choice".to_string(), every));
+            }
+            if !partial_credit && correct.len() > 1 {
+                for choice in &correct {
+                    partial.push((
+                        format!("choice '{}' alone", choice.text.trim()),
+                        Response::new().with(&respident, &choice.id),
+                    ));
+                }
+            }
+            for choice in choices.iter().filter(|c| !c.correct) {
+                cases.push((
+                    format!("choice '{}'", choice.text.trim()),
+                    Response::new().with(&respident, &choice.id),
+                    0.0,
+                ));
+            }
+        }
+        QuestionType::ShortAnswer { answers, .. } => {
+            // Patterns have no single answer text to try
+            for answer in answers
+                .iter()
+                .filter(|a| !matches!(a.answer_type, AnswerType::Pattern(_)))
+            {
+                cases.push((
+                    format!("answer '{}'", answer.text),
+                    Response::new().with(&respident, &answer.text),
+                    points * answer.weight as f64,
+                ));
+            }
+        }
+        QuestionType::Numerical {
+            answer,
+            margin,
+            min,
+            max,
+        } => {
+            let (low, high) = match (min, max) {
+                (Some(min), Some(max)) => (*min, *max),
+                _ => (
+                    answer - margin.unwrap_or(0.0),
+                    answer + margin.unwrap_or(0.0),
+                ),
+            };
+            let correct = if (low..=high).contains(answer) {
+                *answer
+            } else {
+                (low + high) / 2.0
+            };
+            numeric_cases(correct, high + 1.0, &respident, points, &mut cases);
+        }
+        QuestionType::Formula {
+            answer_sets,
+            tolerance,
+            ..
+        } => {
+            if let Some(set) = answer_sets.first() {
+                let outside = set.answer + tolerance + 1.0;
+                numeric_cases(set.answer, outside, &respident, points, &mut cases);
+            }
+        }
+        QuestionType::MultipleDropdowns { dropdowns } => {
+            let mut correct = Response::new();
+            let mut wrong = Response::new();
+            for dropdown in dropdowns {
+                let respident = format!("response_{}", dropdown.name);
+                if let Some(choice) = dropdown.choices.iter().find(|c| c.correct) {
+                    correct = correct.with(&respident, &choice.id);
+                }
+                if let Some(choice) = dropdown.choices.iter().find(|c| !c.correct) {
+                    wrong = wrong.with(&respident, &choice.id);
+                }
+            }
+            cases.push(("the correct choices".to_string(), correct, points));
+            cases.push(("a wrong choice in every blank".to_string(), wrong, 0.0));
+        }
+    }
+
+    if !cases.is_empty() {
+        cases.push(("an empty response".to_string(), Response::new(), 0.0));
+    }
+
+    let mut problems = Vec::new();
+    for (description, response, expected) in cases {
+        let score = score_item(item, &response)?.score;
+        if (score - expected).abs() > SCORE_TOLERANCE {
+            problems.push(AnswerKeyProblem {
+                question_id: question.id.clone(),
+                message: format!(
+                    "{} scores {} but should score {}",
+                    description, score, expected
+                ),
+            });
+        }
+    }
+    for (description, response) in partial {
+        let score = score_item(item, &response)?.score;
+        if score > points - SCORE_TOLERANCE {
+            problems.push(AnswerKeyProblem {
+                question_id: question.id.clone(),
+                message: format!(
+                    "{} scores {} but should score less than {}",
+                    description, score, points
+                ),
+            });
+        }
+    }
+    Ok(problems)
+}
+
+/// Each choice alone: full points when correct, nothing otherwise
+fn single_choice_cases(
+    choices: &[Choice],
+    respident: &str,
+    points: f64,
+    cases: &mut Vec<(String, Response, f64)>,
+) {
+    for choice in choices {
+        cases.push((
+            format!("choice '{}'", choice.text.trim()),
+            Response::new().with(respident, &choice.id),
+            if choice.correct { points } else { 0.0 },
+        ));
+    }
+}
+
+fn numeric_cases(
+    correct: f64,
+    outside: f64,
+    respident: &str,
+    points: f64,
+    cases: &mut Vec<(String, Response, f64)>,
+) {
+    cases.push((
+        format!("answer {}", correct),
+        Response::new().with(respident, correct.to_string()),
+        points,
+    ));
+    cases.push((
+        format!("answer {}", outside),
+        Response::new().with(respident, outside.to_string()),
+        0.0,
+    ));
+}
+
+/// A `conditionvar` (or `and`) holds when every condition in it does
+fn all(element: &Element, response: &Response) -> Result<bool> {
+    for condition in element_children(element) {
+        if !evaluate(condition, response)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn evaluate(condition: &Element, response: &Response) -> Result<bool> {
+    let expected = || condition.get_text().unwrap_or_default().trim().to_string();
+    let values = || {
+        let respident = condition
+            .attributes
+            .get("respident")
+            .map(String::as_str)
+            .unwrap_or_default();
+        response.values(respident)
+    };
+    // QTI compares case-insensitively unless `case="Yes"`
+    let case_sensitive = condition.attributes.get("case").map(String::as_str) == Some("Yes");
+    let fold = |text: &str| {
+        if case_sensitive {
+            text.to_string()
+        } else {
+            text.to_lowercase()
+        }
+    };
+
+    Ok(match condition.name.as_str() {
+        "other" => true,
+        "and" => all(condition, response)?,
+        "or" => {
+            let mut any = false;
+            for child in element_children(condition) {
+                if evaluate(child, response)? {
+                    any = true;
+                    break;
+                }
+            }
+            any
+        }
+        "not" => match element_children(condition).next() {
+            Some(child) => !evaluate(child, response)?,
+            None => false,
+        },
+        "varequal" => {
+            let expected = expected();
+            values()
+                .iter()
+                .any(|value| match (number(value), number(&expected)) {
+                    (Some(value), Some(expected)) => value == expected,
+                    _ => fold(value.trim()) == fold(&expected),
+                })
+        }
+        "unanswered" => values().iter().all(|value| value.trim().is_empty()),
+        "varsubstring" => {
+            let expected = fold(&expected());
+            values().iter().any(|value| fold(value).contains(&expected))
+        }
+        "vargte" | "varlte" | "vargt" | "varlt" => {
+            let bound = number(&expected()).ok_or_else(|| {
+                QtiError::ValidationError(format!(
+                    "'{}' needs a numeric bound, found '{}'",
+                    condition.name,
+                    expected()
+                ))
+            })?;
+            values()
+                .iter()
+                .filter_map(|value| number(value))
+                .any(|value| match condition.name.as_str() {
+                    "vargte" => value >= bound,
+                    "varlte" => value <= bound,
+                    "vargt" => value > bound,
+                    _ => value < bound,
+                })
+        }
+        other => {
+            return Err(QtiError::ValidationError(format!(
+                "Unsupported response condition '{}'",
+                other
+            )))
+        }
+    })
+}
+
+fn apply_setvar(setvar: &Element, outcomes: &mut HashMap<String, f64>) -> Result<()> {
+    let text = setvar.get_text().unwrap_or_default();
+    let value = number(&text).ok_or_else(|| {
+        QtiError::ValidationError(format!("setvar needs a number, found '{}'", text.trim()))
+    })?;
+    let outcome = outcomes.entry(varname(setvar)).or_insert(0.0);
+    match setvar.attributes.get("action").map(String::as_str) {
+        None | Some("Set") => *outcome = value,
+        Some("Add") => *outcome += value,
+        Some("Subtract") => *outcome -= value,
+        Some("Multiply") => *outcome *= value,
+        // Dividing by zero leaves the outcome unchanged rather than making it infinite
+        Some("Divide") => {
+            if value != 0.0 {
+                *outcome /= value
+            }
+        }
+        Some(action) => {
+            return Err(QtiError::ValidationError(format!(
+                "Unsupported setvar action '{}'",
+                action
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn varname(element: &Element) -> String {
+    element
+        .attributes
+        .get("varname")
+        .cloned()
+        .unwrap_or_else(|| "SCORE".to_string())
+}
+
+fn number(text: &str) -> Option<f64> {
+    text.trim().parse().ok()
+}
+
+fn number_attribute(element: &Element, name: &str) -> Result<Option<f64>> {
+    element
+        .attributes
+        .get(name)
+        .map(|value| {
+            number(value).ok_or_else(|| {
+                QtiError::ValidationError(format!(
+                    "Attribute '{}' on '{}' must be a number, found '{}'",
+                    name, element.name, value
+                ))
+            })
+        })
+        .transpose()
+}
+
+fn element_children(element: &Element) -> impl Iterator<Item = &Element> {
+    element.children.iter().filter_map(|node| match node {
+        XMLNode::Element(child) => Some(child),
+        _ => None,
+    })
+}
+
+fn child_elements<'a>(element: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    element_children(element).filter(move |child| child.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::QtiBuilder;
+    use crate::types::{Assessment, QuestionType};
+
+    fn built(source: &str) -> (Assessment, Element) {
+        let assessment = crate::Parser::new().parse(source).unwrap();
+        let document = QtiBuilder::new()
+            .build_questestinterop(&assessment)
+            .unwrap();
+        (assessment, document)
+    }
+
+    #[test]
+    fn test_score_builder_items() {
+        let (assessment, document) = built(
+            "1. Pick one\n*a) x\nb) y\ncorrect: Yes\n\n2. Pick some\n[*] a\n[ ] b\n[*] c\n\n3. Colour?\n* Red\n\n4. Roses are {*red|blue}, violets are {red|*blue}.",
+        );
+        let item = |index: usize| {
+            let question = &assessment.questions[index];
+            let respident = format!("response_{}", question.id);
+            (find_item(&document, &question.id).unwrap(), respident)
+        };
+
+        let QuestionType::MultipleChoice { choices, .. } = &assessment.questions[0].question_type
+        else {
+            panic!("expected multiple choice");
+        };
+        let (mc, respident) = item(0);
+        let right = score_item(mc, &Response::new().with(&respident, &choices[0].id)).unwrap();
+        assert_eq!(right.score, 1.0);
+        assert_eq!(right.feedback, ["correct"]);
+        let wrong = score_item(mc, &Response::new().with(&respident, &choices[1].id)).unwrap();
+        assert_eq!(wrong.score, 0.0);
+
+        let QuestionType::MultipleAnswer { choices, .. } = &assessment.questions[1].question_type
+        else {
+            panic!("expected multiple answer");
+        };
+        let (ma, respident) = item(1);
+        let both = Response::new()
+            .with(&respident, &choices[0].id)
+            .with(&respident, &choices[2].id);
+        assert_eq!(score_item(ma, &both).unwrap().score, 1.0);
+        let every = both.clone().with(&respident, &choices[1].id);
+        assert_eq!(score_item(ma, &every).unwrap().score, 0.5);
+        assert!(check_answer_key(&assessment.questions[1], ma)
+            .unwrap()
+            .is_empty());
+
+        // Summing per-choice credit without penalties lets "select everything" score full
+        let mut lenient = ma.clone();
+        let resprocessing = lenient.get_mut_child("resprocessing").unwrap();
+        for condition in resprocessing
+            .children
+            .iter_mut()
+            .filter_map(|n| n.as_mut_element())
+        {
+            if let Some(setvar) = condition.get_mut_child("setvar") {
+                if setvar.get_text().is_some_and(|text| text.starts_with('-')) {
+                    setvar.children = vec![xmltree::XMLNode::Text("0".to_string())];
+                }
+            }
+        }
+        let problems = check_answer_key(&assessment.questions[1], &lenient).unwrap();
+        assert_eq!(
+            problems[0].message,
+            "every choice scores 1 but should score less than 1"
+        );
+
+        let (sa, respident) = item(2);
+        assert_eq!(
+            score_item(sa, &Response::new().with(&respident, "red"))
+                .unwrap()
+                .score,
+            1.0
+        );
+        assert_eq!(score_item(sa, &Response::new()).unwrap().score, 0.0);
+
+        let QuestionType::MultipleDropdowns { dropdowns } = &assessment.questions[3].question_type
+        else {
+            panic!("expected dropdowns");
+        };
+        let (dd, _) = item(3);
+        let mut half = Response::new();
+        for (dropdown, pick) in dropdowns.iter().zip([0, 0]) {
+            half = half.with(
+                format!("response_{}", dropdown.name),
+                &dropdown.choices[pick].id,
+            );
+        }
+        assert_eq!(score_item(dd, &half).unwrap().score, 0.5);
+
+        let (survey, document) = built("quiz_type: graded_survey\n\n1. Favorite colour?\n* blue");
+        let question = &survey.questions[0];
+        let item = find_item(&document, &question.id).unwrap();
+        let respident = format!("response_{}", question.id);
+        let answered = Response::new().with(&respident, "green");
+        assert_eq!(score_item(item, &answered).unwrap().score, 1.0);
+        assert_eq!(score_item(item, &Response::new()).unwrap().score, 0.0);
+    }
+
+    #[test]
+    fn test_condition_logic() {
+        let item = Element::parse(
+            r#"<item ident="i"><resprocessing>
+                <outcomes><decvar varname="SCORE" maxvalue="3" defaultval="0"/></outcomes>
+                <respcondition continue="Yes">
+                  <conditionvar><or><varequal respident="r">3.14</varequal>
+                    <and><vargte respident="r">3.1</vargte><varlte respident="r">3.2</varlte></and></or>
+                  </conditionvar>
+                  <setvar action="Add">2</setvar><displayfeedback linkrefid="close"/>
+                </respcondition>
+                <respcondition>
+                  <conditionvar><not><varequal respident="r">0</varequal></not></conditionvar>
+                  <setvar action="Add">2</setvar>
+                </respcondition>
+                <respcondition><conditionvar><other/></conditionvar><setvar>9</setvar></respcondition>
+              </resprocessing></item>"#
+                .as_bytes(),
+        )
+        .unwrap();
+
+        let score = |value: &str| score_item(&item, &Response::new().with("r", value)).unwrap();
+        assert_eq!(score("3.15").score, 3.0);
+        assert_eq!(score("3.15").feedback, ["close"]);
+        assert_eq!(score("7").score, 2.0);
+        assert_eq!(score("0").score, 3.0);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::ids;
+use crate::pattern;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -9,6 +11,9 @@ pub struct Assessment {
     pub description: Option<String>,
     pub time_limit: Option<u32>, // in minutes
     pub questions: Vec<Question>,
+    pub passages: Vec<Passage>,
+    pub groups: Vec<QuestionGroup>,
+    pub media: Vec<MediaFile>,
     pub metadata: AssessmentMetadata,
 }
 
@@ -20,12 +25,266 @@ impl Assessment {
             description: None,
             time_limit: None,
             questions: Vec::new(),
+            passages: Vec::new(),
+            groups: Vec::new(),
+            media: Vec::new(),
             metadata: AssessmentMetadata::default(),
         }
     }
+
+    pub fn passage(&self, id: &str) -> Option<&Passage> {
+        self.passages.iter().find(|p| p.id == id)
+    }
+
+    pub fn group(&self, id: &str) -> Option<&QuestionGroup> {
+        self.groups.iter().find(|g| g.id == id)
+    }
+
+    /// Points a learner can earn: ungrouped graded questions plus each group's draw
+    pub fn points_possible(&self) -> f32 {
+        let ungrouped: f32 = self
+            .questions
+            .iter()
+            .filter(|q| q.earns_points() && q.group.is_none())
+            .map(|q| q.points)
+            .sum();
+        let grouped: f32 = self
+            .groups
+            .iter()
+            .filter(|g| {
+                self.questions
+                    .iter()
+                    .any(|q| q.earns_points() && q.group.as_ref() == Some(&g.id))
+            })
+            .map(|g| g.pick as f32 * g.points_per_question)
+            .sum();
+        ungrouped + grouped
+    }
+
+    /// Add a group drawing `pick` (default: all) of the bank's `tags` questions
+    pub fn add_group_from_bank(
+        &mut self,
+        bank: &QuestionBank,
+        tags: &[String],
+        pick: Option<usize>,
+        points_per_question: f32,
+    ) -> &QuestionGroup {
+        let selected = bank.select(tags);
+        let mut group = QuestionGroup::new(
+            bank.selection_title(tags),
+            pick.unwrap_or(selected.len()),
+            points_per_question,
+        );
+        group.bank = Some(bank.bank_ref(tags));
+
+        for question in selected {
+            let mut question = self.copy_from_bank(bank, question);
+            question.group = Some(group.id.clone());
+            if question.earns_points() {
+                question.points = points_per_question;
+            }
+            self.questions.push(question);
+        }
+        self.groups.push(group);
+        self.groups.last().unwrap()
+    }
+
+    /// Copy up to `count` (default: all) of the bank's `tags` questions, in bank order
+    pub fn pull_from_bank(&mut self, bank: &QuestionBank, tags: &[String], count: Option<usize>) {
+        let selected = bank.select(tags);
+        let count = count.unwrap_or(selected.len());
+        for question in selected.into_iter().take(count) {
+            let question = self.copy_from_bank(bank, question);
+            self.questions.push(question);
+        }
+    }
+
+    /// A copy of a bank question for this assessment, with an ident of its own so
+    /// it never clashes with the bank's item or with other copies of it
+    pub fn copy_from_bank(&self, bank: &QuestionBank, question: &Question) -> Question {
+        let name = format!("{}/{}/{}", bank.id, self.identifier, question.id);
+        let mut id = ids::stable_id("question", &name);
+        let mut occurrence = 1;
+        while self.questions.iter().any(|q| q.id == id) {
+            occurrence += 1;
+            id = ids::stable_id("question", &format!("{}#{}", name, occurrence));
+        }
+
+        let mut copy = question.clone();
+        copy.bank_item = Some(question.id.clone());
+        copy.id = id;
+        copy
+    }
+
+    /// Apply `f` to every piece of learner-facing text: question stems,
+    /// choices, passages, feedback and solutions
+    pub fn visit_text_mut(&mut self, mut f: impl FnMut(&mut String)) {
+        for passage in &mut self.passages {
+            f(&mut passage.text);
+        }
+
+        for question in &mut self.questions {
+            f(&mut question.text);
+
+            match &mut question.question_type {
+                QuestionType::MultipleChoice { choices, .. }
+                | QuestionType::MultipleAnswer { choices, .. }
+                | QuestionType::Likert { scale: choices } => {
+                    for choice in choices {
+                        f(&mut choice.text);
+                        if let Some(feedback) = &mut choice.feedback {
+                            f(feedback);
+                        }
+                    }
+                }
+                QuestionType::MultipleDropdowns { dropdowns } => {
+                    for choice in dropdowns.iter_mut().flat_map(|d| d.choices.iter_mut()) {
+                        f(&mut choice.text);
+                    }
+                }
+                _ => {}
+            }
+
+            if let Some(feedback) = &mut question.feedback {
+                for text in [
+                    &mut feedback.correct,
+                    &mut feedback.incorrect,
+                    &mut feedback.general,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    f(text);
+                }
+            }
+
+            if let Some(solution) = &mut question.solution {
+                f(solution);
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// An image or other media file packaged alongside the assessment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaFile {
+    pub filename: String, // Path inside the package, e.g. media/diagram.png
+    pub content: Vec<u8>,
+}
+
+/// Shared stimulus (reading passage, figure, case study) for a run of questions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Passage {
+    pub id: String,
+    pub title: String,
+    pub text: String,
+}
+
+impl Passage {
+    pub fn new(title: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: format!("passage_{}", Uuid::new_v4()),
+            title: title.into(),
+            text: text.into(),
+        }
+    }
+}
+
+/// A pool of questions from which each attempt draws `pick` at random
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionGroup {
+    pub id: String,
+    pub title: String,
+    pub pick: usize,
+    pub points_per_question: f32,
+    pub bank: Option<BankRef>, // Set when the questions come from a question bank
+}
+
+impl QuestionGroup {
+    pub fn new(title: impl Into<String>, pick: usize, points_per_question: f32) -> Self {
+        Self {
+            id: format!("group_{}", Uuid::new_v4()),
+            title: title.into(),
+            pick,
+            points_per_question,
+            bank: None,
+        }
+    }
+}
+
+/// A reusable collection of tagged questions that quizzes draw from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionBank {
+    pub id: String, // Derived from the title so every quiz refers to the same bank
+    pub title: String,
+    pub questions: Vec<Question>,
+}
+
+impl QuestionBank {
+    pub fn new(title: impl Into<String>) -> Self {
+        let title = title.into();
+        Self {
+            id: ids::stable_id("bank", &title),
+            title,
+            questions: Vec::new(),
+        }
+    }
+
+    /// A bank read from the text `source`, so banks sharing a title in
+    /// different files stay apart wherever the files are read from
+    pub fn from_source(title: impl Into<String>, source: &str) -> Self {
+        let title = title.into();
+        Self {
+            id: ids::stable_id("bank", &format!("{}\n{}", title, source)),
+            title,
+            questions: Vec::new(),
+        }
+    }
+
+    /// Questions carrying any of `tags`, or every question when `tags` is empty
+    pub fn select(&self, tags: &[String]) -> Vec<&Question> {
+        self.questions
+            .iter()
+            .filter(|q| {
+                tags.is_empty()
+                    || q.tags
+                        .iter()
+                        .any(|tag| tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            })
+            .collect()
+    }
+
+    /// The exported bank a group draws its `tags` selection from
+    pub fn bank_ref(&self, tags: &[String]) -> BankRef {
+        let mut tags: Vec<String> = tags.iter().map(|t| t.to_lowercase()).collect();
+        tags.sort();
+        tags.dedup();
+        BankRef {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            tags,
+        }
+    }
+
+    /// Title for a group drawing the `tags` selection, e.g. `Algebra (linear)`
+    pub fn selection_title(&self, tags: &[String]) -> String {
+        if tags.is_empty() {
+            self.title.clone()
+        } else {
+            format!("{} ({})", self.title, tags.join(", "))
+        }
+    }
+}
+
+/// Identity of the Canvas question bank a group draws from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BankRef {
+    pub id: String,
+    pub title: String,
+    pub tags: Vec<String>, // Lowercase tag filter; empty when the group draws from the whole bank
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssessmentMetadata {
     pub author: Option<String>,
     pub course: Option<String>,
@@ -33,6 +292,63 @@ pub struct AssessmentMetadata {
     pub shuffle_answers: bool,
     pub show_feedback: bool,
     pub allow_review: bool,
+    pub quiz_type: QuizType,
+    pub allowed_attempts: Option<Attempts>, // None leaves the LMS default
+    pub show_correct_answers: bool,
+    pub one_question_at_a_time: bool,
+    pub cant_go_back: bool, // Only applies with one question at a time
+    pub access_code: Option<String>,
+}
+
+impl Default for AssessmentMetadata {
+    fn default() -> Self {
+        Self {
+            author: None,
+            course: None,
+            shuffle_questions: false,
+            shuffle_answers: false,
+            show_feedback: false,
+            allow_review: false,
+            quiz_type: QuizType::default(),
+            allowed_attempts: None,
+            show_correct_answers: true,
+            one_question_at_a_time: false,
+            cant_go_back: false,
+            access_code: None,
+        }
+    }
+}
+
+/// How many times a learner may take a quiz
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Attempts {
+    Limited(u32),
+    Unlimited,
+}
+
+/// Canvas quiz type; surveys have no correct answers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuizType {
+    #[default]
+    Assignment,
+    PracticeQuiz,
+    GradedSurvey,
+    Survey,
+}
+
+impl QuizType {
+    pub fn canvas_name(&self) -> &'static str {
+        match self {
+            QuizType::Assignment => "assignment",
+            QuizType::PracticeQuiz => "practice_quiz",
+            QuizType::GradedSurvey => "graded_survey",
+            QuizType::Survey => "survey",
+        }
+    }
+
+    pub fn is_survey(&self) -> bool {
+        matches!(self, QuizType::GradedSurvey | QuizType::Survey)
+    }
 }
 
 /// Represents an individual question/item
@@ -45,6 +361,12 @@ pub struct Question {
     pub points: f32,
     pub feedback: Option<Feedback>,
     pub solution: Option<String>,
+    pub passage: Option<String>, // Id of the shared passage shown before this question
+    pub group: Option<String>,   // Id of the question group this question is drawn from
+    pub tags: Vec<String>,       // Bank tags used to select questions
+    pub graded: bool,            // Ungraded (survey) questions get no scoring conditions
+    pub participation: bool,     // Graded-survey questions earn their points for any answer
+    pub bank_item: Option<String>, // Ident of the bank question this is a copy of
 }
 
 impl Question {
@@ -57,8 +379,19 @@ impl Question {
             points: 1.0,
             feedback: None,
             solution: None,
+            passage: None,
+            group: None,
+            tags: Vec::new(),
+            graded: true,
+            participation: false,
+            bank_item: None,
         }
     }
+
+    /// Whether the question is worth points: graded, or scored for participation
+    pub fn earns_points(&self) -> bool {
+        self.graded || self.participation
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -91,6 +424,22 @@ pub enum QuestionType {
     FileUpload {
         allowed_extensions: Vec<String>,
     },
+    MultipleDropdowns {
+        dropdowns: Vec<Dropdown>,
+    },
+    /// Ungraded informational text (Canvas `text_only_question`)
+    TextOnly,
+    /// Ungraded rating scale, e.g. Strongly disagree .. Strongly agree
+    Likert {
+        scale: Vec<Choice>,
+    },
+    Formula {
+        formula: String,
+        variables: Vec<FormulaVariable>,
+        answer_sets: Vec<FormulaAnswerSet>,
+        tolerance: f64,
+        decimals: u32, // Places the computed answer is rounded to
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,22 +463,102 @@ impl Choice {
     }
 }
 
+/// An inline select menu; the question text refers to it as `[name]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dropdown {
+    pub name: String,
+    pub choices: Vec<Choice>,
+}
+
+impl Dropdown {
+    pub fn new(name: impl Into<String>, choices: Vec<Choice>) -> Self {
+        Self {
+            name: name.into(),
+            choices,
+        }
+    }
+}
+
+/// A randomized variable referenced as `[name]` in a formula question
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormulaVariable {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub decimals: u32,
+}
+
+impl FormulaVariable {
+    pub fn new(name: impl Into<String>, min: f64, max: f64, decimals: u32) -> Self {
+        Self {
+            name: name.into(),
+            min,
+            max,
+            decimals,
+        }
+    }
+}
+
+/// One pre-generated combination of variable values and the resulting answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormulaAnswerSet {
+    pub values: Vec<(String, f64)>,
+    pub answer: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AcceptableAnswer {
     pub text: String,
     pub weight: f32, // 1.0 for fully correct, 0.5 for partial credit, etc.
+    pub answer_type: AnswerType, // Single for literal text, Pattern for a regex
 }
 
 impl AcceptableAnswer {
     pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
         Self {
-            text: text.into(),
+            answer_type: AnswerType::Single(text.clone()),
+            text,
+            weight: 1.0,
+        }
+    }
+
+    /// An answer accepting any response that fully matches `regex`
+    pub fn pattern(regex: impl Into<String>) -> Self {
+        let regex = regex.into();
+        Self {
+            answer_type: AnswerType::Pattern(regex.clone()),
+            text: regex,
             weight: 1.0,
         }
     }
+
+    /// Whether a learner's response earns this answer's credit
+    pub fn matches(&self, response: &str, case_sensitive: bool) -> bool {
+        match &self.answer_type {
+            AnswerType::Pattern(regex) => pattern::is_match(regex, response, case_sensitive),
+            AnswerType::Multiple(texts) => texts
+                .iter()
+                .any(|text| text_matches(text, response, case_sensitive)),
+            AnswerType::Range(min, max) => response
+                .trim()
+                .parse::<f64>()
+                .map(|value| *min <= value && value <= *max)
+                .unwrap_or(false),
+            AnswerType::Single(text) => text_matches(text, response, case_sensitive),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn text_matches(expected: &str, response: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        expected.trim() == response.trim()
+    } else {
+        expected.trim().to_lowercase() == response.trim().to_lowercase()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Feedback {
     pub correct: Option<String>,
     pub incorrect: Option<String>,
@@ -1,33 +1,308 @@
+use crate::canvas;
 use crate::error::{QtiError, Result};
+use crate::report::{child_path, visit, Severity, SourceMap, ValidationReport};
 use crate::schema::QtiSchema;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek};
 use std::path::Path;
 use xmltree::Element;
+use zip::ZipArchive;
 
 /// Validates QTI XML against schema
 pub struct Validator {
-    schema: QtiSchema,
+    schema: Schema,
+    warnings_as_errors: bool,
+    canvas_rules: bool,
+}
+
+/// A bundled schema is only parsed once something is validated against it
+enum Schema {
+    Bundled(fn() -> &'static QtiSchema),
+    Loaded(QtiSchema),
+}
+
+impl Schema {
+    fn get(&self) -> &QtiSchema {
+        match self {
+            Schema::Bundled(schema) => schema(),
+            Schema::Loaded(schema) => schema,
+        }
+    }
 }
 
 impl Validator {
     pub fn new() -> Self {
         Self {
-            schema: QtiSchema::qti_1_2_schema(),
+            schema: Schema::Bundled(QtiSchema::bundled_qti_1_2),
+            warnings_as_errors: false,
+            canvas_rules: false,
         }
     }
 
     pub fn from_xsd_file(path: impl AsRef<Path>) -> Result<Self> {
         Ok(Self {
-            schema: QtiSchema::from_xsd_file(path)?,
+            schema: Schema::Loaded(QtiSchema::from_xsd_file(path)?),
+            warnings_as_errors: false,
+            canvas_rules: false,
+        })
+    }
+
+    /// Validate against the QTI-Lite profile: items with one single-choice response
+    pub fn qti_lite() -> Self {
+        Self {
+            schema: Schema::Bundled(QtiSchema::bundled_qti_lite),
+            warnings_as_errors: false,
+            canvas_rules: false,
+        }
+    }
+
+    /// Validate against the bundled QTI 1.2 DTD, as legacy tooling did
+    pub fn with_dtd() -> Self {
+        Self {
+            schema: Schema::Bundled(QtiSchema::bundled_qti_1_2_dtd),
+            warnings_as_errors: false,
+            canvas_rules: false,
+        }
+    }
+
+    pub fn from_dtd_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self {
+            schema: Schema::Loaded(QtiSchema::from_dtd_file(path)?),
+            warnings_as_errors: false,
+            canvas_rules: false,
         })
     }
 
+    /// The QTI 1.2 XSD plus Canvas's import rules, which warn about content Canvas
+    /// would drop or mangle
+    pub fn canvas() -> Self {
+        Self::new().with_canvas_rules()
+    }
+
+    /// Also check documents against Canvas's import rules
+    pub fn with_canvas_rules(mut self) -> Self {
+        self.canvas_rules = true;
+        self
+    }
+
+    /// Fail on warnings as well as errors
+    pub fn with_warnings_as_errors(mut self) -> Self {
+        self.warnings_as_errors = true;
+        self
+    }
+
     pub fn validate_xml(&self, xml: &str) -> Result<()> {
-        let element = self.parse_xml(xml)?;
-        self.validate_element(&element)
+        self.report_xml(xml)?.into_result()
     }
 
     pub fn validate_element(&self, element: &Element) -> Result<()> {
-        self.schema.validate(element)
+        self.report_element(element).into_result()
+    }
+
+    /// Every schema violation in `xml`, located by element path and source line
+    pub fn report_xml(&self, xml: &str) -> Result<ValidationReport> {
+        let element = self.parse_xml(xml)?;
+        let mut report = self.new_report();
+        self.check(&element, &SourceMap::new(&element, xml), &mut report);
+        Ok(report)
+    }
+
+    /// Schema and completeness violations in `xml`, as the CLI reports them
+    pub fn full_report_xml(&self, xml: &str) -> Result<ValidationReport> {
+        let element = self.parse_xml(xml)?;
+        let source = SourceMap::new(&element, xml);
+        let mut report = self.new_report();
+        self.check(&element, &source, &mut report);
+        self.check_completeness(&element, &source, &mut report);
+        Ok(report)
+    }
+
+    /// Check a QTI zip package: its manifest, the files the manifest lists, identifier
+    /// uniqueness across manifest and items, and every QTI document it contains
+    pub fn report_package<R: Read + Seek>(&self, reader: R) -> Result<ValidationReport> {
+        let mut archive = ZipArchive::new(reader)?;
+        let mut report = self.new_report();
+        let files: Vec<String> = archive
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(String::from)
+            .collect();
+
+        let manifest_xml = match read_archive_file(&mut archive, MANIFEST) {
+            Ok(xml) => xml,
+            Err(message) => {
+                report.file_violation(MANIFEST, Severity::Error, "missing-manifest", message);
+                return Ok(report);
+            }
+        };
+        let manifest = match self.parse_xml(&manifest_xml) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                report.file_violation(MANIFEST, Severity::Error, "malformed-xml", e.to_string());
+                return Ok(report);
+            }
+        };
+
+        let mut manifest_report = self.new_report();
+        let source = SourceMap::new(&manifest, &manifest_xml);
+        let mut identifiers = HashMap::new();
+        let mut referenced = HashSet::new();
+        let mut documents = Vec::new();
+        if manifest.name != "manifest" {
+            manifest_report.error(
+                &source,
+                &manifest,
+                &manifest.name,
+                "wrong-root",
+                format!("Root element must be 'manifest', found '{}'", manifest.name),
+            );
+        }
+
+        let mut resource_ids = HashSet::new();
+        visit(&manifest, &manifest.name, &mut |element, path| {
+            if let Some(identifier) = element.attributes.get("identifier") {
+                resource_ids.insert(identifier.clone());
+                if let Some(first) = identifiers.insert(identifier.clone(), path.to_string()) {
+                    manifest_report.error(
+                        &source,
+                        element,
+                        path,
+                        "duplicate-identifier",
+                        format!("Identifier '{}' is also used by {}", identifier, first),
+                    );
+                }
+            }
+        });
+
+        visit(&manifest, &manifest.name, &mut |element, path| {
+            let href = element.attributes.get("href");
+            match element.name.as_str() {
+                "resource" | "file" => {
+                    let Some(href) = href.filter(|href| !href.contains("://")) else {
+                        return;
+                    };
+                    referenced.insert(href.clone());
+                    if !files.contains(href) {
+                        manifest_report.error(
+                            &source,
+                            element,
+                            path,
+                            "missing-file",
+                            format!("'{}' is not in the package", href),
+                        );
+                    } else if element.name == "resource"
+                        && element
+                            .attributes
+                            .get("type")
+                            .is_some_and(|t| t.starts_with("imsqti_xmlv1p2"))
+                        && !documents.iter().any(|(file, _)| file == href)
+                    {
+                        let identifier = element.attributes.get("identifier").cloned();
+                        documents.push((href.clone(), identifier));
+                    }
+                }
+                "dependency" => {
+                    let target = element.attributes.get("identifierref");
+                    if let Some(target) = target.filter(|t| !resource_ids.contains(*t)) {
+                        manifest_report.error(
+                            &source,
+                            element,
+                            path,
+                            "unresolved-dependency",
+                            format!("Dependency '{}' names no resource", target),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        });
+        report.merge(MANIFEST, manifest_report);
+
+        for file in files
+            .iter()
+            .filter(|file| *file != MANIFEST && !referenced.contains(*file))
+        {
+            report.file_violation(
+                file,
+                Severity::Warning,
+                "orphan-file",
+                "File is not listed in the manifest",
+            );
+        }
+
+        for (file, resource_id) in documents {
+            let xml = match read_archive_file(&mut archive, &file) {
+                Ok(xml) => xml,
+                Err(message) => {
+                    report.file_violation(&file, Severity::Error, "unreadable-file", message);
+                    continue;
+                }
+            };
+            let document = match self.parse_xml(&xml) {
+                Ok(document) => document,
+                Err(e) => {
+                    report.file_violation(&file, Severity::Error, "malformed-xml", e.to_string());
+                    continue;
+                }
+            };
+
+            let source = SourceMap::new(&document, &xml);
+            let mut document_report = self.new_report();
+            self.check(&document, &source, &mut document_report);
+            // Question bank documents hold an objectbank instead of an assessment
+            if document.get_child("assessment").is_some() {
+                self.check_completeness(&document, &source, &mut document_report);
+            }
+            visit(&document, &document.name, &mut |element, path| {
+                let Some(ident) = element.attributes.get("ident") else {
+                    return;
+                };
+                // Canvas names a bank after the resource that points to it
+                if !IDENTIFIED_ELEMENTS.contains(&element.name.as_str())
+                    || resource_id.as_ref() == Some(ident)
+                {
+                    return;
+                }
+                let location = format!("{}:{}", file, path);
+                if let Some(first) = identifiers.insert(ident.clone(), location) {
+                    document_report.error(
+                        &source,
+                        element,
+                        path,
+                        "duplicate-identifier",
+                        format!("Identifier '{}' is also used by {}", ident, first),
+                    );
+                }
+            });
+            report.merge(&file, document_report);
+        }
+
+        Ok(report)
+    }
+
+    pub fn validate_package<R: Read + Seek>(&self, reader: R) -> Result<()> {
+        self.report_package(reader)?.into_result()
+    }
+
+    pub fn report_element(&self, element: &Element) -> ValidationReport {
+        let mut report = self.new_report();
+        self.check(element, &SourceMap::empty(), &mut report);
+        report
+    }
+
+    /// Schema violations, plus Canvas warnings when that profile is on
+    fn check(&self, element: &Element, source: &SourceMap, report: &mut ValidationReport) {
+        self.schema.get().check(element, source, report);
+        if self.canvas_rules {
+            canvas::check(element, source, report);
+        }
+    }
+
+    fn new_report(&self) -> ValidationReport {
+        ValidationReport {
+            warnings_as_errors: self.warnings_as_errors,
+            ..ValidationReport::new()
+        }
     }
 
     fn parse_xml(&self, xml: &str) -> Result<Element> {
@@ -36,82 +311,187 @@ impl Validator {
     }
 
     pub fn validate_completeness(&self, element: &Element) -> Result<()> {
+        let mut report = self.new_report();
+        self.check_completeness(element, &SourceMap::empty(), &mut report);
+        report.into_result()
+    }
+
+    fn check_completeness(
+        &self,
+        element: &Element,
+        source: &SourceMap,
+        report: &mut ValidationReport,
+    ) {
+        let root = element.name.as_str();
         if element.name != "questestinterop" {
-            return Err(QtiError::ValidationError(format!(
-                "Root element must be 'questestinterop', found '{}'",
-                element.name
-            )));
+            report.error(
+                source,
+                element,
+                root,
+                "wrong-root",
+                format!(
+                    "Root element must be 'questestinterop', found '{}'",
+                    element.name
+                ),
+            );
+            return;
         }
 
-        let assessment = element
-            .get_child("assessment")
-            .ok_or_else(|| QtiError::ValidationError("Missing 'assessment' element".to_string()))?;
+        let Some(assessment) = element.get_child("assessment") else {
+            report.error(
+                source,
+                element,
+                root,
+                "missing-assessment",
+                "Missing 'assessment' element",
+            );
+            return;
+        };
+        let assessment_path = child_path(root, element, assessment);
 
-        let section = assessment
-            .get_child("section")
-            .ok_or_else(|| QtiError::ValidationError("Missing 'section' element".to_string()))?;
+        let Some(section) = assessment.get_child("section") else {
+            report.error(
+                source,
+                assessment,
+                &assessment_path,
+                "missing-section",
+                "Missing 'section' element",
+            );
+            return;
+        };
+        let section_path = child_path(&assessment_path, assessment, section);
 
-        let items: Vec<&Element> = section
-            .children
-            .iter()
-            .filter_map(|node| {
-                if let xmltree::XMLNode::Element(elem) = node {
-                    if elem.name == "item" {
-                        return Some(elem);
+        let mut items = Vec::new();
+        let bank_sections = self.collect_items(section, &section_path, source, report, &mut items);
+
+        if items.is_empty() && bank_sections == 0 {
+            report.error(
+                source,
+                assessment,
+                &assessment_path,
+                "empty-assessment",
+                "Assessment must contain at least one item",
+            );
+        }
+
+        for (path, item) in items {
+            self.check_item(item, &path, source, report);
+        }
+    }
+
+    /// Items of a section and its nested (question group) sections, with their
+    /// paths, returning how many sections draw from a question bank instead of
+    /// listing items
+    fn collect_items<'a>(
+        &self,
+        section: &'a Element,
+        path: &str,
+        source: &SourceMap,
+        report: &mut ValidationReport,
+        items: &mut Vec<(String, &'a Element)>,
+    ) -> usize {
+        let start = items.len();
+        let mut bank_sections = 0;
+        for node in &section.children {
+            if let xmltree::XMLNode::Element(elem) = node {
+                let elem_path = child_path(path, section, elem);
+                match elem.name.as_str() {
+                    "item" => items.push((elem_path, elem)),
+                    "section" => {
+                        bank_sections += self.collect_items(elem, &elem_path, source, report, items)
                     }
+                    _ => {}
                 }
-                None
-            })
-            .collect();
-
-        if items.is_empty() {
-            return Err(QtiError::ValidationError(
-                "Assessment must contain at least one item".to_string(),
-            ));
+            }
         }
 
-        // Validate each item
-        for item in items {
-            self.validate_item(item)?;
+        let selection = section
+            .get_child("selection_ordering")
+            .and_then(|ordering| ordering.get_child("selection"));
+        if selection.is_some_and(|s| s.get_child("sourcebank_ref").is_some()) {
+            return bank_sections + 1;
         }
 
-        Ok(())
+        let selection_number = selection
+            .and_then(|selection| selection.get_child("selection_number"))
+            .and_then(|number| number.get_text())
+            .map(|text| text.trim().parse::<usize>());
+        match selection_number {
+            Some(Ok(count)) if count <= items.len() - start => {}
+            Some(_) => report.error(
+                source,
+                section,
+                path,
+                "selection-too-large",
+                format!(
+                    "Section '{}' selects more items than it contains",
+                    section.attributes.get("ident").cloned().unwrap_or_default()
+                ),
+            ),
+            None => {}
+        }
+        bank_sections
     }
 
-    fn validate_item(&self, item: &Element) -> Result<()> {
+    fn check_item(
+        &self,
+        item: &Element,
+        path: &str,
+        source: &SourceMap,
+        report: &mut ValidationReport,
+    ) {
         if !item.attributes.contains_key("ident") {
-            return Err(QtiError::ValidationError(
-                "Item missing required 'ident' attribute".to_string(),
-            ));
-        }
-
-        let presentation = item.get_child("presentation").ok_or_else(|| {
-            QtiError::ValidationError("Item missing 'presentation' element".to_string())
-        })?;
-
-        let _resprocessing = item.get_child("resprocessing").ok_or_else(|| {
-            QtiError::ValidationError("Item missing 'resprocessing' element".to_string())
-        })?;
-
-        let material = presentation.get_child("material").ok_or_else(|| {
-            QtiError::ValidationError("Presentation missing 'material' element".to_string())
-        })?;
-
-        let mattext = material.get_child("mattext").ok_or_else(|| {
-            QtiError::ValidationError("Material missing 'mattext' element".to_string())
-        })?;
-
-        if mattext.get_text().is_none()
-            || mattext
-                .get_text()
-                .map(|s| s.to_string())
-                .unwrap_or_default()
-                .trim()
-                .is_empty()
-        {
-            return Err(QtiError::ValidationError(
-                "Question text cannot be empty".to_string(),
-            ));
+            report.error(
+                source,
+                item,
+                path,
+                "missing-ident",
+                "Item missing required 'ident' attribute",
+            );
+        }
+
+        let Some(presentation) = item.get_child("presentation") else {
+            report.error(
+                source,
+                item,
+                path,
+                "missing-presentation",
+                "Item missing 'presentation' element",
+            );
+            return;
+        };
+        let presentation_path = child_path(path, item, presentation);
+
+        match presentation.get_child("material") {
+            None => report.error(
+                source,
+                presentation,
+                &presentation_path,
+                "missing-material",
+                "Presentation missing 'material' element",
+            ),
+            Some(material) => {
+                let material_path = child_path(&presentation_path, presentation, material);
+                match material.get_child("mattext") {
+                    None => report.error(
+                        source,
+                        material,
+                        &material_path,
+                        "missing-mattext",
+                        "Material missing 'mattext' element",
+                    ),
+                    Some(mattext) if mattext.get_text().is_none_or(|t| t.trim().is_empty()) => {
+                        report.error(
+                            source,
+                            mattext,
+                            &child_path(&material_path, material, mattext),
+                            "empty-question-text",
+                            "Question text cannot be empty",
+                        )
+                    }
+                    Some(_) => {}
+                }
+            }
         }
 
         let has_response = presentation.get_child("response_lid").is_some()
@@ -119,16 +499,50 @@ impl Validator {
             || presentation.get_child("response_num").is_some()
             || presentation.get_child("response_grp").is_some();
 
-        if !has_response {
-            return Err(QtiError::ValidationError(
-                "Item presentation missing response element".to_string(),
-            ));
+        let has_resprocessing = item.get_child("resprocessing").is_some();
+
+        // Text-only items carry neither a response nor response processing
+        if !has_response && has_resprocessing {
+            report.error(
+                source,
+                presentation,
+                &presentation_path,
+                "missing-response",
+                "Item presentation missing response element",
+            );
         }
 
-        Ok(())
+        if has_response && !has_resprocessing {
+            report.error(
+                source,
+                item,
+                path,
+                "missing-resprocessing",
+                "Item missing 'resprocessing' element",
+            );
+        }
     }
 }
 
+/// Manifest file at the root of every IMS package
+const MANIFEST: &str = "imsmanifest.xml";
+
+/// QTI elements whose `ident` must be unique across a package
+const IDENTIFIED_ELEMENTS: [&str; 4] = ["assessment", "section", "item", "objectbank"];
+
+fn read_archive_file<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> std::result::Result<String, String> {
+    let mut file = archive
+        .by_name(name)
+        .map_err(|_| format!("'{}' is not in the package", name))?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .map_err(|e| format!("Could not read '{}': {}", name, e))?;
+    Ok(content)
+}
+
 impl Default for Validator {
     fn default() -> Self {
         Self::new()
@@ -174,4 +588,176 @@ mod tests {
             .validate_completeness(&element)
             .expect("Should be complete");
     }
+
+    #[test]
+    fn test_validate_multiple_dropdowns() {
+        let assessment = crate::Parser::new()
+            .parse("1. Roses are {*red|blue}, violets are {red|*blue}.")
+            .expect("Should parse");
+
+        let xml = Generator::new()
+            .with_canvas_extensions()
+            .generate(&assessment)
+            .expect("Should generate XML");
+        assert!(xml.contains("response_dropdown1"));
+        assert!(xml.contains("multiple_dropdowns_question"));
+
+        Validator::new()
+            .validate_xml(&xml)
+            .expect("Dropdown XML should be valid");
+    }
+
+    #[test]
+    fn test_validate_passage_items() {
+        let assessment = crate::Parser::new()
+            .parse("passage: Intro\nRead this.\n\n1. Question?\n* answer\n\ntext: Done.")
+            .expect("Should parse");
+
+        let xml = Generator::new()
+            .with_canvas_extensions()
+            .generate(&assessment)
+            .expect("Should generate XML");
+        assert_eq!(xml.matches("text_only_question").count(), 2);
+        assert!(xml.find("Read this.").unwrap() < xml.find("Question?").unwrap());
+
+        let validator = Validator::new();
+        validator.validate_xml(&xml).expect("Should be valid");
+        let element = Element::parse(xml.as_bytes()).expect("Should parse XML");
+        validator
+            .validate_completeness(&element)
+            .expect("Should be complete");
+    }
+
+    #[test]
+    fn test_validate_question_groups() {
+        let assessment = crate::Parser::new()
+            .parse(
+                "group: Review, pick 1, 2 points\n1. A?\n* a\n\n2. B?\n* b\nend group\n\n3. C?\n* c",
+            )
+            .expect("Should parse");
+
+        let xml = Generator::new()
+            .with_canvas_extensions()
+            .generate(&assessment)
+            .expect("Should generate XML");
+        assert!(xml.contains("<selection_number>1</selection_number>"));
+        assert!(xml.contains("<points_per_item>2</points_per_item>"));
+
+        let validator = Validator::new();
+        validator.validate_xml(&xml).expect("Should be valid");
+        let mut element = Element::parse(xml.as_bytes()).expect("Should parse XML");
+        validator
+            .validate_completeness(&element)
+            .expect("Should be complete");
+
+        let number = element
+            .get_mut_child("assessment")
+            .and_then(|a| a.get_mut_child("section"))
+            .and_then(|s| s.get_mut_child("section"))
+            .and_then(|g| g.get_mut_child("selection_ordering"))
+            .and_then(|o| o.get_mut_child("selection"))
+            .and_then(|s| s.get_mut_child("selection_number"))
+            .expect("Should have selection_number");
+        number.children = vec![xmltree::XMLNode::Text("3".to_string())];
+        assert!(validator.validate_completeness(&element).is_err());
+    }
+
+    #[test]
+    fn test_report_collects_every_violation() {
+        let mut question = Question::new(
+            "Pick one",
+            QuestionType::MultipleChoice {
+                choices: vec![Choice::new("a", true), Choice::new("b", false)],
+                shuffle: false,
+            },
+        );
+        question.id = "q3".to_string();
+        let mut assessment = Assessment::new("Report");
+        assessment.questions.push(question);
+        let xml = Generator::new().generate(&assessment).unwrap();
+        let xml = xml
+            .replace("<presentation>", "<presentation foo=\"x\">")
+            .replacen("<resprocessing>", "<resprocessing><bogus/>", 1)
+            .replace("rcardinality=\"Single\"", "rcardinality=\"Several\"");
+
+        let report = Validator::new().full_report_xml(&xml).unwrap();
+        let codes: Vec<_> = report.violations.iter().map(|v| v.code).collect();
+        assert_eq!(
+            codes,
+            [
+                "undeclared-attribute",
+                "invalid-attribute-value",
+                "unexpected-child"
+            ]
+        );
+        let warning = &report.violations[0];
+        assert!(warning
+            .path
+            .starts_with("questestinterop/assessment[@ident="));
+        assert!(warning.path.ends_with("/item[@ident='q3']/presentation"));
+        let line = xml
+            .lines()
+            .position(|l| l.contains("<presentation"))
+            .unwrap()
+            + 1;
+        assert_eq!(warning.line, Some(line));
+        assert!(!report.is_valid());
+
+        let xml = xml
+            .replace("rcardinality=\"Several\"", "rcardinality=\"Single\"")
+            .replacen("<bogus/>", "", 1);
+        assert!(Validator::new().validate_xml(&xml).is_ok());
+        assert!(Validator::new()
+            .with_warnings_as_errors()
+            .validate_xml(&xml)
+            .is_err());
+    }
+
+    #[test]
+    fn test_report_package() {
+        use std::io::{Cursor, Write};
+
+        let mut assessment = crate::Parser::new()
+            .parse("1. Pick one\n*a) x\nb) y")
+            .unwrap();
+        assessment.identifier = "quiz".to_string();
+        let package = crate::Exporter::new()
+            .with_canvas_extensions()
+            .export_to_memory(&assessment)
+            .unwrap();
+        let report = Validator::new()
+            .report_package(Cursor::new(&package))
+            .unwrap();
+        assert!(report.violations.is_empty(), "{}", report);
+
+        // Drop the quiz, add a stray file and point a dependency nowhere
+        let mut archive = ZipArchive::new(Cursor::new(&package)).unwrap();
+        let manifest = read_archive_file(&mut archive, MANIFEST).unwrap().replacen(
+            "</resource>",
+            "<dependency identifierref=\"gone\"/></resource>",
+            1,
+        );
+        let mut broken = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        broken.start_file(MANIFEST, options).unwrap();
+        broken.write_all(manifest.as_bytes()).unwrap();
+        broken.start_file("notes.txt", options).unwrap();
+        let broken = broken.finish().unwrap().into_inner();
+
+        let report = Validator::new()
+            .report_package(Cursor::new(broken))
+            .unwrap();
+        let codes: Vec<_> = report.violations.iter().map(|v| v.code).collect();
+        assert!(codes.contains(&"missing-file"));
+        assert!(codes.contains(&"unresolved-dependency"));
+        assert!(codes.contains(&"orphan-file"));
+        assert!(
+            report
+                .violations
+                .iter()
+                .all(|v| v.file.as_deref() == Some(MANIFEST)
+                    || v.file.as_deref() == Some("notes.txt"))
+        );
+        assert!(!report.is_valid());
+    }
 }